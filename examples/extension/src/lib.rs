@@ -0,0 +1,27 @@
+// An extension built against the C ABI of `lim-abi`, through the typed natives of `lim-native`. It
+// works with any lim binary speaking the same ABI version whatever the compiler either was built
+// with.
+use lim_native::{lim_native, LimError};
+
+lim_native::abi::lim_extension!();
+
+/// Adds up an array of integers.
+#[lim_native]
+pub fn sum(numbers: Vec<i64>) -> i64 {
+    numbers.iter().fold(0, |total, number| total.wrapping_add(*number))
+}
+
+/// Splits a string on whitespace.
+#[lim_native]
+pub fn words(text: String) -> Vec<String> {
+    text.split_whitespace().map(String::from).collect()
+}
+
+/// Divides two numbers, failing on a zero divisor.
+#[lim_native]
+pub fn divide(dividend: f64, divisor: f64) -> Result<f64, LimError> {
+    if divisor == 0.0 {
+        return Err(LimError::new("Cannot divide by zero"));
+    }
+    Ok(dividend / divisor)
+}
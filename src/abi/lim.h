@@ -0,0 +1,77 @@
+/* The C ABI lim native extensions are built against, mirrors src/abi/src/lib.rs.
+ *
+ * An extension is a shared library exporting lim_abi_version, checked when the library is loaded,
+ * and any number of natives of type lim_native, each optionally described by a lim_native_info.
+ * Values cross the boundary as opaque handles and are only created and read through the functions
+ * of the lim_api table passed to every native.
+ *
+ * Ownership: every handle a lim_api function returns is owned by the extension, which either
+ * releases it or returns it from the native. Handles passed to a native or to a lim_api function
+ * are borrowed and stay valid until the native returns.
+ */
+#ifndef LIM_H
+#define LIM_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#define LIM_ABI_VERSION 2
+
+typedef struct lim_value lim_value;
+
+/* What kind reports, values of any other type are LIM_OTHER. */
+#define LIM_NONE 0
+#define LIM_INTEGER 1
+#define LIM_FLOAT 2
+#define LIM_STRING 3
+#define LIM_ARRAY 4
+#define LIM_ERROR 5
+#define LIM_OTHER 6
+
+typedef struct lim_api {
+    uint32_t version;
+    uint32_t (*kind)(const lim_value *value);
+    lim_value *(*none)(void);
+    lim_value *(*integer)(int64_t integer);
+    /* float in Rust, a keyword in C. */
+    lim_value *(*float_)(double float_);
+    /* Strings are UTF-8 and not nul terminated, invalid sequences are replaced. */
+    lim_value *(*string)(const char *bytes, size_t length);
+    lim_value *(*error)(const char *bytes, size_t length);
+    lim_value *(*array)(const lim_value *const *elements, size_t length);
+    /* Returns 0 and leaves integer untouched when the value is not an integer. */
+    int32_t (*as_integer)(const lim_value *value, int64_t *integer);
+    /* Integers are read as floats too. */
+    int32_t (*as_float)(const lim_value *value, double *float_);
+    /* The bytes of a string or of an error message, NULL for any other value. They live as long
+     * as the handle. */
+    const char *(*as_string)(const lim_value *value, size_t *length);
+    const char *(*as_error)(const lim_value *value, size_t *length);
+    /* The string lim would print for any value. */
+    lim_value *(*display)(const lim_value *value);
+    /* 0 for any value but an array, array_get returns NULL past the end. */
+    size_t (*array_length)(const lim_value *value);
+    lim_value *(*array_get)(const lim_value *value, size_t index);
+    void (*release)(lim_value *value);
+} lim_api;
+
+/* A NULL result is read as None. */
+typedef lim_value *(*lim_native)(const lim_api *api, const lim_value *const *args, size_t count);
+
+/* Describes a native to the interpreter linking or loading it. A library exports the description of
+ * name as lim_native_<name>, native(library, name) prefers it to a bare name symbol. */
+typedef struct lim_native_info {
+    /* Both nul terminated, doc is empty when the native is undocumented. */
+    const char *name;
+    const char *doc;
+    /* The number of parameters, the least number of arguments when the native is variadic. */
+    size_t arity;
+    bool variadic;
+    lim_native function;
+} lim_native_info;
+
+/* Exports lim_abi_version from the extension including this header. */
+#define LIM_EXTENSION uint32_t lim_abi_version(void) { return LIM_ABI_VERSION; }
+
+#endif
@@ -0,0 +1,89 @@
+// The C ABI native extensions are built against, `lim.h` declares the same items for C.
+//
+// An extension is a shared library exporting `lim_abi_version`, checked when the library is loaded,
+// and any number of natives of type `LimNative`, each optionally described by a `LimNativeInfo`.
+// Values cross the boundary as opaque handles and are only created and read through the functions
+// of the `LimApi` table passed to every native.
+//
+// Ownership: every handle a `LimApi` function returns is owned by the extension, which either
+// releases it or returns it from the native. Handles passed to a native or to a `LimApi` function
+// are borrowed and stay valid until the native returns.
+use std::os::raw::c_char;
+
+// Bumped on any change to `LimApi`, `LimNative`, `LimNativeInfo` or the ownership rules.
+pub const LIM_ABI_VERSION: u32 = 2;
+
+#[repr(C)]
+pub struct LimValue {
+    _private: [u8; 0],
+}
+
+// What `LimApi::kind` reports, values of any other type are `LIM_OTHER`.
+pub const LIM_NONE: u32 = 0;
+pub const LIM_INTEGER: u32 = 1;
+pub const LIM_FLOAT: u32 = 2;
+pub const LIM_STRING: u32 = 3;
+pub const LIM_ARRAY: u32 = 4;
+pub const LIM_ERROR: u32 = 5;
+pub const LIM_OTHER: u32 = 6;
+
+#[repr(C)]
+pub struct LimApi {
+    pub version: u32,
+    pub kind: unsafe extern "C" fn(value: *const LimValue) -> u32,
+    pub none: unsafe extern "C" fn() -> *mut LimValue,
+    pub integer: unsafe extern "C" fn(integer: i64) -> *mut LimValue,
+    pub float: unsafe extern "C" fn(float: f64) -> *mut LimValue,
+    // Strings are UTF-8 and not nul terminated, invalid sequences are replaced.
+    pub string: unsafe extern "C" fn(bytes: *const c_char, length: usize) -> *mut LimValue,
+    pub error: unsafe extern "C" fn(bytes: *const c_char, length: usize) -> *mut LimValue,
+    pub array: unsafe extern "C" fn(elements: *const *const LimValue, length: usize) -> *mut LimValue,
+    // Returns 0 and leaves `integer` untouched when the value is not an integer.
+    pub as_integer: unsafe extern "C" fn(value: *const LimValue, integer: *mut i64) -> i32,
+    // Integers are read as floats too.
+    pub as_float: unsafe extern "C" fn(value: *const LimValue, float: *mut f64) -> i32,
+    // The bytes of a string or of an error message, null for any other value. They live as long as
+    // the handle.
+    pub as_string: unsafe extern "C" fn(value: *const LimValue, length: *mut usize) -> *const c_char,
+    pub as_error: unsafe extern "C" fn(value: *const LimValue, length: *mut usize) -> *const c_char,
+    // The string lim would print for any value.
+    pub display: unsafe extern "C" fn(value: *const LimValue) -> *mut LimValue,
+    // 0 for any value but an array, `array_get` returns null past the end.
+    pub array_length: unsafe extern "C" fn(value: *const LimValue) -> usize,
+    pub array_get: unsafe extern "C" fn(value: *const LimValue, index: usize) -> *mut LimValue,
+    pub release: unsafe extern "C" fn(value: *mut LimValue),
+}
+
+// A null result is read as None.
+pub type LimNative = unsafe extern "C" fn(api: *const LimApi, args: *const *const LimValue, count: usize) -> *mut LimValue;
+
+pub type LimAbiVersion = unsafe extern "C" fn() -> u32;
+
+// Describes a native to the interpreter linking or loading it. A library exports the description of
+// `name` as `lim_native_<name>`, `native(library, name)` prefers it to a bare `name` symbol.
+#[repr(C)]
+pub struct LimNativeInfo {
+    // Both nul terminated, `doc` is empty when the native is undocumented.
+    pub name: *const c_char,
+    pub doc: *const c_char,
+    // The number of parameters, the least number of arguments when the native is variadic.
+    pub arity: usize,
+    pub variadic: bool,
+    pub function: LimNative,
+}
+
+// Only ever built from string literals, the pointers are never written through.
+unsafe impl Sync for LimNativeInfo {}
+
+pub const LIM_NATIVE_PREFIX: &str = "lim_native_";
+
+// Exports `lim_abi_version` from the extension using this crate.
+#[macro_export]
+macro_rules! lim_extension {
+    () => {
+        #[no_mangle]
+        pub extern "C" fn lim_abi_version() -> u32 {
+            $crate::LIM_ABI_VERSION
+        }
+    };
+}
@@ -0,0 +1,220 @@
+use std::cmp::Ordering;
+use std::rc::Rc;
+
+use crate::interpreter::{call, is_truthy};
+use object::Object;
+
+pub const METHODS: [&str; 7] = ["push", "pop", "len", "map", "filter", "reduce", "sort"];
+
+pub fn position(index: i64, length: usize) -> Option<usize> {
+    let position = if index < 0 { index + length as i64 } else { index };
+    if position >= 0 && position < length as i64 {
+        Some(position as usize)
+    } else {
+        None
+    }
+}
+
+pub fn bound(index: &Object, length: usize, default: usize) -> Result<usize, Rc<Object>> {
+    match index {
+        Object::None => Ok(default),
+        Object::Integer(index) => {
+            let position = if *index < 0 { index + length as i64 } else { *index };
+            Ok(position.max(0).min(length as i64) as usize)
+        }
+        _ => Err(Rc::new(Object::Error(format!("Slice bound {} is not an Integer", index.to_string())))),
+    }
+}
+
+pub fn get(elements: &[Rc<Object>], index: &Rc<Object>) -> Rc<Object> {
+    if let Object::Integer(index) = **index {
+        match position(index, elements.len()) {
+            Some(position) => elements[position].to_owned(),
+            None => Rc::new(Object::Error(format!("Index {} out of range for array of length {}", index, elements.len()))),
+        }
+    } else {
+        Rc::new(Object::Error(format!("Array index {} is not an Integer", index.to_string())))
+    }
+}
+
+pub fn set(elements: &mut [Rc<Object>], index: &Rc<Object>, value: Rc<Object>) -> Rc<Object> {
+    if let Object::Integer(index) = **index {
+        match position(index, elements.len()) {
+            Some(position) => {
+                elements[position] = value;
+                Rc::new(Object::None)
+            }
+            None => Rc::new(Object::Error(format!("Index {} out of range for array of length {}", index, elements.len()))),
+        }
+    } else {
+        Rc::new(Object::Error(format!("Array index {} is not an Integer", index.to_string())))
+    }
+}
+
+pub fn slice(elements: &[Rc<Object>], start: &Rc<Object>, end: &Rc<Object>) -> Rc<Object> {
+    let start = match bound(start, elements.len(), 0) {
+        Ok(start) => start,
+        Err(error) => return error,
+    };
+    let end = match bound(end, elements.len(), elements.len()) {
+        Ok(end) => end,
+        Err(error) => return error,
+    };
+    if start < end {
+        Rc::new(Object::Array(elements[start..end].to_vec()))
+    } else {
+        Rc::new(Object::Array(vec![]))
+    }
+}
+
+fn compare(lhs: &Rc<Object>, rhs: &Rc<Object>) -> Option<Ordering> {
+    match (&**lhs, &**rhs) {
+        (Object::Integer(lhs), Object::Integer(rhs)) => Some(lhs.cmp(rhs)),
+        (Object::Integer(lhs), Object::Float(rhs)) => (*lhs as f64).partial_cmp(rhs),
+        (Object::Float(lhs), Object::Integer(rhs)) => lhs.partial_cmp(&(*rhs as f64)),
+        (Object::Float(lhs), Object::Float(rhs)) => lhs.partial_cmp(rhs),
+        (Object::String(lhs), Object::String(rhs)) => Some(lhs.cmp(rhs)),
+        _ => None,
+    }
+}
+
+// Callbacks may change or reallocate the array they are called on, so the methods calling them run
+// on a snapshot of the elements and only push and pop borrow the array mutably.
+pub fn call_method(name: &str, instance: &Rc<Object>, args: &[Rc<Object>]) -> Rc<Object> {
+    match name {
+        "push" | "pop" => return mutate(name, instance, args),
+        "sort" => return sort(instance, args),
+        _ => {}
+    }
+    let elements = match &**instance {
+        Object::Array(elements) => elements.to_vec(),
+        _ => unreachable!(),
+    };
+    match name {
+        "len" => Rc::new(Object::Integer(elements.len() as i64)),
+        "map" => match args.first() {
+            Some(function) => Rc::new(Object::Array(elements.iter().map(|element| call(function, &vec![element.to_owned()], function)).collect())),
+            None => Rc::new(Object::Error(String::from("map expects a function"))),
+        },
+        "filter" => match args.first() {
+            Some(function) => Rc::new(Object::Array(elements.into_iter().filter(|element| is_truthy(&call(function, &vec![element.to_owned()], function))).collect())),
+            None => Rc::new(Object::Error(String::from("filter expects a function"))),
+        },
+        "reduce" => match args.first() {
+            Some(function) => {
+                let mut remaining = elements.into_iter();
+                let initial = match args.get(1) {
+                    Some(initial) => Some(initial.to_owned()),
+                    None => remaining.next(),
+                };
+                match initial {
+                    Some(initial) => remaining.fold(initial, |accumulator, element| call(function, &vec![accumulator, element], function)),
+                    None => Rc::new(Object::Error(String::from("Cannot reduce an empty array without an initial value"))),
+                }
+            }
+            None => Rc::new(Object::Error(String::from("reduce expects a function"))),
+        },
+        _ => Rc::new(Object::Error(format!("Array has no method {}", name))),
+    }
+}
+
+fn mutate(name: &str, instance: &Rc<Object>, args: &[Rc<Object>]) -> Rc<Object> {
+    let mut array = instance.to_owned();
+    let elements = match unsafe { Rc::get_mut_unchecked(&mut array) } {
+        Object::Array(elements) => elements,
+        _ => unreachable!(),
+    };
+    match name {
+        "push" => {
+            elements.extend(args.iter().cloned());
+            Rc::new(Object::None)
+        }
+        _ => elements.pop().unwrap_or_else(|| Rc::new(Object::Error(String::from("Cannot pop from an empty array")))),
+    }
+}
+
+// The keys are computed on a snapshot, the sorted snapshot then replaces the elements.
+fn sort(instance: &Rc<Object>, args: &[Rc<Object>]) -> Rc<Object> {
+    let elements = match &**instance {
+        Object::Array(elements) => elements.to_vec(),
+        _ => unreachable!(),
+    };
+    let keys: Vec<Rc<Object>> = match args.first() {
+        Some(function) => elements.iter().map(|element| call(function, &vec![element.to_owned()], function)).collect(),
+        None => elements.to_vec(),
+    };
+    for pair in keys.windows(2) {
+        if compare(&pair[0], &pair[1]).is_none() {
+            return Rc::new(Object::Error(format!("Cannot compare {} and {}", pair[0].to_string(), pair[1].to_string())));
+        }
+    }
+    let mut order: Vec<usize> = (0..elements.len()).collect();
+    order.sort_by(|lhs, rhs| compare(&keys[*lhs], &keys[*rhs]).unwrap_or(Ordering::Equal));
+    let sorted = order.iter().map(|index| elements[*index].to_owned()).collect();
+    let mut array = instance.to_owned();
+    if let Object::Array(elements) = unsafe { Rc::get_mut_unchecked(&mut array) } {
+        *elements = sorted;
+    }
+    Rc::new(Object::None)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::run;
+
+    const ITEMS: &str = "items = [10, 20, 30, 40]\n";
+
+    #[test]
+    fn arrays_index_from_either_end() {
+        assert_eq!(run(&format!("{}[items[0], items[3], items[-1], items[-4]]", ITEMS)), "[10, 40, 40, 10]");
+        assert_eq!(run(&format!("{}items[4]", ITEMS)), "Error(\"Index 4 out of range for array of length 4\")");
+        assert_eq!(run(&format!("{}items[-5]", ITEMS)), "Error(\"Index -5 out of range for array of length 4\")");
+        assert_eq!(run(&format!("{}items['a']", ITEMS)), "Error(\"Array index a is not an Integer\")");
+    }
+
+    #[test]
+    fn arrays_assign_through_an_index() {
+        assert_eq!(run(&format!("{}items[1] = 2\nitems[-1] = 4\nitems", ITEMS)), "[10, 2, 30, 4]");
+        assert_eq!(run(&format!("{}(items[4] = 1)", ITEMS)), "Error(\"Index 4 out of range for array of length 4\")");
+    }
+
+    // Slice bounds are clamped to the array, a slice never fails on its range.
+    #[test]
+    fn arrays_slice() {
+        assert_eq!(run(&format!("{}[items[1:3], items[:2], items[2:], items[:]]", ITEMS)), "[[20, 30], [10, 20], [30, 40], [10, 20, 30, 40]]");
+        assert_eq!(run(&format!("{}[items[-2:], items[:-3], items[-10:10], items[3:1]]", ITEMS)), "[[30, 40], [10], [10, 20, 30, 40], []]");
+        assert_eq!(run(&format!("{}items[1:'a']", ITEMS)), "Error(\"Slice bound a is not an Integer\")");
+    }
+
+    #[test]
+    fn array_methods() {
+        assert_eq!(run(&format!("{}[items.push(50), items.len(), items.pop(), items]", ITEMS)), "[None, 5, 50, [10, 20, 30, 40]]");
+        assert_eq!(run("[[3, 1, 2].map((x) { x * 2 }), [3, 1, 2].filter((x) { x - 1 }), [1, 2, 3].reduce((sum, x) { sum + x }, 0)]"), "[[6, 2, 4], [3, 2], 6]");
+        assert_eq!(run("items = [3, 1, 2]\nitems.sort()\nitems"), "[1, 2, 3]");
+        assert_eq!(run("[].pop()"), "Error(\"Cannot pop from an empty array\")");
+        assert_eq!(run("[].shift()"), "Error(\"Array has no method shift\")");
+    }
+
+    // Callbacks run on a snapshot of the array, changing the array from them is seen afterwards.
+    #[test]
+    fn array_callbacks_can_change_their_array() {
+        let code = "class Bag {
+  items = [3, 1, 2]
+  grow = (x) {
+    self.items.push(x * 10)
+    x
+  }
+  shrink = (x) {
+    self.items.pop()
+    x
+  }
+}
+bag = Bag()
+mapped = bag.items.map(bag.grow)
+bag.items.sort(bag.grow)
+total = bag.items.reduce((sum, x) { sum + x }, 0)
+filtered = bag.items.filter(bag.shrink)
+[mapped, total, filtered, bag.items]";
+        assert_eq!(run(code), "[[3, 1, 2], 66, [1, 2, 3, 10, 20, 30], []]");
+    }
+}
@@ -0,0 +1,120 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::map;
+use crate::module;
+use object::{Object, Table};
+
+pub const PREFIXES: [&str; 2] = ["f", "set"];
+
+pub fn namespaces() -> Vec<(String, Rc<Object>)> {
+    let mut float = HashMap::new();
+    float.insert(String::from("inf"), Rc::new(Object::Float(f64::INFINITY)));
+    float.insert(String::from("nan"), Rc::new(Object::Float(f64::NAN)));
+    float.insert(String::from("epsilon"), Rc::new(Object::Float(f64::EPSILON)));
+    float.insert(String::from("max"), Rc::new(Object::Float(f64::MAX)));
+    float.insert(String::from("min"), Rc::new(Object::Float(f64::MIN)));
+    let mut integer = HashMap::new();
+    integer.insert(String::from("max"), Rc::new(Object::Integer(i64::MAX)));
+    integer.insert(String::from("min"), Rc::new(Object::Integer(i64::MIN)));
+    vec![(String::from("Float"), Rc::new(Object::Class(float))), (String::from("Integer"), Rc::new(Object::Class(integer)))]
+}
+
+pub fn call(name: &str, args: &[Rc<Object>]) -> Rc<Object> {
+    match name {
+        "import" => match args.first().map(|arg| &**arg) {
+            Some(Object::String(name)) => module::import(name),
+            _ => Rc::new(Object::Error(String::from("import expects a module name"))),
+        },
+        "native" => match (args.first().map(|arg| &**arg), args.get(1).map(|arg| &**arg)) {
+            (Some(Object::String(library)), Some(Object::String(symbol))) => Rc::new(Object::Native(library.to_owned(), symbol.to_owned())),
+            _ => Rc::new(Object::Error(String::from("native expects a library path and a symbol name"))),
+        },
+        "f" => match (args.first().map(|arg| &**arg), args.get(1).map(|arg| &**arg)) {
+            (Some(Object::Array(strings)), Some(Object::Array(values))) => {
+                let mut string = String::new();
+                for (index, part) in strings.iter().enumerate() {
+                    string.push_str(&part.to_string());
+                    if let Some(value) = values.get(index) {
+                        string.push_str(&value.to_string());
+                    }
+                }
+                Rc::new(Object::String(string))
+            }
+            _ => Rc::new(Object::Error(String::from("f expects an array of strings and an array of values"))),
+        },
+        "set" => {
+            let mut table = Table::new();
+            let elements = match args.first().map(|arg| &**arg) {
+                None => vec![],
+                Some(Object::Array(elements)) => elements.to_vec(),
+                Some(Object::Set(elements)) => elements.keys().cloned().collect(),
+                Some(Object::Map(entries)) => entries.keys().cloned().collect(),
+                Some(other) => return Rc::new(Object::Error(format!("Cannot build a set from {}", other.to_string()))),
+            };
+            for element in elements {
+                if let Err(error) = map::insert(&mut table, element, Rc::new(Object::None)) {
+                    return error;
+                }
+            }
+            Rc::new(Object::Set(table))
+        }
+        _ => Rc::new(Object::Error(format!("Unknown builtin {}", name))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::run;
+
+    #[test]
+    fn f_strings_interpolate() {
+        assert_eq!(run("name = 'lim'\n[f'Hello {name}', f'{name}', f'']"), "[Hello lim, lim, ]");
+        assert_eq!(run("name = 'lim'\n[f'{1 + 2}', f'{[1, 2][1]}', f'{ {\"a\": 1}[\"a\"] }', f'{f\"{name}!\"}']"), "[3, 2, 1, lim!]");
+    }
+
+    #[test]
+    fn f_strings_escape_braces() {
+        assert_eq!(run("name = 'lim'\n[f'{{name}}', f'{{{name}}}', f'{{ {name} }}']"), "[{name}, {lim}, { lim }]");
+    }
+
+    // A prefix other than `f` is called with the strings around the embedded values and the values.
+    #[test]
+    fn template_prefixes_get_strings_and_values() {
+        assert_eq!(run("tag = (strings, values) { [strings, values] }\ntag'a{1}b{2}c'"), "[[a, b, c], [1, 2]]");
+    }
+
+    #[test]
+    fn square_brackets_after_a_prefix_build_a_value() {
+        assert_eq!(run("[set[1, 2, 2], set[]]"), "[set[1, 2], set[]]");
+        assert_eq!(run("set[[1]]"), "Error(\"[1] is not hashable\")");
+        assert_eq!(run("f[]"), "Error(\"f expects an array of strings and an array of values\")");
+    }
+
+    // `self.$prefix` makes the class a prefix, its instances are still indexed through `$get` and `$set`.
+    #[test]
+    fn classes_are_prefixes_but_not_their_instances() {
+        let code = "class Bag {
+  items = []
+  self.$prefix = (elements) {
+    bag = self()
+    bag.items = elements
+    bag
+  }
+  $get = (index) { self.items[index] * 10 }
+  $set = (index, value) { self.items[index] = value }
+}
+bag = Bag[1, 2, 3]
+bag[0] = 4
+[bag.items, bag[0], bag[-1]]";
+        assert_eq!(run(code), "[[4, 2, 3], 40, 30]");
+        let code = "class Point {
+  x = 0
+  $prefix = (elements) { 'built' }
+  $get = (index) { self.x + index }
+}
+point = Point()
+[point[1], f'{point[2]}']";
+        assert_eq!(run(code), "[1, 2]");
+    }
+}
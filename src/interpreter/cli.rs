@@ -0,0 +1,137 @@
+use std::path::PathBuf;
+
+pub const USAGE: &str = "Usage:
+    lim run [<file>] [<args>...]   Run a file, or the entry point of the project in lim.toml
+    lim <file>.lim [<args>...]     Same as lim run <file>
+    lim -e <code> [<args>...]      Run code given on the command line
+    lim - [<args>...]              Run code read from stdin
+    lim repl [<args>...]           Start an interactive session, the default in a terminal
+    lim tokens <file>              Print the tokens of a file as JSON, - reads stdin
+    lim ast <file>                 Print the syntax tree of a file as JSON, - reads stdin
+    lim fmt [--check] <files>...   Format files in place, --check only lists the unformatted ones
+    lim check [--json] <files>...  Report likely mistakes, configured by the [lint] table of lim.toml
+    lim compare <files>...         Run files with both engines and report the results that differ
+    lim bench <files>...           Time files with both engines, --runs <n> before the files sets the runs
+    lim lsp                        Serve the language server protocol on stdin and stdout
+    lim help                       Show this message
+
+Arguments following the program are available to it as the `args` array. Programs run on a bytecode
+virtual machine, LIM_ENGINE=tree runs them with the tree-walking interpreter instead.";
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Run(Option<PathBuf>, Vec<String>),
+    Eval(String, Vec<String>),
+    Stdin(Vec<String>),
+    Repl(Vec<String>),
+    Tokens(PathBuf),
+    Ast(PathBuf),
+    Fmt(Vec<PathBuf>, bool),
+    Check(Vec<PathBuf>, bool),
+    Compare(Vec<PathBuf>),
+    Bench(Vec<PathBuf>, usize),
+    Lsp,
+    Help,
+}
+
+pub fn parse(args: &[String]) -> Result<Command, String> {
+    let rest = |from: usize| args.iter().skip(from).cloned().collect::<Vec<String>>();
+    match args.first().map(|arg| arg.as_str()) {
+        None => Ok(Command::Run(None, vec![])),
+        Some("run") => match args.get(1) {
+            Some(file) => Ok(Command::Run(Some(PathBuf::from(file)), rest(2))),
+            None => Ok(Command::Run(None, vec![])),
+        },
+        Some("-e") | Some("--eval") => match args.get(1) {
+            Some(code) => Ok(Command::Eval(code.to_owned(), rest(2))),
+            None => Err(String::from("-e expects code to run")),
+        },
+        Some("-") => Ok(Command::Stdin(rest(1))),
+        Some("repl") => Ok(Command::Repl(rest(1))),
+        Some(command @ "tokens") | Some(command @ "ast") => match args.get(1) {
+            Some(file) if command == "tokens" => Ok(Command::Tokens(PathBuf::from(file))),
+            Some(file) => Ok(Command::Ast(PathBuf::from(file))),
+            None => Err(format!("{} expects a file", command)),
+        },
+        Some(command @ "fmt") | Some(command @ "check") => {
+            let flag = if command == "fmt" { "--check" } else { "--json" };
+            let flagged = args.iter().skip(1).any(|arg| arg == flag);
+            let files: Vec<PathBuf> = args.iter().skip(1).filter(|arg| *arg != flag).map(PathBuf::from).collect();
+            if files.is_empty() {
+                Err(format!("{} expects files", command))
+            } else if command == "fmt" {
+                Ok(Command::Fmt(files, flagged))
+            } else {
+                Ok(Command::Check(files, flagged))
+            }
+        }
+        Some("compare") if args.len() > 1 => Ok(Command::Compare(rest(1).into_iter().map(PathBuf::from).collect())),
+        Some("compare") => Err(String::from("compare expects files")),
+        Some("bench") => {
+            let (runs, files) = match args.get(1).map(|arg| arg.as_str()) {
+                Some("--runs") => match args.get(2).and_then(|runs| runs.parse::<usize>().ok()) {
+                    Some(runs) if runs > 0 => (runs, rest(3)),
+                    _ => return Err(String::from("--runs expects a positive number")),
+                },
+                _ => (5, rest(1)),
+            };
+            if files.is_empty() {
+                Err(String::from("bench expects files"))
+            } else {
+                Ok(Command::Bench(files.into_iter().map(PathBuf::from).collect(), runs))
+            }
+        }
+        Some("lsp") => Ok(Command::Lsp),
+        Some("help") | Some("-h") | Some("--help") => Ok(Command::Help),
+        Some(file) if file.ends_with(".lim") => Ok(Command::Run(Some(PathBuf::from(file)), rest(1))),
+        Some(command) => Err(format!("Unknown command {}", command)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::{parse, Command};
+
+
+    fn command(line: &str) -> Result<Command, String> {
+        parse(&line.split_whitespace().map(String::from).collect::<Vec<String>>())
+    }
+
+    #[test]
+    fn cli_commands() {
+        let strings = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<String>>();
+        let paths = |files: &[&str]| files.iter().map(PathBuf::from).collect::<Vec<PathBuf>>();
+        assert_eq!(command(""), Ok(Command::Run(None, vec![])));
+        assert_eq!(command("run"), Ok(Command::Run(None, vec![])));
+        assert_eq!(command("run main.lim a b"), Ok(Command::Run(Some(PathBuf::from("main.lim")), strings(&["a", "b"]))));
+        assert_eq!(command("main.lim --flag"), Ok(Command::Run(Some(PathBuf::from("main.lim")), strings(&["--flag"]))));
+        assert_eq!(command("-e 1 a"), Ok(Command::Eval(String::from("1"), strings(&["a"]))));
+        assert_eq!(command("--eval 1"), Ok(Command::Eval(String::from("1"), vec![])));
+        assert_eq!(command("- a"), Ok(Command::Stdin(strings(&["a"]))));
+        assert_eq!(command("repl a"), Ok(Command::Repl(strings(&["a"]))));
+        assert_eq!(command("tokens a.lim"), Ok(Command::Tokens(PathBuf::from("a.lim"))));
+        assert_eq!(command("ast -"), Ok(Command::Ast(PathBuf::from("-"))));
+        assert_eq!(command("fmt a.lim --check b.lim"), Ok(Command::Fmt(paths(&["a.lim", "b.lim"]), true)));
+        assert_eq!(command("fmt a.lim"), Ok(Command::Fmt(paths(&["a.lim"]), false)));
+        assert_eq!(command("check --json a.lim"), Ok(Command::Check(paths(&["a.lim"]), true)));
+        assert_eq!(command("compare a.lim b.lim"), Ok(Command::Compare(paths(&["a.lim", "b.lim"]))));
+        assert_eq!(command("bench a.lim"), Ok(Command::Bench(paths(&["a.lim"]), 5)));
+        assert_eq!(command("bench --runs 2 a.lim"), Ok(Command::Bench(paths(&["a.lim"]), 2)));
+        assert_eq!(command("lsp"), Ok(Command::Lsp));
+        assert_eq!(command("--help"), Ok(Command::Help));
+    }
+
+    #[test]
+    fn cli_errors() {
+        assert_eq!(command("-e"), Err(String::from("-e expects code to run")));
+        assert_eq!(command("tokens"), Err(String::from("tokens expects a file")));
+        assert_eq!(command("fmt --check"), Err(String::from("fmt expects files")));
+        assert_eq!(command("check"), Err(String::from("check expects files")));
+        assert_eq!(command("compare"), Err(String::from("compare expects files")));
+        assert_eq!(command("bench --runs 0 a.lim"), Err(String::from("--runs expects a positive number")));
+        assert_eq!(command("bench --runs 2"), Err(String::from("bench expects files")));
+        assert_eq!(command("main.py"), Err(String::from("Unknown command main.py")));
+    }
+}
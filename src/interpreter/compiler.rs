@@ -0,0 +1,542 @@
+use std::rc::Rc;
+
+use crate::interpreter::{number, parameter_name};
+use crate::resolver::{intern, resolve_class, resolve_function, resolve_module, Frame, Resolved, Symbol};
+use object::Object;
+use parser::node::Node;
+
+// Jump operands are positions in `code`, `fail` operands point at the code run when a pattern does not fit.
+#[derive(Clone, Copy, Debug)]
+pub enum Op {
+    Constant(usize),
+    None,
+    Load(usize),
+    Global(Symbol),
+    Store(usize),
+    Pop,
+    Dup,
+    Get(Symbol),
+    SetMember(Symbol),
+    Operate(&'static str),
+    Compound(&'static str),
+    Negate,
+    Array(usize),
+    Append,
+    Extend(&'static str, usize, usize),
+    Map,
+    Insert(usize),
+    Set,
+    Add(usize),
+    JumpUnlessPrefix(usize),
+    Prefix(usize),
+    GetIndex,
+    GetSlice,
+    SetIndex,
+    CurrentIndex(usize),
+    Call(usize),
+    CallNamed(usize),
+    Jump(usize),
+    Matches(usize),
+    Default(usize, usize),
+    Class(usize),
+    Mark,
+    Reset,
+    Raise,
+    Assign,
+    Bind(usize),
+    BindMember(Symbol, usize),
+    Payload(usize),
+    BindIndex,
+    Destructure { elements: usize, rest: Option<usize>, many: bool, fail: usize },
+    DestructureMap(usize),
+    Lookup(usize),
+    Expect(usize),
+    Throw(usize),
+    ReturnIfError,
+    Return,
+}
+
+// `slots` holds the name of every slot and None for temporaries, `parameters` the slot of each parameter.
+// Class bodies are compiled to their own chunks, function bodies when they are first called.
+#[derive(Debug, Default)]
+pub struct Chunk {
+    pub code: Vec<Op>,
+    pub constants: Vec<Rc<Object>>,
+    pub slots: Vec<Option<Symbol>>,
+    pub arguments: Vec<Vec<Symbol>>,
+    pub classes: Vec<Rc<Chunk>>,
+    pub parameters: Vec<(Node, Option<usize>)>,
+}
+
+fn operator(operator: &str) -> &'static str {
+    match operator {
+        "+" => "+",
+        "-" => "-",
+        "*" => "*",
+        "/" => "/",
+        "%" => "%",
+        _ => unreachable!(),
+    }
+}
+
+fn error(message: String) -> Rc<Object> {
+    Rc::new(Object::Error(message))
+}
+
+struct Compiler {
+    chunk: Chunk,
+    frame: Frame,
+}
+
+impl Compiler {
+    fn new(frame: Frame) -> Compiler {
+        Compiler { chunk: Chunk::default(), frame }
+    }
+
+    fn emit(&mut self, op: Op) -> usize {
+        self.chunk.code.push(op);
+        self.chunk.code.len() - 1
+    }
+
+    fn here(&self) -> usize {
+        self.chunk.code.len()
+    }
+
+    // Jumps are emitted before their target is known and patched once it is.
+    fn patch(&mut self, at: usize) {
+        let target = self.here();
+        self.chunk.code[at] = match self.chunk.code[at] {
+            Op::Extend(into, below, _) => Op::Extend(into, below, target),
+            Op::Insert(_) => Op::Insert(target),
+            Op::Add(_) => Op::Add(target),
+            Op::JumpUnlessPrefix(_) => Op::JumpUnlessPrefix(target),
+            Op::CurrentIndex(_) => Op::CurrentIndex(target),
+            Op::Jump(_) => Op::Jump(target),
+            Op::Matches(_) => Op::Matches(target),
+            Op::Default(slot, _) => Op::Default(slot, target),
+            Op::BindMember(name, _) => Op::BindMember(name, target),
+            Op::Payload(_) => Op::Payload(target),
+            Op::Destructure { elements, rest, many, .. } => Op::Destructure { elements, rest, many, fail: target },
+            Op::DestructureMap(_) => Op::DestructureMap(target),
+            Op::Lookup(_) => Op::Lookup(target),
+            Op::Expect(_) => Op::Expect(target),
+            Op::Throw(_) => Op::Throw(target),
+            op => op,
+        };
+    }
+
+    fn constant(&mut self, object: Rc<Object>) {
+        self.chunk.constants.push(object);
+        let index = self.chunk.constants.len() - 1;
+        self.emit(Op::Constant(index));
+    }
+
+    // The resolver declared every name the frame binds before compiling it.
+    fn slot(&mut self, name: &str) -> usize {
+        self.frame.declare(intern(name))
+    }
+
+    fn finish(mut self) -> Chunk {
+        self.chunk.slots = self.frame.slots;
+        self.chunk
+    }
+
+    fn statements(&mut self, statements: &[Node]) {
+        for statement in statements.iter() {
+            self.expression(statement);
+            self.emit(Op::Pop);
+        }
+        self.emit(Op::None);
+    }
+
+    // A spread that is not an array makes the whole expression an error, `below` counts the values
+    // of that expression already on the stack under the array.
+    fn spread(&mut self, elements: &[&Node], into: &'static str, below: usize) -> Vec<usize> {
+        self.emit(Op::Array(0));
+        let mut exits = vec![];
+        for element in elements.iter() {
+            match element {
+                Node::Variadic(spread) => {
+                    self.expression(spread);
+                    exits.push(self.emit(Op::Extend(into, below, 0)));
+                }
+                element => {
+                    self.expression(element);
+                    self.emit(Op::Append);
+                }
+            }
+        }
+        exits
+    }
+
+    fn elements(&mut self, elements: &[Node]) {
+        if elements.iter().all(|element| !matches!(element, Node::Variadic(_))) {
+            elements.iter().for_each(|element| self.expression(element));
+            self.emit(Op::Array(elements.len()));
+            return;
+        }
+        let elements: Vec<&Node> = elements.iter().collect();
+        let exits = self.spread(&elements, "into an array", 0);
+        exits.into_iter().for_each(|exit| self.patch(exit));
+    }
+
+    fn index(&mut self, target: &Node, index: &Node) {
+        self.expression(target);
+        let normal = self.emit(Op::JumpUnlessPrefix(0));
+        let elements = match index {
+            Node::Empty => vec![],
+            Node::FunctionArgs(elements) => elements.iter().filter(|element| **element != Node::Empty).collect(),
+            element => vec![element],
+        };
+        elements.iter().for_each(|element| self.expression(element));
+        self.emit(Op::Array(elements.len()));
+        self.emit(Op::Prefix(1));
+        let end = self.emit(Op::Jump(0));
+        self.patch(normal);
+        match index {
+            Node::Slice(start, end) => {
+                self.expression(start);
+                self.expression(end);
+                self.emit(Op::GetSlice);
+            }
+            index => {
+                self.expression(index);
+                self.emit(Op::GetIndex);
+            }
+        }
+        self.patch(end);
+    }
+
+    fn call(&mut self, callee: &Node, args: &[Node]) {
+        self.expression(callee);
+        let named = |arg: &Node| matches!(arg, Node::Assignment(name, _) if matches!(**name, Node::Identifier(_)));
+        if args.iter().all(|arg| !named(arg) && !matches!(arg, Node::Variadic(_))) {
+            args.iter().for_each(|arg| self.expression(arg));
+            self.emit(Op::Call(args.len()));
+            return;
+        }
+        let positional: Vec<&Node> = args.iter().filter(|arg| !named(arg)).collect();
+        let exits = self.spread(&positional, "as arguments", 1);
+        let mut names = vec![];
+        for arg in args.iter() {
+            if let Node::Assignment(name, value) = arg {
+                if let Node::Identifier(name) = &**name {
+                    names.push(intern(name));
+                    self.expression(value);
+                }
+            }
+        }
+        self.chunk.arguments.push(names);
+        self.emit(Op::CallNamed(self.chunk.arguments.len() - 1));
+        exits.into_iter().for_each(|exit| self.patch(exit));
+    }
+
+    // Leaves the value of the pattern on the stack consumed, the targets it binds pending until `Assign`.
+    fn pattern(&mut self, pattern: &Node, fails: &mut Vec<usize>) {
+        match pattern {
+            Node::Identifier(name) => {
+                let slot = self.slot(name);
+                self.emit(Op::Bind(slot));
+            }
+            Node::Member(instance, name) => {
+                self.expression(instance);
+                fails.push(self.emit(Op::BindMember(intern(name), 0)));
+            }
+            Node::Index(target, index) => {
+                self.expression(target);
+                self.expression(index);
+                self.emit(Op::BindIndex);
+            }
+            Node::Call(variant, elements) => {
+                self.expression(variant);
+                fails.push(self.emit(Op::Payload(0)));
+                self.elements_pattern(elements, fails);
+            }
+            Node::Parenthesized(pattern) => self.pattern(pattern, fails),
+            Node::Tuple(elements) | Node::Array(elements) => self.elements_pattern(elements, fails),
+            Node::Map(entries) => {
+                fails.push(self.emit(Op::DestructureMap(0)));
+                for entry in entries.iter() {
+                    if let Node::KeyValue(key, pattern) = entry {
+                        self.emit(Op::Dup);
+                        self.expression(key);
+                        fails.push(self.emit(Op::Lookup(0)));
+                        self.pattern(pattern, fails);
+                    }
+                }
+                self.emit(Op::Pop);
+            }
+            Node::Literal(_, _) | Node::Number(_) | Node::Negation(_) => {
+                self.expression(pattern);
+                fails.push(self.emit(Op::Expect(0)));
+            }
+            pattern => {
+                self.constant(error(format!("Invalid pattern {:?}", pattern)));
+                fails.push(self.emit(Op::Throw(0)));
+            }
+        }
+    }
+
+    fn elements_pattern(&mut self, elements: &[Node], fails: &mut Vec<usize>) {
+        let variadic = |element: &&Node| matches!(element, Node::Variadic(_));
+        let rest = elements.iter().position(|element| variadic(&element));
+        let many = elements.iter().filter(variadic).count() > 1;
+        fails.push(self.emit(Op::Destructure { elements: elements.len(), rest, many, fail: 0 }));
+        for element in elements.iter() {
+            let element: &Node = match element {
+                Node::Variadic(element) => element,
+                element => element,
+            };
+            self.pattern(element, fails);
+        }
+    }
+
+    fn assignment(&mut self, target: &Node, value: &Node) {
+        match target {
+            Node::Identifier(name) => {
+                self.expression(value);
+                let slot = self.slot(name);
+                self.emit(Op::Store(slot));
+                self.emit(Op::None);
+            }
+            Node::Index(target, index) => {
+                self.expression(target);
+                self.expression(index);
+                self.expression(value);
+                self.emit(Op::SetIndex);
+            }
+            pattern => {
+                self.emit(Op::Mark);
+                self.expression(value);
+                let mut fails = vec![];
+                self.pattern(pattern, &mut fails);
+                self.emit(Op::Assign);
+                let end = self.emit(Op::Jump(0));
+                fails.into_iter().for_each(|fail| self.patch(fail));
+                self.emit(Op::Reset);
+                self.emit(Op::Raise);
+                self.patch(end);
+            }
+        }
+    }
+
+    fn compound_assignment(&mut self, target: &Node, operator: &'static str, value: &Node) {
+        match target {
+            Node::Identifier(name) => {
+                let slot = self.slot(name);
+                self.emit(Op::Load(slot));
+                self.expression(value);
+                self.emit(Op::Compound(operator));
+                self.emit(Op::Store(slot));
+                self.emit(Op::None);
+            }
+            Node::Member(instance, name) => {
+                let name = intern(name);
+                self.expression(instance);
+                self.emit(Op::Dup);
+                self.emit(Op::Get(name));
+                self.expression(value);
+                self.emit(Op::Compound(operator));
+                self.emit(Op::SetMember(name));
+            }
+            Node::Index(target, index) => {
+                self.expression(target);
+                self.expression(index);
+                let exit = self.emit(Op::CurrentIndex(0));
+                self.expression(value);
+                self.emit(Op::Compound(operator));
+                self.emit(Op::SetIndex);
+                self.patch(exit);
+            }
+            target => self.constant(error(format!("Cannot assign to {:?}", target))),
+        }
+    }
+
+    // Arms whose pattern is a name compare against its value, the others destructure the subject.
+    fn match_arms(&mut self, subject: &Node, arms: &[Node]) {
+        let temporary = self.frame.temporary();
+        self.expression(subject);
+        self.emit(Op::Store(temporary));
+        let mut ends = vec![];
+        for arm in arms.iter() {
+            if let Node::MatchArm(pattern, body) = arm {
+                let mut fails = vec![];
+                if let Node::Identifier(_) = &**pattern {
+                    self.emit(Op::Load(temporary));
+                    self.expression(pattern);
+                    fails.push(self.emit(Op::Matches(0)));
+                    self.expression(body);
+                    ends.push(self.emit(Op::Jump(0)));
+                    fails.into_iter().for_each(|fail| self.patch(fail));
+                } else {
+                    self.emit(Op::Mark);
+                    self.emit(Op::Load(temporary));
+                    self.pattern(pattern, &mut fails);
+                    self.emit(Op::Assign);
+                    self.emit(Op::Pop);
+                    self.expression(body);
+                    ends.push(self.emit(Op::Jump(0)));
+                    fails.into_iter().for_each(|fail| self.patch(fail));
+                    self.emit(Op::Reset);
+                }
+            }
+        }
+        self.emit(Op::None);
+        ends.into_iter().for_each(|end| self.patch(end));
+    }
+
+    fn expression(&mut self, node: &Node) {
+        match node {
+            Node::Member(object, name) => {
+                self.expression(object);
+                self.emit(Op::Get(intern(name)));
+            }
+            Node::Program(statements) => self.statements(statements),
+            Node::Literal(string, _delimiter) => self.constant(Rc::new(Object::String(string.to_owned()))),
+            Node::Match(subject, arms) => self.match_arms(subject, arms),
+            Node::Parenthesized(node) => self.expression(node),
+            Node::Array(elements) | Node::Tuple(elements) => self.elements(elements),
+            Node::Map(entries) => {
+                self.emit(Op::Map);
+                let mut exits = vec![];
+                for entry in entries.iter() {
+                    if let Node::KeyValue(key, value) = entry {
+                        self.expression(key);
+                        self.expression(value);
+                        exits.push(self.emit(Op::Insert(0)));
+                    }
+                }
+                exits.into_iter().for_each(|exit| self.patch(exit));
+            }
+            Node::Set(elements) => {
+                self.emit(Op::Set);
+                let mut exits = vec![];
+                for element in elements.iter() {
+                    self.expression(element);
+                    exits.push(self.emit(Op::Add(0)));
+                }
+                exits.into_iter().for_each(|exit| self.patch(exit));
+            }
+            Node::Prefixed(prefix, literal) => {
+                self.expression(prefix);
+                match &**literal {
+                    Node::Template(parts) => {
+                        let strings: Vec<&Node> = parts.iter().filter(|part| matches!(part, Node::Literal(_, _))).collect();
+                        let values: Vec<&Node> = parts.iter().filter(|part| !matches!(part, Node::Literal(_, _))).collect();
+                        strings.iter().for_each(|string| self.expression(string));
+                        self.emit(Op::Array(strings.len()));
+                        values.iter().for_each(|value| self.expression(value));
+                        self.emit(Op::Array(values.len()));
+                        self.emit(Op::Prefix(2));
+                    }
+                    literal => {
+                        self.expression(literal);
+                        self.emit(Op::Prefix(1));
+                    }
+                }
+            }
+            Node::Index(target, index) => self.index(target, index),
+            Node::Negation(node) => {
+                self.expression(node);
+                self.emit(Op::Negate);
+            }
+            Node::Variadic(_) => self.constant(error(String::from("Variadic expressions are only allowed in calls and parameters"))),
+            Node::EnumDefinition(name, variants) => {
+                if let Node::Identifier(name) = &**name {
+                    self.constant(Rc::new(Object::Enum(name.to_owned(), variants.to_owned())));
+                    let slot = self.slot(name);
+                    self.emit(Op::Store(slot));
+                }
+                self.emit(Op::None);
+            }
+            Node::ClassDefinition(name, body) => {
+                if let Node::Identifier(name) = &**name {
+                    self.chunk.classes.push(Rc::new(compile_class(body)));
+                    self.emit(Op::Class(self.chunk.classes.len() - 1));
+                    let slot = self.slot(name);
+                    self.emit(Op::Store(slot));
+                }
+                self.emit(Op::None);
+            }
+            Node::Addition(lhs, rhs) => self.binary("+", lhs, rhs),
+            Node::Substraction(lhs, rhs) => self.binary("-", lhs, rhs),
+            Node::Multiplication(lhs, rhs) => self.binary("*", lhs, rhs),
+            Node::Division(lhs, rhs) => self.binary("/", lhs, rhs),
+            Node::Modulus(lhs, rhs) => self.binary("%", lhs, rhs),
+            Node::Number(number_string) => self.constant(number(number_string)),
+            Node::Identifier(name) => {
+                match self.frame.resolve(intern(name)) {
+                    Resolved::Local(slot) => self.emit(Op::Load(slot)),
+                    Resolved::Global(name) => self.emit(Op::Global(name)),
+                };
+            }
+            Node::FunctionDefinition(parameters, body) => self.constant(Rc::new(Object::Function(parameters.to_owned(), body.to_owned()))),
+            Node::Call(callee, args) => self.call(callee, args),
+            Node::Assignment(target, value) => self.assignment(target, value),
+            Node::CompoundAssignment(target, operator_string, value) => self.compound_assignment(target, operator(operator_string), value),
+            Node::Empty => {
+                self.emit(Op::None);
+            }
+            node => self.constant(error(format!("Cannot evaluate {}", node.kind()))),
+        }
+    }
+
+    fn binary(&mut self, operator: &'static str, lhs: &Node, rhs: &Node) {
+        self.expression(lhs);
+        self.expression(rhs);
+        self.emit(Op::Operate(operator));
+    }
+}
+
+// A module stops at the first statement that evaluates to an error and returns the value of its last statement.
+pub fn compile_module(statements: &[Node]) -> Chunk {
+    let mut compiler = Compiler::new(resolve_module(statements));
+    let statements: Vec<&Node> = statements.iter().filter(|statement| **statement != Node::Empty).collect();
+    if statements.is_empty() {
+        compiler.emit(Op::None);
+    }
+    for (index, statement) in statements.iter().enumerate() {
+        compiler.expression(statement);
+        compiler.emit(Op::ReturnIfError);
+        if index + 1 < statements.len() {
+            compiler.emit(Op::Pop);
+        }
+    }
+    compiler.emit(Op::Return);
+    compiler.finish()
+}
+
+// Defaults are evaluated by the code before the body, for the parameters not given by the call.
+pub fn compile_function(parameters: &[Node], body: &[Node]) -> Chunk {
+    let mut compiler = Compiler::new(resolve_function(parameters, body));
+    compiler.chunk.parameters = parameters.iter().map(|parameter| (parameter.to_owned(), parameter_name(parameter).map(|name| compiler.slot(name)))).collect();
+    for parameter in parameters.iter() {
+        if let Node::Assignment(name, default) = parameter {
+            if let Node::Identifier(name) = &**name {
+                let slot = compiler.slot(name);
+                let skip = compiler.emit(Op::Default(slot, 0));
+                compiler.expression(default);
+                compiler.emit(Op::Store(slot));
+                compiler.patch(skip);
+            }
+        }
+    }
+    if body.is_empty() {
+        compiler.emit(Op::None);
+    }
+    for (index, statement) in body.iter().enumerate() {
+        compiler.expression(statement);
+        if index + 1 < body.len() {
+            compiler.emit(Op::Pop);
+        }
+    }
+    compiler.emit(Op::Return);
+    compiler.finish()
+}
+
+fn compile_class(body: &Node) -> Chunk {
+    let mut compiler = Compiler::new(resolve_class(body));
+    compiler.expression(body);
+    compiler.emit(Op::Return);
+    compiler.finish()
+}
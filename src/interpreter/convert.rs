@@ -0,0 +1,206 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use object::{Object, Table};
+
+use crate::interpreter::is_truthy;
+use crate::map;
+
+// Reads a lim value as a Rust value, the error names what was expected.
+pub trait FromObject: Sized {
+    fn from_object(value: &Rc<Object>) -> Result<Self, String>;
+}
+
+pub trait IntoObject {
+    fn into_object(self) -> Rc<Object>;
+}
+
+// Lim calls the type of a value by its variant.
+pub fn type_name(value: &Object) -> &'static str {
+    match value {
+        Object::None => "None",
+        Object::Integer(_) => "Integer",
+        Object::Float(_) => "Float",
+        Object::Error(_) => "Error",
+        Object::String(_) => "String",
+        Object::Native(_, _) | Object::Linked(_) | Object::Builtin(_) | Object::Host(_, _) | Object::Function(_, _) | Object::BoundFunction(_, _) => "Function",
+        Object::Enum(_, _) => "Enum",
+        Object::EnumVariant(_, _) => "EnumVariant",
+        Object::Class(_) => "Class",
+        Object::Instance(_) => "Instance",
+        Object::Array(_) => "Array",
+        Object::Map(_) => "Map",
+        Object::Set(_) => "Set",
+        Object::Module(_, _) => "Module",
+    }
+}
+
+fn expected(name: &str, value: &Object) -> String {
+    format!("expected {}, got {}", name, type_name(value))
+}
+
+impl FromObject for Rc<Object> {
+    fn from_object(value: &Rc<Object>) -> Result<Self, String> {
+        Ok(value.to_owned())
+    }
+}
+
+impl FromObject for i64 {
+    fn from_object(value: &Rc<Object>) -> Result<Self, String> {
+        match **value {
+            Object::Integer(integer) => Ok(integer),
+            ref value => Err(expected("Integer", value)),
+        }
+    }
+}
+
+// Integers widen to floats the way arithmetic mixing them does.
+impl FromObject for f64 {
+    fn from_object(value: &Rc<Object>) -> Result<Self, String> {
+        match **value {
+            Object::Float(float) => Ok(float),
+            Object::Integer(integer) => Ok(integer as f64),
+            ref value => Err(expected("Float", value)),
+        }
+    }
+}
+
+impl FromObject for bool {
+    fn from_object(value: &Rc<Object>) -> Result<Self, String> {
+        Ok(is_truthy(value))
+    }
+}
+
+impl FromObject for String {
+    fn from_object(value: &Rc<Object>) -> Result<Self, String> {
+        match &**value {
+            Object::String(string) => Ok(string.to_owned()),
+            value => Err(expected("String", value)),
+        }
+    }
+}
+
+impl<T: FromObject> FromObject for Option<T> {
+    fn from_object(value: &Rc<Object>) -> Result<Self, String> {
+        match **value {
+            Object::None => Ok(None),
+            _ => T::from_object(value).map(Some),
+        }
+    }
+}
+
+impl<T: FromObject> FromObject for Vec<T> {
+    fn from_object(value: &Rc<Object>) -> Result<Self, String> {
+        match &**value {
+            Object::Array(elements) => elements
+                .iter()
+                .enumerate()
+                .map(|(index, element)| T::from_object(element).map_err(|error| format!("{} at index {}", error, index)))
+                .collect(),
+            value => Err(expected("Array", value)),
+        }
+    }
+}
+
+// Only string keys convert, the map a host reads is usually a record.
+impl<T: FromObject> FromObject for HashMap<String, T> {
+    fn from_object(value: &Rc<Object>) -> Result<Self, String> {
+        match &**value {
+            Object::Map(table) => table
+                .iter()
+                .map(|(key, value)| {
+                    let key = String::from_object(key).map_err(|error| format!("{} as a key", error))?;
+                    let value = T::from_object(value).map_err(|error| format!("{} at key {}", error, key))?;
+                    Ok((key, value))
+                })
+                .collect(),
+            Object::Instance(fields) => fields.iter().map(|(key, value)| Ok((key.to_owned(), T::from_object(value)?))).collect(),
+            value => Err(expected("Map", value)),
+        }
+    }
+}
+
+impl IntoObject for Rc<Object> {
+    fn into_object(self) -> Rc<Object> {
+        self
+    }
+}
+
+impl IntoObject for Object {
+    fn into_object(self) -> Rc<Object> {
+        Rc::new(self)
+    }
+}
+
+impl IntoObject for () {
+    fn into_object(self) -> Rc<Object> {
+        Rc::new(Object::None)
+    }
+}
+
+impl IntoObject for i64 {
+    fn into_object(self) -> Rc<Object> {
+        Rc::new(Object::Integer(self))
+    }
+}
+
+impl IntoObject for f64 {
+    fn into_object(self) -> Rc<Object> {
+        Rc::new(Object::Float(self))
+    }
+}
+
+// Lim has no booleans, 0 is falsy and 1 truthy.
+impl IntoObject for bool {
+    fn into_object(self) -> Rc<Object> {
+        Rc::new(Object::Integer(self as i64))
+    }
+}
+
+impl IntoObject for String {
+    fn into_object(self) -> Rc<Object> {
+        Rc::new(Object::String(self))
+    }
+}
+
+impl IntoObject for &str {
+    fn into_object(self) -> Rc<Object> {
+        Rc::new(Object::String(self.to_owned()))
+    }
+}
+
+impl<T: IntoObject> IntoObject for Option<T> {
+    fn into_object(self) -> Rc<Object> {
+        match self {
+            Some(value) => value.into_object(),
+            None => Rc::new(Object::None),
+        }
+    }
+}
+
+impl<T: IntoObject> IntoObject for Vec<T> {
+    fn into_object(self) -> Rc<Object> {
+        Rc::new(Object::Array(self.into_iter().map(IntoObject::into_object).collect()))
+    }
+}
+
+impl<T: IntoObject> IntoObject for HashMap<String, T> {
+    fn into_object(self) -> Rc<Object> {
+        let mut table = Table::new();
+        for (key, value) in self {
+            // String keys always hash.
+            let _ = map::insert(&mut table, Rc::new(Object::String(key)), value.into_object());
+        }
+        Rc::new(Object::Map(table))
+    }
+}
+
+// An error becomes an error value, which fails the lim code using it.
+impl<T: IntoObject, E: ToString> IntoObject for Result<T, E> {
+    fn into_object(self) -> Rc<Object> {
+        match self {
+            Ok(value) => value.into_object(),
+            Err(error) => Rc::new(Object::Error(error.to_string())),
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+
+use serde_json::{json, Value};
+
+use lexer::span::{Position, Span};
+use lexer::token::Token;
+use parser::node::Node;
+use parser::parser::node_spans;
+
+// The JSON written here is documented in examples/json_dumps.md, changing a field name or a type name
+// breaks the tools reading it.
+pub const VERSION: u64 = 1;
+
+type Spans = HashMap<*const Node, Span>;
+
+fn position(position: &Position) -> Value {
+    json!({ "line": position.line, "column": position.column, "offset": position.offset })
+}
+
+pub fn span(span: &Span) -> Value {
+    json!({ "start": position(&span.start), "end": position(&span.end) })
+}
+
+fn token_type(token: &Token) -> &'static str {
+    match token {
+        Token::Identifier(_) => "Identifier",
+        Token::Prefix(_) => "Prefix",
+        Token::Number(_) => "Number",
+        Token::Literal(_, _) => "String",
+        Token::Equal => "Equal",
+        Token::Plus => "Plus",
+        Token::Minus => "Minus",
+        Token::Times => "Times",
+        Token::Division => "Division",
+        Token::Modulus => "Modulus",
+        Token::PlusEqual => "PlusEqual",
+        Token::MinusEqual => "MinusEqual",
+        Token::TimesEqual => "TimesEqual",
+        Token::DivisionEqual => "DivisionEqual",
+        Token::ModulusEqual => "ModulusEqual",
+        Token::Comma => "Comma",
+        Token::Colon => "Colon",
+        Token::Period => "Period",
+        Token::Ellipsis => "Ellipsis",
+        Token::SemiColon => "SemiColon",
+        Token::NewLine => "NewLine",
+        Token::OpenParenthesis => "OpenParenthesis",
+        Token::CloseParenthesis => "CloseParenthesis",
+        Token::OpenBrace => "OpenBrace",
+        Token::CloseBrace => "CloseBrace",
+        Token::OpenBracket => "OpenBracket",
+        Token::CloseBracket => "CloseBracket",
+        Token::Comment(_) => "Comment",
+    }
+}
+
+pub fn token(token: &Token, token_span: &Span) -> Value {
+    let mut value = json!({ "type": token_type(token), "span": span(token_span) });
+    match token {
+        Token::Identifier(string) | Token::Prefix(string) | Token::Number(string) | Token::Comment(string) => value["value"] = json!(string),
+        Token::Literal(string, delimiter) => {
+            value["value"] = json!(string);
+            value["quote"] = json!(delimiter.to_string());
+        }
+        _ => {}
+    }
+    value
+}
+
+pub fn tokens(tokens: &[(Token, Span)]) -> Value {
+    json!({ "version": VERSION, "tokens": tokens.iter().map(|(token, token_span)| self::token(token, token_span)).collect::<Vec<Value>>() })
+}
+
+fn nodes(nodes: &[Node], spans: &Spans) -> Value {
+    Value::Array(nodes.iter().filter(|node| **node != Node::Empty).map(|node| located(node, spans)).collect())
+}
+
+fn binary(operator: &str, left: &Node, right: &Node, spans: &Spans) -> Value {
+    json!({ "type": "BinaryOperation", "operator": operator, "left": located(left, spans), "right": located(right, spans) })
+}
+
+fn name(node: &Node) -> Value {
+    match node {
+        Node::Identifier(name) => json!(name),
+        _ => Value::Null,
+    }
+}
+
+// A node without a span, like the nodes of a template, has no `span` field.
+fn located(node: &Node, spans: &Spans) -> Value {
+    let mut value = match node {
+        Node::Program(statements) => json!({ "type": "Program", "body": nodes(statements, spans) }),
+        Node::Assignment(target, value) => json!({ "type": "Assignment", "target": located(target, spans), "value": located(value, spans) }),
+        Node::CompoundAssignment(target, operator, value) => json!({ "type": "CompoundAssignment", "operator": operator, "target": located(target, spans), "value": located(value, spans) }),
+        Node::Addition(left, right) => binary("+", left, right, spans),
+        Node::Substraction(left, right) => binary("-", left, right, spans),
+        Node::Multiplication(left, right) => binary("*", left, right, spans),
+        Node::Division(left, right) => binary("/", left, right, spans),
+        Node::Modulus(left, right) => binary("%", left, right, spans),
+        Node::Negation(operand) => json!({ "type": "Negation", "operand": located(operand, spans) }),
+        Node::Number(value) => json!({ "type": "Number", "value": value }),
+        Node::Identifier(name) => json!({ "type": "Identifier", "name": name }),
+        Node::Literal(value, delimiter) => json!({ "type": "String", "value": value, "quote": delimiter.to_string() }),
+        Node::Template(parts) => json!({ "type": "Template", "parts": nodes(parts, spans) }),
+        Node::Prefixed(prefix, literal) => json!({ "type": "Prefixed", "prefix": name(prefix), "literal": located(literal, spans) }),
+        Node::Parenthesized(expression) => json!({ "type": "Parenthesized", "expression": located(expression, spans) }),
+        Node::Call(callee, arguments) => json!({ "type": "Call", "callee": located(callee, spans), "arguments": nodes(arguments, spans) }),
+        Node::Member(object, name) => json!({ "type": "Member", "object": located(object, spans), "name": name }),
+        Node::Index(object, index) => json!({ "type": "Index", "object": located(object, spans), "index": located(index, spans) }),
+        Node::Slice(start, end) => json!({ "type": "Slice", "start": located(start, spans), "end": located(end, spans) }),
+        Node::FunctionArgs(elements) => json!({ "type": "Arguments", "elements": nodes(elements, spans) }),
+        Node::Variadic(expression) => json!({ "type": "Spread", "expression": located(expression, spans) }),
+        Node::Tuple(elements) => json!({ "type": "Tuple", "elements": nodes(elements, spans) }),
+        Node::Array(elements) => json!({ "type": "Array", "elements": nodes(elements, spans) }),
+        Node::Set(elements) => json!({ "type": "Set", "elements": nodes(elements, spans) }),
+        Node::Map(entries) => json!({ "type": "Map", "entries": nodes(entries, spans) }),
+        Node::KeyValue(key, value) => json!({ "type": "Entry", "key": located(key, spans), "value": located(value, spans) }),
+        Node::FunctionDefinition(parameters, body) => json!({ "type": "Function", "parameters": nodes(parameters, spans), "body": nodes(body, spans) }),
+        Node::EnumDefinition(enum_name, variants) => json!({ "type": "Enum", "name": name(enum_name), "variants": nodes(variants, spans) }),
+        Node::ClassDefinition(class_name, body) => json!({ "type": "Class", "name": name(class_name), "body": located(body, spans) }),
+        Node::Match(subject, arms) => json!({ "type": "Match", "subject": located(subject, spans), "arms": nodes(arms, spans) }),
+        Node::MatchArm(pattern, body) => json!({ "type": "MatchArm", "pattern": located(pattern, spans), "body": located(body, spans) }),
+        Node::Empty => Value::Null,
+        // Only an unfinished statement holds the other nodes, they keep the name the parser gives them.
+        node => json!({ "type": node.kind() }),
+    };
+    if let Some(node_span) = spans.get(&(node as *const Node)) {
+        value["span"] = span(node_span);
+    }
+    value
+}
+
+pub fn node(node: &Node) -> Value {
+    located(node, &HashMap::new())
+}
+
+// `tokens` are the ones the statements were parsed from, they give the spans of the nested nodes.
+pub fn ast(statements: &[(Node, Span)], tokens: &[(Token, Span)]) -> Value {
+    let spans = node_spans(statements, tokens);
+    let body = statements.iter().filter(|(statement, _span)| *statement != Node::Empty).map(|(statement, _span)| located(statement, &spans)).collect::<Vec<Value>>();
+    json!({ "version": VERSION, "type": "Program", "body": body })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ast, node};
+    use lexer::lexer::lex_spanned;
+    use parser::node::Node;
+    use parser::parser::parse_spanned;
+    use serde_json::json;
+
+    fn at(start: u64, end: u64) -> serde_json::Value {
+        json!({ "start": { "line": 2, "column": start - 5, "offset": start }, "end": { "line": 2, "column": end - 5, "offset": end } })
+    }
+
+    #[test]
+    fn ast_dump_spans_nested_nodes() {
+        let source = "x = 1\ny = f(x)";
+        let tokens = lex_spanned(source).unwrap();
+        let ast = ast(&parse_spanned(tokens.to_vec()).unwrap(), &tokens);
+        assert_eq!(ast["body"][1]["span"], at(6, 14));
+        assert_eq!(ast["body"][1]["target"], json!({ "type": "Identifier", "name": "y", "span": at(6, 7) }));
+        assert_eq!(ast["body"][1]["value"], json!({
+            "type": "Call",
+            "callee": { "type": "Identifier", "name": "f", "span": at(10, 11) },
+            "arguments": [{ "type": "Identifier", "name": "x", "span": at(12, 13) }],
+            "span": at(10, 14),
+        }));
+        assert_eq!(node(&Node::PartialCall(Box::new(Node::Identifier(String::from("f"))), vec![])), json!({ "type": "PartialCall" }));
+    }
+}
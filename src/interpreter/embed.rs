@@ -0,0 +1,354 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::env;
+use std::ffi::CStr;
+use std::path::{Path, PathBuf};
+use std::rc::{Rc, Weak};
+
+use lim_abi::LimNativeInfo;
+use object::Object;
+
+use crate::convert::{FromObject, IntoObject};
+use crate::extension;
+use crate::interpreter;
+use crate::module;
+
+type Host = dyn Fn(&[Rc<Object>]) -> Rc<Object>;
+type HostFunction = Rc<Host>;
+
+// Host functions belong to the `Interpreter` registering them and are dropped with it. `Object::Host`
+// values hold an index unique to the thread, `HOSTS` finds the function behind it for as long as its
+// interpreter lives, wherever the value was passed to.
+thread_local! {
+    static HOSTS: RefCell<HashMap<usize, Weak<Host>>> = RefCell::new(HashMap::new());
+    static NEXT_HOST: Cell<usize> = const { Cell::new(0) };
+}
+
+pub fn call_host(name: &str, index: usize, args: &[Rc<Object>]) -> Rc<Object> {
+    // Upgraded out of the table, the function may register or call other host functions.
+    match HOSTS.with(|hosts| hosts.borrow().get(&index).and_then(Weak::upgrade)) {
+        Some(function) => function(args),
+        None => Rc::new(Object::Error(format!("{} cannot be called once its interpreter is dropped", name))),
+    }
+}
+
+// Undoes what `Interpreter::enter` did once the code it runs returns.
+struct Running {
+    shadowed: Vec<(String, Option<Rc<Object>>)>,
+}
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        for (name, native) in self.shadowed.drain(..).rev() {
+            match native {
+                Some(native) => {
+                    interpreter::define_native(&name, native);
+                }
+                None => interpreter::remove_native(&name),
+            }
+        }
+    }
+}
+
+fn argument<T: FromObject>(name: &str, args: &[Rc<Object>], index: usize) -> Result<T, Rc<Object>> {
+    T::from_object(&args[index]).map_err(|error| Rc::new(Object::Error(format!("Argument {} of {}: {}", index + 1, name, error))))
+}
+
+// Rust functions and closures whose arguments convert from lim values and whose result converts
+// back, `Args` only tells the arities apart.
+pub trait IntoNative<Args> {
+    fn into_native(self, name: &str) -> HostFunction;
+}
+
+macro_rules! into_native {
+    ($count:literal $(, $arg:ident $index:literal)*) => {
+        impl<Function, R, $($arg),*> IntoNative<($($arg,)*)> for Function
+        where
+            Function: Fn($($arg),*) -> R + 'static,
+            R: IntoObject,
+            $($arg: FromObject,)*
+        {
+            #[allow(non_snake_case)]
+            fn into_native(self, name: &str) -> HostFunction {
+                let name = name.to_owned();
+                Rc::new(move |args: &[Rc<Object>]| {
+                    if args.len() != $count {
+                        let noun = if $count == 1 { "argument" } else { "arguments" };
+                        return Rc::new(Object::Error(format!("{} expects {} {}, got {}", name, $count, noun, args.len())));
+                    }
+                    $(
+                        let $arg = match argument::<$arg>(&name, args, $index) {
+                            Ok(value) => value,
+                            Err(error) => return error,
+                        };
+                    )*
+                    self($($arg),*).into_object()
+                })
+            }
+        }
+    };
+}
+
+into_native!(0);
+into_native!(1, A 0);
+into_native!(2, A 0, B 1);
+into_native!(3, A 0, B 1, C 2);
+into_native!(4, A 0, B 1, C 2, D 3);
+into_native!(5, A 0, B 1, C 2, D 3, E 4);
+into_native!(6, A 0, B 1, C 2, D 3, E 4, F 5);
+
+// The arguments of a call from Rust, a tuple of convertible values or the values themselves.
+pub trait IntoArgs {
+    fn into_args(self) -> Vec<Rc<Object>>;
+}
+
+impl IntoArgs for Vec<Rc<Object>> {
+    fn into_args(self) -> Vec<Rc<Object>> {
+        self
+    }
+}
+
+macro_rules! into_args {
+    ($($arg:ident)*) => {
+        impl<$($arg: IntoObject),*> IntoArgs for ($($arg,)*) {
+            #[allow(non_snake_case)]
+            fn into_args(self) -> Vec<Rc<Object>> {
+                let ($($arg,)*) = self;
+                vec![$($arg.into_object()),*]
+            }
+        }
+    };
+}
+
+into_args!();
+into_args!(A);
+into_args!(A B);
+into_args!(A B C);
+into_args!(A B C D);
+into_args!(A B C D E);
+into_args!(A B C D E F);
+
+fn result(value: Rc<Object>) -> Result<Rc<Object>, String> {
+    match &*value {
+        Object::Error(message) => Err(message.to_owned()),
+        _ => Ok(value),
+    }
+}
+
+// Runs lim code inside a Rust application. The interpreter owns the global scope every evaluation
+// shares and the Rust functions registered on it, errors of the lim code come back as `Err` with
+// their message.
+pub struct Interpreter {
+    scope: HashMap<String, Rc<Object>>,
+    directory: PathBuf,
+    // The `Object::Host` value of every registered name and the functions they stand for.
+    natives: HashMap<String, Rc<Object>>,
+    hosts: HashMap<usize, HostFunction>,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        Self::with_directory(env::current_dir().unwrap_or_default())
+    }
+
+    // Relative imports of evaluated source resolve from `directory`, the working directory by default.
+    pub fn with_directory(directory: impl Into<PathBuf>) -> Self {
+        Interpreter { scope: HashMap::new(), directory: directory.into(), natives: HashMap::new(), hosts: HashMap::new() }
+    }
+
+    // The natives of this interpreter shadow the ones of the thread until the guard is dropped.
+    fn enter(&self) -> Running {
+        let shadowed = self.natives.iter().map(|(name, native)| (name.to_owned(), interpreter::define_native(name, native.to_owned()))).collect();
+        Running { shadowed }
+    }
+
+    // The value of the last statement.
+    pub fn eval(&mut self, source: &str) -> Result<Rc<Object>, String> {
+        let _running = self.enter();
+        result(module::evaluate(&self.directory.join("<embedded>"), source, &mut self.scope))
+    }
+
+    pub fn run_file(&mut self, path: impl AsRef<Path>) -> Result<Rc<Object>, String> {
+        let path = self.directory.join(path);
+        let path = path.canonicalize().map_err(|error| format!("Cannot open {}: {}", path.display(), error))?;
+        let _running = self.enter();
+        result(module::execute(&path, &mut self.scope))
+    }
+
+    pub fn get<T: FromObject>(&self, name: &str) -> Result<T, String> {
+        let value = self
+            .scope
+            .get(name)
+            .or_else(|| self.natives.get(name))
+            .cloned()
+            .or_else(|| interpreter::native(name))
+            .ok_or_else(|| format!("{} is not defined", name))?;
+        T::from_object(&value).map_err(|error| format!("{}: {}", name, error))
+    }
+
+    pub fn set(&mut self, name: &str, value: impl IntoObject) {
+        self.scope.insert(name.to_owned(), value.into_object());
+    }
+
+    fn define_host(&mut self, name: &str, function: HostFunction) {
+        let index = NEXT_HOST.with(|next| next.replace(next.get() + 1));
+        HOSTS.with(|hosts| hosts.borrow_mut().insert(index, Rc::downgrade(&function)));
+        self.hosts.insert(index, function);
+        // A function registered again under the same name replaces the previous one.
+        if let Some(Object::Host(_, replaced)) = self.natives.insert(name.to_owned(), Rc::new(Object::Host(name.to_owned(), index))).as_deref() {
+            self.hosts.remove(replaced);
+        }
+    }
+
+    // Makes a Rust function a native, visible from every module and function this interpreter runs
+    // like `print`.
+    pub fn register<Args>(&mut self, name: &str, function: impl IntoNative<Args>) {
+        self.define_host(name, function.into_native(name));
+    }
+
+    // Links a native declared with `#[lim_native]`, the one its library would export.
+    pub fn register_native(&mut self, info: &'static LimNativeInfo) {
+        let name = unsafe { CStr::from_ptr(info.name) }.to_string_lossy();
+        self.define_host(&name, Rc::new(move |args: &[Rc<Object>]| extension::call(info.function, args)));
+    }
+
+    pub fn call<R: FromObject>(&mut self, name: &str, args: impl IntoArgs) -> Result<R, String> {
+        let function: Rc<Object> = self.get(name)?;
+        self.call_value(&function, args)
+    }
+
+    pub fn call_value<R: FromObject>(&mut self, function: &Rc<Object>, args: impl IntoArgs) -> Result<R, String> {
+        let _running = self.enter();
+        let value = result(interpreter::call(function, &args.into_args(), function))?;
+        R::from_object(&value)
+    }
+}
+
+impl Drop for Interpreter {
+    fn drop(&mut self) {
+        HOSTS.with(|hosts| {
+            let mut hosts = hosts.borrow_mut();
+            for index in self.hosts.keys() {
+                hosts.remove(index);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::env;
+    use std::ffi::CStr;
+    use std::rc::Rc;
+
+    use super::Interpreter;
+    use crate::interpreter;
+    use lim_abi::LimNativeInfo;
+    use lim_native::{lim_native, LimError};
+    use object::Object;
+
+    #[test]
+    fn interpreter_registers_evaluates_and_calls() {
+        let mut lim = Interpreter::new();
+        lim.register("hypot", |x: f64, y: f64| (x * x + y * y).sqrt());
+        lim.register("halve", |n: i64| if n % 2 == 0 { Ok(n / 2) } else { Err(format!("{} is odd", n)) });
+        lim.set("sides", vec![3, 4]);
+        lim.eval("class Shape {\n  diagonal = (sides) { hypot(sides[0], sides[1]) }\n}\narea = (width, height) { width * height }").unwrap();
+        assert_eq!(lim.eval("Shape().diagonal(sides)").unwrap().to_string(), "5");
+        assert_eq!(lim.call::<i64>("area", (6, 7)), Ok(42));
+        assert_eq!(lim.call::<i64>("halve", (8,)), Ok(4));
+        assert_eq!(lim.call::<i64>("halve", (7,)), Err(String::from("7 is odd")));
+        assert_eq!(lim.eval("hypot(1)").unwrap_err(), "hypot expects 2 arguments, got 1");
+        assert_eq!(lim.eval("hypot('a', 1)").unwrap_err(), "Argument 1 of hypot: expected Float, got String");
+        assert_eq!(lim.eval("area = ").unwrap_err(), format!("Unexpected end of input in {}", env::current_dir().unwrap().join("<embedded>").display()));
+        assert_eq!(lim.get::<i64>("missing"), Err(String::from("missing is not defined")));
+    }
+
+    #[test]
+    fn interpreter_converts_values() {
+        let mut lim = Interpreter::new();
+        lim.eval("record = {'name': 'lim', 'age': 3}\nwords = ['a', 'b', 1]\nnumbers = [1, 2.5]").unwrap();
+        assert_eq!(lim.get::<HashMap<String, Rc<Object>>>("record").map(|record| record.len()), Ok(2));
+        assert_eq!(lim.get::<HashMap<String, i64>>("record"), Err(String::from("record: expected Integer, got String at key name")));
+        assert_eq!(lim.get::<Vec<String>>("words"), Err(String::from("words: expected String, got Integer at index 2")));
+        assert_eq!(lim.get::<Vec<f64>>("numbers"), Ok(vec![1.0, 2.5]));
+        assert_eq!(lim.get::<Vec<i64>>("numbers"), Err(String::from("numbers: expected Integer, got Float at index 1")));
+        assert_eq!(lim.get::<Option<i64>>("nothing"), Err(String::from("nothing is not defined")));
+        lim.set("nothing", ());
+        lim.set("flag", true);
+        lim.set("table", HashMap::from([(String::from("key"), Some(1.5))]));
+        assert_eq!(lim.get::<Option<i64>>("nothing"), Ok(None));
+        assert_eq!(lim.get::<bool>("flag"), Ok(true));
+        assert_eq!(lim.eval("[flag, table['key'], nothing]").unwrap().to_string(), "[1, 1.5, None]");
+    }
+
+    // Every interpreter only sees its own host functions, they are dropped with it.
+    #[test]
+    fn interpreter_owns_its_host_functions() {
+        let token = Rc::new(());
+        let mut first = Interpreter::new();
+        let held = token.to_owned();
+        first.register("secret", move || {
+            let _held = &held;
+            42
+        });
+        let mut second = Interpreter::new();
+        second.register("secret", || 6);
+        second.register("secret", || 7);
+        assert_eq!(first.call::<i64>("secret", ()), Ok(42));
+        assert_eq!(second.eval("f = () { secret() }\nf()").unwrap().to_string(), "7");
+        assert!(interpreter::native("secret").is_none());
+        let secret: Rc<Object> = first.get("secret").unwrap();
+        assert_eq!(second.call_value::<i64>(&secret, ()), Ok(42));
+        drop(first);
+        assert_eq!(Rc::strong_count(&token), 1);
+        assert_eq!(second.call_value::<i64>(&secret, ()), Err(String::from("secret cannot be called once its interpreter is dropped")));
+        assert_eq!(Interpreter::new().eval("secret()").unwrap_err(), "None is not callable");
+    }
+
+    /// Divides two numbers, failing on a zero divisor.
+    #[lim_native]
+    fn divide(dividend: f64, divisor: f64) -> Result<f64, LimError> {
+        if divisor == 0.0 {
+            return Err(LimError::new("Cannot divide by zero"));
+        }
+        Ok(dividend / divisor)
+    }
+
+    #[lim_native]
+    fn join(separator: String, #[rest] parts: Vec<Option<String>>) -> String {
+        parts.into_iter().map(|part| part.unwrap_or_default()).collect::<Vec<String>>().join(&separator)
+    }
+
+    #[lim_native]
+    fn explode() -> i64 {
+        panic!("on purpose")
+    }
+
+    #[test]
+    fn lim_native_checks_arity_and_types() {
+        let doc = |info: &LimNativeInfo| unsafe { CStr::from_ptr(info.doc) }.to_string_lossy().into_owned();
+        assert_eq!((lim_native_divide.arity, lim_native_divide.variadic, doc(&lim_native_divide)), (2, false, String::from("Divides two numbers, failing on a zero divisor.")));
+        assert_eq!((lim_native_join.arity, lim_native_join.variadic, doc(&lim_native_join)), (1, true, String::new()));
+        assert_eq!(divide(1.0, 4.0), Ok(0.25));
+        let mut lim = Interpreter::new();
+        lim.register_native(&lim_native_divide);
+        lim.register_native(&lim_native_join);
+        lim.register_native(&lim_native_explode);
+        assert_eq!(lim.eval("[divide(1, 4), join('-', 'a', None, 'b'), join(', ')]").unwrap().to_string(), "[0.25, a--b, ]");
+        assert_eq!(lim.eval("divide(1, 0)").unwrap_err(), "Cannot divide by zero");
+        assert_eq!(lim.eval("divide(1)").unwrap_err(), "divide expects 2 arguments, got 1");
+        assert_eq!(lim.eval("divide(1, 2, 3)").unwrap_err(), "divide expects 2 arguments, got 3");
+        assert_eq!(lim.eval("divide('1', 2)").unwrap_err(), "Argument 1 of divide: expected Float, got String");
+        assert_eq!(lim.eval("join()").unwrap_err(), "join expects at least 1 argument, got 0");
+        assert_eq!(lim.eval("join('-', 'a', 2)").unwrap_err(), "Argument 3 of join: expected String, got Integer");
+        assert_eq!(lim.eval("explode()").unwrap_err(), "explode panicked: on purpose");
+    }
+}
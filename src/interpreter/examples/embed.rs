@@ -0,0 +1,49 @@
+// Runs lim code from a Rust program: `cargo run --example embed`.
+use lim::Interpreter;
+use lim_native::lim_native;
+
+/// Keeps a number between two bounds.
+#[lim_native]
+fn clamp(value: f64, low: f64, high: f64) -> f64 {
+    value.max(low).min(high)
+}
+
+fn main() -> Result<(), String> {
+    let mut lim = Interpreter::new();
+    lim.register("hypot", |x: f64, y: f64| (x * x + y * y).sqrt());
+    lim.register("shout", |text: String| -> Result<String, String> {
+        if text.is_empty() {
+            return Err(String::from("Nothing to shout"));
+        }
+        Ok(text.to_uppercase())
+    });
+    lim.register_native(&lim_native_clamp);
+    lim.set("greeting", "hello");
+
+    lim.eval(
+        "
+area = (width, height) { width * height }
+
+class Shape {
+  diagonal = (width, height) { hypot(width, height) }
+}
+
+loud = shout(greeting)
+",
+    )?;
+
+    let area: i64 = lim.call("area", (6, 7))?;
+    let loud: String = lim.get("loud")?;
+    lim.eval("diagonal = Shape().diagonal(3, 4)")?;
+    let diagonal: f64 = lim.get("diagonal")?;
+    let clamped: f64 = lim.call("clamp", (12, 0, 10))?;
+    println!("area {}, {}, diagonal {}, clamped {}", area, loud, diagonal, clamped);
+
+    if let Err(error) = lim.eval("shout('')") {
+        println!("error: {}", error);
+    }
+    if let Err(error) = lim.eval("hypot('a', 1)") {
+        println!("error: {}", error);
+    }
+    Ok(())
+}
@@ -0,0 +1,211 @@
+use std::os::raw::c_char;
+use std::ptr;
+use std::rc::Rc;
+use std::slice;
+
+use lim_abi::{LimApi, LimNative, LimValue, LIM_ABI_VERSION};
+use lim_abi::{LIM_ARRAY, LIM_ERROR, LIM_FLOAT, LIM_INTEGER, LIM_NONE, LIM_OTHER, LIM_STRING};
+use object::Object;
+
+// A handle is a pointer to the object of an `Rc`. Owned handles hold one strong count of it,
+// borrowed ones none.
+fn owned(object: Rc<Object>) -> *mut LimValue {
+    Rc::into_raw(object) as *mut LimValue
+}
+
+unsafe fn borrowed<'a>(value: *const LimValue) -> &'a Object {
+    &*(value as *const Object)
+}
+
+unsafe fn shared(value: *const LimValue) -> Rc<Object> {
+    Rc::increment_strong_count(value as *const Object);
+    Rc::from_raw(value as *const Object)
+}
+
+unsafe fn text(bytes: *const c_char, length: usize) -> String {
+    if bytes.is_null() {
+        return String::new();
+    }
+    String::from_utf8_lossy(slice::from_raw_parts(bytes as *const u8, length)).into_owned()
+}
+
+unsafe fn bytes(string: &str, length: *mut usize) -> *const c_char {
+    if !length.is_null() {
+        *length = string.len();
+    }
+    string.as_ptr() as *const c_char
+}
+
+unsafe extern "C" fn kind(value: *const LimValue) -> u32 {
+    match borrowed(value) {
+        Object::None => LIM_NONE,
+        Object::Integer(_) => LIM_INTEGER,
+        Object::Float(_) => LIM_FLOAT,
+        Object::String(_) => LIM_STRING,
+        Object::Array(_) => LIM_ARRAY,
+        Object::Error(_) => LIM_ERROR,
+        _ => LIM_OTHER,
+    }
+}
+
+unsafe extern "C" fn none() -> *mut LimValue {
+    owned(Rc::new(Object::None))
+}
+
+unsafe extern "C" fn integer(integer: i64) -> *mut LimValue {
+    owned(Rc::new(Object::Integer(integer)))
+}
+
+unsafe extern "C" fn float(float: f64) -> *mut LimValue {
+    owned(Rc::new(Object::Float(float)))
+}
+
+unsafe extern "C" fn string(bytes: *const c_char, length: usize) -> *mut LimValue {
+    owned(Rc::new(Object::String(text(bytes, length))))
+}
+
+unsafe extern "C" fn error(bytes: *const c_char, length: usize) -> *mut LimValue {
+    owned(Rc::new(Object::Error(text(bytes, length))))
+}
+
+unsafe extern "C" fn array(elements: *const *const LimValue, length: usize) -> *mut LimValue {
+    let elements = if elements.is_null() { &[] } else { slice::from_raw_parts(elements, length) };
+    owned(Rc::new(Object::Array(elements.iter().map(|element| shared(*element)).collect())))
+}
+
+unsafe extern "C" fn as_integer(value: *const LimValue, integer: *mut i64) -> i32 {
+    match borrowed(value) {
+        Object::Integer(value) => {
+            if !integer.is_null() {
+                *integer = *value;
+            }
+            1
+        }
+        _ => 0,
+    }
+}
+
+unsafe extern "C" fn as_float(value: *const LimValue, float: *mut f64) -> i32 {
+    let value = match borrowed(value) {
+        Object::Float(value) => *value,
+        Object::Integer(value) => *value as f64,
+        _ => return 0,
+    };
+    if !float.is_null() {
+        *float = value;
+    }
+    1
+}
+
+unsafe extern "C" fn as_string(value: *const LimValue, length: *mut usize) -> *const c_char {
+    match borrowed(value) {
+        Object::String(string) => bytes(string, length),
+        _ => ptr::null(),
+    }
+}
+
+unsafe extern "C" fn as_error(value: *const LimValue, length: *mut usize) -> *const c_char {
+    match borrowed(value) {
+        Object::Error(message) => bytes(message, length),
+        _ => ptr::null(),
+    }
+}
+
+unsafe extern "C" fn display(value: *const LimValue) -> *mut LimValue {
+    owned(Rc::new(Object::String(borrowed(value).to_string())))
+}
+
+unsafe extern "C" fn array_length(value: *const LimValue) -> usize {
+    match borrowed(value) {
+        Object::Array(elements) => elements.len(),
+        _ => 0,
+    }
+}
+
+unsafe extern "C" fn array_get(value: *const LimValue, index: usize) -> *mut LimValue {
+    match borrowed(value) {
+        Object::Array(elements) => elements.get(index).map_or(ptr::null_mut(), |element| owned(element.to_owned())),
+        _ => ptr::null_mut(),
+    }
+}
+
+unsafe extern "C" fn release(value: *mut LimValue) {
+    if !value.is_null() {
+        drop(Rc::from_raw(value as *const Object));
+    }
+}
+
+static API: LimApi = LimApi {
+    version: LIM_ABI_VERSION,
+    kind,
+    none,
+    integer,
+    float,
+    string,
+    error,
+    array,
+    as_integer,
+    as_float,
+    as_string,
+    as_error,
+    display,
+    array_length,
+    array_get,
+    release,
+};
+
+// The arguments are lent to the native for the duration of the call, its result is handed over.
+pub fn call(function: LimNative, args: &[Rc<Object>]) -> Rc<Object> {
+    let handles: Vec<*const LimValue> = args.iter().map(|arg| Rc::as_ptr(arg) as *const LimValue).collect();
+    let result = unsafe { function(&API, handles.as_ptr(), handles.len()) };
+    if result.is_null() {
+        return Rc::new(Object::None);
+    }
+    unsafe { Rc::from_raw(result as *const Object) }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::raw::c_char;
+    use std::rc::Rc;
+
+    use super::call;
+    use lim_abi::{LimApi, LimValue};
+    use object::Object;
+
+    // Returns the kind of every argument, its display and, for numbers, its value as a float.
+    unsafe extern "C" fn describe(api: *const LimApi, args: *const *const LimValue, count: usize) -> *mut LimValue {
+        let api = &*api;
+        let mut described = vec![];
+        for index in 0..count {
+            let arg = *args.add(index);
+            let mut float = f64::NAN;
+            let number = if (api.as_float)(arg, &mut float) == 1 { (api.float)(float) } else { (api.none)() };
+            let parts = [(api.integer)((api.kind)(arg) as i64), (api.display)(arg), number];
+            let elements: Vec<*const LimValue> = parts.iter().map(|part| *part as *const LimValue).collect();
+            described.push((api.array)(elements.as_ptr(), elements.len()));
+            parts.iter().for_each(|part| (api.release)(*part));
+        }
+        let elements: Vec<*const LimValue> = described.iter().map(|value| *value as *const LimValue).collect();
+        let rtn = (api.array)(elements.as_ptr(), elements.len());
+        described.iter().for_each(|value| (api.release)(*value));
+        rtn
+    }
+
+    unsafe extern "C" fn fail(api: *const LimApi, _args: *const *const LimValue, _count: usize) -> *mut LimValue {
+        let message = "failed";
+        ((*api).error)(message.as_ptr() as *const c_char, message.len())
+    }
+
+    unsafe extern "C" fn nothing(_api: *const LimApi, _args: *const *const LimValue, _count: usize) -> *mut LimValue {
+        std::ptr::null_mut()
+    }
+
+    #[test]
+    fn natives_see_values_through_the_abi() {
+        let args = [Rc::new(Object::Integer(2)), Rc::new(Object::Float(0.5)), Rc::new(Object::String(String::from("text"))), Rc::new(Object::None)];
+        assert_eq!(call(describe, &args).to_string(), "[[1, 2, 2], [2, 0.5, 0.5], [3, text, None], [0, None, None]]");
+        assert_eq!(call(fail, &[]).to_string(), "Error(\"failed\")");
+        assert_eq!(call(nothing, &[]).to_string(), "None");
+    }
+}
@@ -1,45 +1,64 @@
-extern crate libloading;
-
+use std::cell::RefCell;
 use std::collections::HashMap;
 
-use object::{Object};
+use lexer::token::Token;
+use object::{Object, Table};
 use parser::node::Node;
 use std::rc::Rc;
 
+use crate::array;
+use crate::builtin;
+use crate::embed;
+use crate::map;
+use crate::module::{self, Engine};
+use crate::pattern;
+use crate::registry;
+use crate::vm;
+
 fn buildNatives() -> HashMap<String, Rc<Object>> {
     let mut natives = HashMap::new();
-    natives.insert(
-        String::from("print"),
-        Rc::new(
-            Object::Native(
-                String::from("../std/target/release/libstd.so"),
-                String::from("println"),
-            ),
-        ),
-    );
-    natives.insert(
-        String::from("native"),
-        Rc::new(
-            Object::Native(
-                String::from("../std/target/release/libstd.so"),
-                String::from("native"),
-            ),
-        ),
-    );
+    for name in registry::linked() {
+        natives.insert(name.to_owned(), Rc::new(Object::Linked(name)));
+    }
+    for (name, namespace) in builtin::namespaces() {
+        natives.insert(name, namespace);
+    }
+    natives.insert(String::from("import"), Rc::new(Object::Builtin(String::from("import"))));
+    natives.insert(String::from("native"), Rc::new(Object::Builtin(String::from("native"))));
+    for prefix in builtin::PREFIXES.iter() {
+        natives.insert(String::from(*prefix), Rc::new(Object::Builtin(String::from(*prefix))));
+    }
 
     natives
 }
 
-fn call_dynamic(lib: &str, symbol: &str, args: Vec<Rc<Object>>) -> Rc<Object> {
-    let lib = libloading::Library::new(lib).unwrap();
-    unsafe {
-        let func: libloading::Symbol<unsafe extern fn(Vec<Rc<Object>>) -> Rc<Object>> = lib.get(symbol.as_bytes()).unwrap();
-        func(args)
-    }
+// Natives are built once per program, changes made to them are seen by every module.
+thread_local! {
+    static NATIVES: RefCell<HashMap<String, Rc<Object>>> = RefCell::new(buildNatives());
+}
+
+pub fn is_native(name: &str) -> bool {
+    NATIVES.with(|natives| natives.borrow().contains_key(name))
+}
+
+pub fn native(name: &str) -> Option<Rc<Object>> {
+    NATIVES.with(|natives| natives.borrow().get(name).cloned())
+}
+
+// Returns the native the name was bound to before.
+pub fn define_native(name: &str, value: Rc<Object>) -> Option<Rc<Object>> {
+    NATIVES.with(|natives| natives.borrow_mut().insert(name.to_owned(), value))
+}
+
+pub fn remove_native(name: &str) {
+    NATIVES.with(|natives| natives.borrow_mut().remove(name));
+}
+
+pub fn reset_natives() {
+    NATIVES.with(|natives| *natives.borrow_mut() = buildNatives());
 }
 
 pub fn interpret(ast: Node, scope: &mut HashMap<String, Rc<Object>>) -> Rc<Object> {
-    let mut natives = buildNatives();
     match ast {
         Node::Member(node, string) => get(&interpret(*node, scope), string),
         Node::Program(nodes) => {
@@ -53,7 +72,13 @@ pub fn interpret(ast: Node, scope: &mut HashMap<String, Rc<Object>>) -> Rc<Objec
             let matched = interpret(*matched, scope);
             for arm in match_arms.iter() {
                 if let Node::MatchArm(matcher, program) = arm {
-                    if eq(&matched, &interpret(*matcher.to_owned(), scope)) {
+                    let mut targets = vec![];
+                    let fits = match &**matcher {
+                        Node::Identifier(_) => map::key_eq(&matched, &interpret(*matcher.to_owned(), scope)),
+                        matcher => pattern::destructure(matcher, &matched, scope, &mut targets).is_ok(),
+                    };
+                    if fits {
+                        pattern::assign(targets, scope);
                         return interpret(*program.to_owned(), scope);
                     }
                 }
@@ -61,6 +86,92 @@ pub fn interpret(ast: Node, scope: &mut HashMap<String, Rc<Object>>) -> Rc<Objec
             Rc::new(Object::None)
         }
         Node::Parenthesized(node) => interpret(*node, scope),
+        Node::Array(elements) | Node::Tuple(elements) => {
+            let mut objects = vec![];
+            for element in elements {
+                if let Node::Variadic(node) = element {
+                    let spread = interpret(*node, scope);
+                    match &*spread {
+                        Object::Array(spread) => objects.extend(spread.iter().cloned()),
+                        _ => return Rc::new(Object::Error(format!("Cannot spread {} into an array", spread.to_string()))),
+                    }
+                } else {
+                    objects.push(interpret(element, scope));
+                }
+            }
+            Rc::new(Object::Array(objects))
+        }
+        Node::Map(entries) => {
+            let mut table = Table::new();
+            for entry in entries {
+                if let Node::KeyValue(key, value) = entry {
+                    let key = interpret(*key, scope);
+                    let value = interpret(*value, scope);
+                    if let Err(error) = map::insert(&mut table, key, value) {
+                        return error;
+                    }
+                }
+            }
+            Rc::new(Object::Map(table))
+        }
+        Node::Set(elements) => {
+            let mut table = Table::new();
+            for element in elements {
+                let element = interpret(element, scope);
+                if let Err(error) = map::insert(&mut table, element, Rc::new(Object::None)) {
+                    return error;
+                }
+            }
+            Rc::new(Object::Set(table))
+        }
+        Node::Prefixed(prefix, literal) => {
+            let prefix = interpret(*prefix, scope);
+            let args = match *literal {
+                Node::Template(parts) => {
+                    let mut strings = vec![];
+                    let mut values = vec![];
+                    for part in parts {
+                        match part {
+                            Node::Literal(string, _) => strings.push(Rc::new(Object::String(string))),
+                            part => values.push(interpret(part, scope)),
+                        }
+                    }
+                    vec![Rc::new(Object::Array(strings)), Rc::new(Object::Array(values))]
+                }
+                literal => vec![interpret(literal, scope)],
+            };
+            call_prefix(&prefix, &args)
+        }
+        Node::Index(target, index) => {
+            let target = interpret(*target, scope);
+            if is_prefix(&target) {
+                let elements = match *index {
+                    Node::Empty => vec![],
+                    Node::FunctionArgs(elements) => elements,
+                    element => vec![element],
+                };
+                let mut objects = vec![];
+                for element in elements {
+                    if element != Node::Empty {
+                        objects.push(interpret(element, scope));
+                    }
+                }
+                return call_prefix(&target, &vec![Rc::new(Object::Array(objects))]);
+            }
+            match *index {
+                Node::Slice(start, end) => {
+                    let start = interpret(*start, scope);
+                    let end = interpret(*end, scope);
+                    get_slice(&target, &start, &end)
+                }
+                index => {
+                    let index = interpret(index, scope);
+                    get_index(&target, &index)
+                }
+            }
+        }
+        Node::Negation(node) => negate(interpret(*node, scope)),
+        Node::Variadic(_) => Rc::new(Object::Error(String::from("Variadic expressions are only allowed in calls and parameters"))),
         Node::EnumDefinition(name, variations) => {
             if let Node::Identifier(name) = *name {
                 scope.insert(name.clone(), Rc::new(Object::Enum(name.clone(), variations)));
@@ -72,44 +183,43 @@ pub fn interpret(ast: Node, scope: &mut HashMap<String, Rc<Object>>) -> Rc<Objec
                 let mut fields = HashMap::new();
                 fields.insert(String::from("self"), Rc::new(Object::Instance(HashMap::new())));
                 interpret(*body, &mut fields);
-                let mut prototype_fields = HashMap::new();
-                let mut class_fields = HashMap::new();
-                for (var_name, object) in fields.iter_mut() {
-                    if var_name == "self" {
-                        prototype_fields.insert(String::from("$class"), object.clone());
-                    } else {
-                        prototype_fields.insert(var_name.to_owned(), object.clone());
-                    }
-                }
-                class_fields.insert(String::from("$prototype"), Rc::new(Object::Instance(prototype_fields.clone())));
-                if let Object::Instance(self_fields) = &**fields.get("self").unwrap() {
-                    for (var_name, object) in self_fields.iter() {
-                        class_fields.insert(var_name.to_owned(), object.clone());
-                    }
-                }
-
-                scope.insert(name.clone(), Rc::new(Object::Class(class_fields)));
+                scope.insert(name.clone(), class(fields));
             }
             Rc::new(Object::None)
         }
-        Node::Addition(lhs, rhs) => add(interpret(*lhs, scope), interpret(*rhs, scope)),
-        Node::Multiplication(lhs, rhs) => multiply(interpret(*lhs, scope), interpret(*rhs, scope)),
-        Node::Number(number_string) => {
-            if number_string.contains('.') {
-                Rc::new(Object::Float(number_string.parse::<f64>().unwrap()))
-            } else {
-                Rc::new(Object::Integer(number_string.parse::<i64>().unwrap()))
-            }
-        }
-        Node::Identifier(literal_string) => scope.get(&literal_string).unwrap_or(natives.get(&literal_string).unwrap_or(&Rc::new(Object::None))).to_owned(),
+        Node::Addition(lhs, rhs) => operate("+", interpret(*lhs, scope), interpret(*rhs, scope)),
+        Node::Substraction(lhs, rhs) => operate("-", interpret(*lhs, scope), interpret(*rhs, scope)),
+        Node::Multiplication(lhs, rhs) => operate("*", interpret(*lhs, scope), interpret(*rhs, scope)),
+        Node::Division(lhs, rhs) => operate("/", interpret(*lhs, scope), interpret(*rhs, scope)),
+        Node::Modulus(lhs, rhs) => operate("%", interpret(*lhs, scope), interpret(*rhs, scope)),
+        Node::Number(number_string) => number(&number_string),
+        Node::Identifier(literal_string) => match scope.get(&literal_string) {
+            Some(value) => value.to_owned(),
+            None => native(&literal_string).unwrap_or_else(|| Rc::new(Object::None)),
+        },
         Node::FunctionDefinition(args, body) => Rc::new(Object::Function(args, body)),
         Node::Call(callee, args) => {
-            let mut callee_object = interpret(*callee, scope);
+            let callee_object = interpret(*callee, scope);
             let mut arg_objects = vec![];
-            for arg in args.iter() {
-                arg_objects.push(interpret(arg.to_owned(), scope))
+            let mut named_objects = vec![];
+            for arg in args {
+                match arg {
+                    Node::Assignment(name, value) if matches!(*name, Node::Identifier(_)) => {
+                        if let Node::Identifier(name) = *name {
+                            named_objects.push((name, interpret(*value, scope)));
+                        }
+                    }
+                    Node::Variadic(node) => {
+                        let spread = interpret(*node, scope);
+                        match &*spread {
+                            Object::Array(elements) => arg_objects.extend(elements.iter().cloned()),
+                            _ => return Rc::new(Object::Error(format!("Cannot spread {} as arguments", spread.to_string()))),
+                        }
+                    }
+                    arg => arg_objects.push(interpret(arg, scope)),
+                }
             }
-            call(&callee_object, &arg_objects, &callee_object)
+            call_named(&callee_object, &arg_objects, &named_objects, &callee_object)
         }
         Node::Assignment(lhs, rhs) => {
             match *lhs {
@@ -118,34 +228,197 @@ pub fn interpret(ast: Node, scope: &mut HashMap<String, Rc<Object>>) -> Rc<Objec
                     scope.insert(variable_name, value);
                     Rc::new(Object::None)
                 }
-                Node::Member(instance, field_name) => {
-                    let mut instance = interpret(*instance, scope);
+                Node::Index(target, index) => {
+                    let target = interpret(*target, scope);
+                    let index = interpret(*index, scope);
                     let value = interpret(*rhs, scope);
-                    let mut instance = unsafe {
-                        Rc::get_mut_unchecked(&mut instance)
-                    };
-                    match instance {
-                        Object::Class(class_fields) => {
-                            class_fields.insert(field_name, value);
-                        }
-                        Object::Instance(instance_fields) => {
-                            instance_fields.insert(field_name, value);
-                        }
-                        _ => {
-                            unimplemented!("member assignment on {:?}", instance);
-                        }
-                    }
+                    set_index(target, &index, value)
+                }
+                pattern => {
+                    let value = interpret(*rhs, scope);
+                    pattern::bind(&pattern, &value, scope)
+                }
+            }
+        }
+        Node::CompoundAssignment(lhs, operator, rhs) => {
+            match *lhs {
+                Node::Identifier(variable_name) => {
+                    let current = interpret(Node::Identifier(variable_name.clone()), scope);
+                    let value = compound(&operator, current, interpret(*rhs, scope));
+                    scope.insert(variable_name, value);
                     Rc::new(Object::None)
                 }
+                Node::Member(instance, field_name) => {
+                    let instance = interpret(*instance, scope);
+                    let current = get(&instance, field_name.clone());
+                    let value = compound(&operator, current, interpret(*rhs, scope));
+                    set_member(instance, field_name, value)
+                }
+                Node::Index(target, index) => {
+                    let target = interpret(*target, scope);
+                    let index = interpret(*index, scope);
+                    let current = get_index(&target, &index);
+                    if let Object::Error(_) = *current {
+                        return current;
+                    }
+                    let value = compound(&operator, current, interpret(*rhs, scope));
+                    set_index(target, &index, value)
+                }
                 _ => unimplemented!("assigment on {:?}", lhs),
             }
         }
-        _ => Rc::new(Object::None),
+        Node::Empty => Rc::new(Object::None),
+        node => Rc::new(Object::Error(format!("Cannot evaluate {}", node.kind()))),
+    }
+}
+
+// The bindings of a class body become the fields of its prototype, with `self` as its `$class`,
+// and the fields set on `self` become the fields of the class.
+pub fn class(fields: HashMap<String, Rc<Object>>) -> Rc<Object> {
+    let mut prototype_fields = HashMap::new();
+    let mut class_fields = HashMap::new();
+    for (var_name, object) in fields.iter() {
+        if var_name == "self" {
+            prototype_fields.insert(String::from("$class"), object.clone());
+        } else {
+            prototype_fields.insert(var_name.to_owned(), object.clone());
+        }
+    }
+    class_fields.insert(String::from("$prototype"), Rc::new(Object::Instance(prototype_fields.clone())));
+    if let Object::Instance(self_fields) = &**fields.get("self").unwrap() {
+        for (var_name, object) in self_fields.iter() {
+            class_fields.insert(var_name.to_owned(), object.clone());
+        }
+    }
+    Rc::new(Object::Class(class_fields))
+}
+
+pub fn set_member(mut instance: Rc<Object>, field_name: String, value: Rc<Object>) -> Rc<Object> {
+    let instance = unsafe {
+        Rc::get_mut_unchecked(&mut instance)
+    };
+    match instance {
+        Object::Class(class_fields) => {
+            class_fields.insert(field_name, value);
+        }
+        Object::Instance(instance_fields) => {
+            instance_fields.insert(field_name, value);
+        }
+        _ => {
+            return Rc::new(Object::Error(format!("Cannot set {} on {}", field_name, instance.to_string())));
+        }
+    }
+    Rc::new(Object::None)
+}
+
+pub fn get_index(target: &Rc<Object>, index: &Rc<Object>) -> Rc<Object> {
+    match &**target {
+        Object::Array(elements) => array::get(elements, index),
+        Object::String(string) => match **index {
+            Object::Integer(position) => {
+                let length = string.chars().count();
+                match array::position(position, length) {
+                    Some(position) => Rc::new(Object::String(string.chars().nth(position).unwrap().to_string())),
+                    None => Rc::new(Object::Error(format!("Index {} out of range for string of length {}", position, length))),
+                }
+            }
+            _ => Rc::new(Object::Error(format!("String index {} is not an Integer", index.to_string()))),
+        },
+        Object::Map(table) => match map::lookup(table, index) {
+            Ok(Some(value)) => value,
+            Ok(None) => Rc::new(Object::Error(format!("Key {} does not exist", index.to_string()))),
+            Err(error) => error,
+        },
+        Object::Class(_) | Object::Instance(_) if has_method(target, "$get") => call(&get(target, String::from("$get")), &vec![index.to_owned()], target),
+        _ => Rc::new(Object::Error(format!("Cannot index {}", target.to_string()))),
+    }
+}
+
+pub fn get_slice(target: &Rc<Object>, start: &Rc<Object>, end: &Rc<Object>) -> Rc<Object> {
+    match &**target {
+        Object::Array(elements) => array::slice(elements, start, end),
+        Object::String(string) => {
+            let length = string.chars().count();
+            match (array::bound(start, length, 0), array::bound(end, length, length)) {
+                (Ok(start), Ok(end)) => Rc::new(Object::String(string.chars().skip(start).take(end.saturating_sub(start)).collect())),
+                (Err(error), _) | (_, Err(error)) => error,
+            }
+        }
+        Object::Class(_) | Object::Instance(_) if has_method(target, "$slice") => call(&get(target, String::from("$slice")), &vec![start.to_owned(), end.to_owned()], target),
+        _ => Rc::new(Object::Error(format!("Cannot slice {}", target.to_string()))),
+    }
+}
+
+pub fn set_index(mut target: Rc<Object>, index: &Rc<Object>, value: Rc<Object>) -> Rc<Object> {
+    if has_method(&target, "$set") {
+        return call(&get(&target, String::from("$set")), &vec![index.to_owned(), value], &target);
+    }
+    if let Object::Map(_) = *target {
+        return map::set(&target, index.to_owned(), value).err().unwrap_or_else(|| Rc::new(Object::None));
+    }
+    let target = unsafe {
+        Rc::get_mut_unchecked(&mut target)
+    };
+    match target {
+        Object::Array(elements) => array::set(elements, index, value),
+        _ => Rc::new(Object::Error(format!("Cannot assign to an index of {}", target.to_string()))),
+    }
+}
+
+fn method_name(operator: &str) -> &'static str {
+    match operator {
+        "+" => "$add",
+        "-" => "$sub",
+        "*" => "$mul",
+        "/" => "$div",
+        "%" => "$mod",
+        _ => unreachable!(),
+    }
+}
+
+fn has_method(object: &Rc<Object>, name: &str) -> bool {
+    match &**object {
+        Object::Class(fields) | Object::Instance(fields) => fields.contains_key(name),
+        _ => false,
+    }
+}
+
+pub fn operate(operator: &str, lhs: Rc<Object>, rhs: Rc<Object>) -> Rc<Object> {
+    let method = method_name(operator);
+    if has_method(&lhs, method) {
+        return call(&get(&lhs, String::from(method)), &vec![rhs], &lhs);
+    }
+    match operator {
+        "+" => add(lhs, rhs),
+        "-" => subtract(lhs, rhs),
+        "*" => multiply(lhs, rhs),
+        "/" => divide(lhs, rhs),
+        "%" => modulo(lhs, rhs),
+        _ => unreachable!(),
+    }
+}
+
+// `x += y` prefers an in-place `$add_assign` method and falls back to `$add` or the native operation.
+// The in-place method changes `x`, which stays the value assigned whatever the method returns.
+pub fn compound(operator: &str, lhs: Rc<Object>, rhs: Rc<Object>) -> Rc<Object> {
+    let method = format!("{}_assign", method_name(operator));
+    if has_method(&lhs, &method) {
+        let rtn = call(&get(&lhs, method), &vec![rhs], &lhs);
+        match *rtn {
+            Object::Error(_) => rtn,
+            _ => lhs,
+        }
+    } else {
+        operate(operator, lhs, rhs)
     }
 }
 
 fn add(lhs: Rc<Object>, rhs: Rc<Object>) -> Rc<Object> {
     match &*lhs {
+        Object::String(lhs_value) => match &*rhs {
+            Object::String(rhs_value) => Rc::new(Object::String(format!("{}{}", lhs_value, rhs_value))),
+            _ => Rc::new(Object::None),
+        },
         Object::Integer(lhs_value) => match *rhs {
             Object::Integer(rhs_value) => Rc::new(Object::Integer(lhs_value + rhs_value)),
             Object::Float(rhs_value) => Rc::new(Object::Float(*lhs_value as f64 + rhs_value)),
@@ -160,16 +433,117 @@ fn add(lhs: Rc<Object>, rhs: Rc<Object>) -> Rc<Object> {
     }
 }
 
-fn eq(lhs: &Rc<Object>, rhs: &Rc<Object>) -> bool {
-    match &**lhs {
-        Object::EnumVariant(string_lhs) => match &**rhs {
-            Object::EnumVariant(string_rhs) => string_lhs == string_rhs,
-            _ => false,
+fn subtract(lhs: Rc<Object>, rhs: Rc<Object>) -> Rc<Object> {
+    match *lhs {
+        Object::Integer(lhs_value) => match *rhs {
+            Object::Integer(rhs_value) => Rc::new(Object::Integer(lhs_value - rhs_value)),
+            Object::Float(rhs_value) => Rc::new(Object::Float(lhs_value as f64 - rhs_value)),
+            _ => Rc::new(Object::None),
+        },
+        Object::Float(lhs_value) => match *rhs {
+            Object::Integer(rhs_value) => Rc::new(Object::Float(lhs_value - rhs_value as f64)),
+            Object::Float(rhs_value) => Rc::new(Object::Float(lhs_value - rhs_value)),
+            _ => Rc::new(Object::None),
+        },
+        _ => Rc::new(Object::None),
+    }
+}
+
+fn divide(lhs: Rc<Object>, rhs: Rc<Object>) -> Rc<Object> {
+    match *lhs {
+        Object::Integer(lhs_value) => match *rhs {
+            Object::Integer(0) => Rc::new(Object::Error(String::from("Division by zero"))),
+            Object::Integer(rhs_value) => Rc::new(Object::Float(lhs_value as f64 / rhs_value as f64)),
+            Object::Float(rhs_value) => Rc::new(Object::Float(lhs_value as f64 / rhs_value)),
+            _ => Rc::new(Object::None),
         },
+        Object::Float(lhs_value) => match *rhs {
+            Object::Integer(rhs_value) => Rc::new(Object::Float(lhs_value / rhs_value as f64)),
+            Object::Float(rhs_value) => Rc::new(Object::Float(lhs_value / rhs_value)),
+            _ => Rc::new(Object::None),
+        },
+        _ => Rc::new(Object::None),
+    }
+}
+
+// The result of a modulus has the sign of the divisor, `-7 % 3` is `2`.
+fn modulo(lhs: Rc<Object>, rhs: Rc<Object>) -> Rc<Object> {
+    match *lhs {
+        Object::Integer(lhs_value) => match *rhs {
+            Object::Integer(0) => Rc::new(Object::Error(String::from("Modulus by zero"))),
+            Object::Integer(rhs_value) => {
+                // The remainder takes the sign of the divisor, computed without overflowing on extremes.
+                let remainder = lhs_value.wrapping_rem(rhs_value);
+                Rc::new(Object::Integer(if remainder != 0 && (remainder < 0) != (rhs_value < 0) { remainder + rhs_value } else { remainder }))
+            }
+            Object::Float(rhs_value) => Rc::new(Object::Float(((lhs_value as f64 % rhs_value) + rhs_value) % rhs_value)),
+            _ => Rc::new(Object::None),
+        },
+        Object::Float(lhs_value) => match *rhs {
+            Object::Integer(rhs_value) => Rc::new(Object::Float(((lhs_value % rhs_value as f64) + rhs_value as f64) % rhs_value as f64)),
+            Object::Float(rhs_value) => Rc::new(Object::Float(((lhs_value % rhs_value) + rhs_value) % rhs_value)),
+            _ => Rc::new(Object::None),
+        },
+        _ => Rc::new(Object::None),
+    }
+}
+
+pub fn number(number_string: &str) -> Rc<Object> {
+    let digits = number_string.replace('_', "");
+    let radix = Token::radix(&digits);
+    if radix != 10 {
+        match i64::from_str_radix(&digits[2..], radix) {
+            Ok(number) => Rc::new(Object::Integer(number)),
+            Err(_) => Rc::new(Object::Error(format!("Integer literal {} is out of range", number_string))),
+        }
+    } else if digits.contains(['.', 'e', 'E']) {
+        Rc::new(Object::Float(digits.parse::<f64>().unwrap()))
+    } else {
+        match digits.parse::<i64>() {
+            Ok(number) => Rc::new(Object::Integer(number)),
+            Err(_) => Rc::new(Object::Error(format!("Integer literal {} is out of range", number_string))),
+        }
+    }
+}
+
+// Whether square brackets after the object build a value instead of indexing it. Only a class is,
+// its instances are indexed through `$get` like any other value.
+pub fn is_prefix(object: &Rc<Object>) -> bool {
+    match &**object {
+        Object::Builtin(name) => builtin::PREFIXES.contains(&name.as_str()),
+        Object::Class(_) => has_method(object, "$prefix"),
         _ => false,
     }
 }
 
+pub fn call_prefix(prefix: &Rc<Object>, args: &Vec<Rc<Object>>) -> Rc<Object> {
+    match &**prefix {
+        Object::Class(_) | Object::Instance(_) if has_method(prefix, "$prefix") => call(&get(prefix, String::from("$prefix")), args, prefix),
+        _ => call(prefix, args, prefix),
+    }
+}
+
+pub fn negate(value: Rc<Object>) -> Rc<Object> {
+    match *value {
+        Object::Integer(value) => Rc::new(Object::Integer(-value)),
+        Object::Float(value) => Rc::new(Object::Float(-value)),
+        Object::Error(_) => value,
+        _ => Rc::new(Object::None),
+    }
+}
+
+pub fn is_truthy(value: &Rc<Object>) -> bool {
+    match &**value {
+        Object::None | Object::Error(_) => false,
+        Object::Integer(value) => *value != 0,
+        Object::Float(value) => *value != 0.0,
+        Object::String(value) => !value.is_empty(),
+        Object::Array(elements) => !elements.is_empty(),
+        Object::Map(table) | Object::Set(table) => !table.is_empty(),
+        _ => true,
+    }
+}
+
 fn multiply(lhs: Rc<Object>, rhs: Rc<Object>) -> Rc<Object> {
     match *lhs {
         Object::Integer(lhs_value) => match *rhs {
@@ -187,13 +561,13 @@ fn multiply(lhs: Rc<Object>, rhs: Rc<Object>) -> Rc<Object> {
 }
 
 
-fn get(obj: &Rc<Object>, string: String) -> Rc<Object> {
+pub fn get(obj: &Rc<Object>, string: String) -> Rc<Object> {
     match &**obj {
         Object::Enum(name, variants) => {
             for variant in variants.iter() {
                 if let Node::Identifier(variant_name) = variant {
                     if *variant_name == string {
-                        return Rc::new(Object::EnumVariant(format!("{}.{}", name, variant_name)));
+                        return Rc::new(Object::EnumVariant(format!("{}.{}", name, variant_name), vec![]));
                     }
                 } else {
                     unimplemented!();
@@ -202,7 +576,19 @@ fn get(obj: &Rc<Object>, string: String) -> Rc<Object> {
             Rc::new(Object::Error(format!("Variant {} does not exist for enum {}", string, name)))
         }
         Object::Instance(fields) => {
-            let field = fields.get(&string).unwrap().to_owned();
+            let field = match fields.get(&string) {
+                Some(field) => field.to_owned(),
+                None => match fields.get("$class") {
+                    Some(class) => {
+                        let field = get(class, string);
+                        return match &*field {
+                            Object::BoundFunction(_, function) => Rc::new(Object::BoundFunction(obj.to_owned(), function.to_owned())),
+                            _ => field,
+                        };
+                    }
+                    None => return Rc::new(Object::Error(format!("Instance has no field {}", string))),
+                },
+            };
             if let Object::Function(_, _) = *field {
                 Rc::new(Object::BoundFunction(obj.to_owned(), field))
             } else {
@@ -210,50 +596,309 @@ fn get(obj: &Rc<Object>, string: String) -> Rc<Object> {
             }
         }
         Object::Class(fields) => {
-            let field = fields.get(&string).unwrap().to_owned();
+            let field = match fields.get(&string) {
+                Some(field) => field.to_owned(),
+                None => return Rc::new(Object::Error(format!("Class has no field {}", string))),
+            };
             if let Object::Function(_, _) = *field {
                 Rc::new(Object::BoundFunction(obj.to_owned(), field))
             } else {
                 field
             }
         }
-        _ => unimplemented!("cannot get {:?} on {:?}", string, obj),
+        Object::Array(_) => {
+            if array::METHODS.contains(&string.as_str()) {
+                Rc::new(Object::BoundFunction(obj.to_owned(), Rc::new(Object::Builtin(string))))
+            } else {
+                Rc::new(Object::Error(format!("Array has no method {}", string)))
+            }
+        }
+        Object::Map(_) => {
+            if map::MAP_METHODS.contains(&string.as_str()) {
+                Rc::new(Object::BoundFunction(obj.to_owned(), Rc::new(Object::Builtin(string))))
+            } else {
+                Rc::new(Object::Error(format!("Map has no method {}", string)))
+            }
+        }
+        Object::Module(name, fields) => match fields.get(&string) {
+            Some(field) => field.to_owned(),
+            None => Rc::new(Object::Error(format!("Module {} has no binding {}", name, string))),
+        },
+        Object::Set(_) => {
+            if map::SET_METHODS.contains(&string.as_str()) {
+                Rc::new(Object::BoundFunction(obj.to_owned(), Rc::new(Object::Builtin(string))))
+            } else {
+                Rc::new(Object::Error(format!("Set has no method {}", string)))
+            }
+        }
+        Object::Error(_) => obj.to_owned(),
+        _ => Rc::new(Object::Error(format!("Cannot get {} on {}", string, obj.to_string()))),
     }
 }
 
-fn call(callee: &Rc<Object>, args: &Vec<Rc<Object>>, instance: &Rc<Object>) -> Rc<Object> {
+pub fn call(callee: &Rc<Object>, args: &Vec<Rc<Object>>, instance: &Rc<Object>) -> Rc<Object> {
+    call_named(callee, args, &vec![], instance)
+}
+
+pub fn parameter_name(parameter: &Node) -> Option<&String> {
+    match parameter {
+        Node::Identifier(name) => Some(name),
+        Node::Assignment(name, _) | Node::Variadic(name) => match &**name {
+            Node::Identifier(name) => Some(name),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// Positional arguments fill parameters in order until a variadic parameter collects the rest,
+// parameters declared after it can only be given by name. Defaults are evaluated in the call scope.
+fn bind(parameters: &[Node], args: &[Rc<Object>], named: &[(String, Rc<Object>)], scope: &mut HashMap<String, Rc<Object>>) -> Result<(), Rc<Object>> {
+    let mut positional = args.iter();
+    let mut accepted = 0;
+    for parameter in parameters.iter() {
+        let name = match parameter_name(parameter) {
+            Some(name) => name.to_owned(),
+            None => return Err(Rc::new(Object::Error(format!("Invalid parameter {:?}", parameter)))),
+        };
+        if let Node::Variadic(_) = parameter {
+            scope.insert(name, Rc::new(Object::Array(positional.by_ref().cloned().collect())));
+            accepted = args.len();
+        } else if let Some(arg) = positional.next() {
+            scope.insert(name, arg.to_owned());
+            accepted += 1;
+        }
+    }
+    if args.len() > accepted {
+        return Err(Rc::new(Object::Error(format!("Expected at most {} arguments, got {}", accepted, args.len()))));
+    }
+    for (name, value) in named.iter() {
+        match parameters.iter().find(|parameter| parameter_name(parameter) == Some(name)) {
+            None | Some(Node::Variadic(_)) => return Err(Rc::new(Object::Error(format!("Unexpected argument {}", name)))),
+            Some(_) if scope.contains_key(name) => return Err(Rc::new(Object::Error(format!("Argument {} given more than once", name)))),
+            Some(_) => {
+                scope.insert(name.to_owned(), value.to_owned());
+            }
+        }
+    }
+    for parameter in parameters.iter() {
+        match parameter {
+            Node::Identifier(name) if !scope.contains_key(name) => return Err(Rc::new(Object::Error(format!("Missing argument {}", name)))),
+            Node::Assignment(name, default) => {
+                if let Node::Identifier(name) = &**name {
+                    if !scope.contains_key(name) {
+                        let value = interpret(*default.to_owned(), scope);
+                        scope.insert(name.to_owned(), value);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+pub fn call_named(callee: &Rc<Object>, args: &Vec<Rc<Object>>, named: &Vec<(String, Rc<Object>)>, instance: &Rc<Object>) -> Rc<Object> {
     match &**callee {
+        Object::Function(_, _) | Object::BoundFunction(_, _) => {}
+        _ if !named.is_empty() => return Rc::new(Object::Error(format!("{} does not accept named arguments", callee.to_string()))),
+        _ => {}
+    }
+    match &**callee {
+        Object::Builtin(name) => match &**instance {
+            Object::Array(_) => array::call_method(name, instance, args),
+            Object::Map(_) => map::call_map_method(name, instance, args),
+            Object::Set(_) => map::call_set_method(name, instance, args),
+            _ => builtin::call(name, args),
+        },
         Object::Native(lib, symbol) => {
-            call_dynamic(lib, symbol, args.to_vec())
+            registry::call(lib, symbol, args)
         }
+        Object::Linked(name) => registry::call_linked(name, args),
+        Object::Host(name, index) => embed::call_host(name, *index, args),
         Object::Class(fields) => {
             fields.get("$prototype").unwrap().to_owned()
         }
+        Object::Function(_, _) if module::engine() == Engine::Vm => vm::call(callee, args, named, instance),
         Object::Function(argument_names, body) => {
             let mut rtn = Rc::new(Object::None);
             let mut scope = HashMap::new();
 
-            for (index, argument_name) in argument_names.iter().enumerate() {
-                if let Node::Identifier(string) = argument_name {
-                    if let Some(arg) = args.get(index) {
-                        scope.insert(string.to_string(), arg.to_owned());
-                    } else {
-                        scope.insert(string.to_string(), Rc::new(Object::None));
-                    }
-                } else {
-                    unreachable!();
-                }
-            }
-
             scope.insert("self".to_string(), instance.to_owned());
+            if let Err(error) = bind(argument_names, args, named, &mut scope) {
+                return error;
+            }
             for node in body.iter() {
                 rtn = interpret(node.to_owned(), &mut scope);
             }
             rtn
         }
         Object::BoundFunction(instance, function) => {
-            call(&function, args, &instance)
+            call_named(function, args, named, instance)
+        }
+        // Calling a variant gives it a payload, `Shape.Circle(2)`.
+        Object::EnumVariant(name, payload) if payload.is_empty() && !args.is_empty() => Rc::new(Object::EnumVariant(name.to_owned(), args.to_vec())),
+        Object::EnumVariant(_, _) => callee.to_owned(),
+        Object::Error(_) => callee.to_owned(),
+        _ => Rc::new(Object::Error(format!("{} is not callable", callee.to_string()))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::run;
+
+    const EXTREMES: &str = "max = 9223372036854775807\nmin = 0 - max - 1\n";
+
+    // The remainder takes the sign of the divisor, with both operands at the extremes too.
+    #[test]
+    fn modulo_is_floored_without_overflowing() {
+        assert_eq!(run("[7 % 3, 0 - 7 % 3, 7 % (0 - 3), (0 - 7) % (0 - 3), 6 % 3]"), "[1, -1, -2, -1, 0]");
+        assert_eq!(run("[(0 - 7) % 3, 7.5 % 2, (0 - 7.5) % 2]"), "[2, 1.5, 0.5]");
+        assert_eq!(run(&format!("{}[(max - 1) % max, max % max, min % (0 - 1), min % max, max % min, min % min]", EXTREMES)), "[9223372036854775806, 0, 0, 9223372036854775806, -1, 0]");
+        assert_eq!(run("1 % 0"), "Error(\"Modulus by zero\")");
+    }
+
+    #[test]
+    fn numeric_literals() {
+        assert_eq!(run("[0x1F, 0xff_ff, 0o17, 0b101, -0x10]"), "[31, 65535, 15, 5, -16]");
+        assert_eq!(run("[1_000_000, 1_0.5, 1e3, 1E2, 2.5e-2, 1.5e400]"), "[1000000, 10.5, 1000, 100, 0.025, inf]");
+        for literal in ["0x", "0b2", "1_", "1e"] {
+            assert_eq!(run(literal), "Error(\"Syntax error in <test>\")", "{}", literal);
         }
-        _ => unimplemented!(),
+    }
+
+    // An out of range literal is an error value, negating it keeps the error.
+    #[test]
+    fn out_of_range_literals_are_errors() {
+        assert_eq!(run("9223372036854775808"), "Error(\"Integer literal 9223372036854775808 is out of range\")");
+        assert_eq!(run("-9223372036854775808"), "Error(\"Integer literal 9223372036854775808 is out of range\")");
+        assert_eq!(run("0xFFFFFFFFFFFFFFFFF"), "Error(\"Integer literal 0xFFFFFFFFFFFFFFFFF is out of range\")");
+    }
+
+    const TEXT: &str = "text = 'héllo✓'\n";
+
+    // Strings are indexed and sliced by characters, not bytes.
+    #[test]
+    fn strings_index_and_slice_characters() {
+        assert_eq!(run(&format!("{}[text[0], text[1], text[5], text[-1], text[-6]]", TEXT)), "[h, é, ✓, ✓, h]");
+        assert_eq!(run(&format!("{}[text[1:3], text[-2:], text[:-4], text[:]]", TEXT)), "[él, o✓, hé, héllo✓]");
+        assert_eq!(run(&format!("{}[text[10:], text[-10:2], text[3:1]]", TEXT)), "[, hé, ]");
+    }
+
+    #[test]
+    fn strings_report_bad_indices() {
+        assert_eq!(run(&format!("{}text[6]", TEXT)), "Error(\"Index 6 out of range for string of length 6\")");
+        assert_eq!(run(&format!("{}text[-7]", TEXT)), "Error(\"Index -7 out of range for string of length 6\")");
+        assert_eq!(run(&format!("{}text['a']", TEXT)), "Error(\"String index a is not an Integer\")");
+        assert_eq!(run(&format!("{}text[0:'a']", TEXT)), "Error(\"Slice bound a is not an Integer\")");
+        assert_eq!(run(&format!("{}(text[0] = 'x')", TEXT)), "Error(\"Cannot assign to an index of héllo✓\")");
+    }
+
+    // Indexing an instance calls its `$get`, assigning calls its `$set`, a compound assignment both.
+    #[test]
+    fn index_dispatches_to_get_and_set() {
+        let code = "class Grid {
+  cells = {}
+  $get = (key) { self.cells.get(key) }
+  $set = (key, value) { self.cells[key] = value * 2 }
+}
+grid = Grid()
+grid['a'] = 1
+grid['a'] += 3
+[grid['a'], grid['missing'], grid.cells]";
+        assert_eq!(run(code), "[10, None, {a: 10}]");
+        assert_eq!(run("cells = {'a': 1}\ncells['b']"), "Error(\"Key b does not exist\")");
+        assert_eq!(run("cells = {'a': 1}\ncells['b'] += 1"), "Error(\"Key b does not exist\")");
+        assert_eq!(run("number = 1\n[(number[0]), (number[0] = 2)]"), "[Error(\"Cannot index 1\"), Error(\"Cannot assign to an index of 1\")]");
+    }
+
+    // Calls, indexing and member accesses chain on any value, the result of a call included.
+    #[test]
+    fn postfix_operators_chain() {
+        let code = "class Tree {
+  value = 1
+  children = []
+  c = () { self.value * 100 }
+  b = (n) { [self, n] }
+}
+a = Tree()
+a.value = 7
+[a.b(1)[0].c(), a.b(2)[1], a.b(3)[0].children.len()]";
+        assert_eq!(run(code), "[700, 2, 0]");
+        let code = "pairs = () { [[1, 2], [3, 4]] }
+double = () { (x) { x * 2 } }
+table = () { {'k': [5, 6]} }
+[pairs()[1][0], pairs()[0][-1], pairs()[1:][0][1], double()(21), table()['k'][1], table().keys()[0], [3, 1].map((x) { x + 1 })[0]]";
+        assert_eq!(run(code), "[3, 2, 4, 42, 6, k, 4]");
+    }
+
+    const ARGUMENTS: &str = "f = (a, b = 2, rest...) { [a, b, rest] }
+g = (a, b) { [a, b] }
+class Point {
+  x = 0
+  move = (dx, dy = 0, scale = 1) { [self.x + dx * scale, dy] }
+}
+p = Point()
+";
+
+    #[test]
+    fn arguments_bind_by_position_and_name() {
+        assert_eq!(run(&format!("{}[f(1), f(1, 3), f(1, 3, 4, 5), f(b = 5, a = 0), f(1, b = 9)]", ARGUMENTS)), "[[1, 2, []], [1, 3, []], [1, 3, [4, 5]], [0, 5, []], [1, 9, []]]");
+        assert_eq!(run(&format!("{}[p.move(1), p.move(1, scale = 3), p.move(dy = 2, dx = 4)]", ARGUMENTS)), "[[1, 0], [3, 0], [4, 2]]");
+        assert_eq!(run(&format!("{}move = p.move\n[move(dy = 2, dx = 1), [1].map(move)]", ARGUMENTS)), "[[1, 2], [[1, 0]]]");
+    }
+
+    #[test]
+    fn arguments_report_mismatches() {
+        let call = |code: &str| run(&format!("{}{}", ARGUMENTS, code));
+        assert_eq!(call("g(1, 2, 3)"), "Error(\"Expected at most 2 arguments, got 3\")");
+        assert_eq!(call("p.move(1, 2, 3, 4)"), "Error(\"Expected at most 3 arguments, got 4\")");
+        assert_eq!(call("g(1)"), "Error(\"Missing argument b\")");
+        assert_eq!(call("f()"), "Error(\"Missing argument a\")");
+        assert_eq!(call("f(1, c = 2)"), "Error(\"Unexpected argument c\")");
+        assert_eq!(call("p.move(nope = 1)"), "Error(\"Unexpected argument nope\")");
+        assert_eq!(call("f(1, a = 2)"), "Error(\"Argument a given more than once\")");
+        assert_eq!(call("f(1, 2, b = 3)"), "Error(\"Argument b given more than once\")");
+        assert_eq!(call("p.move(1, dx = 2)"), "Error(\"Argument dx given more than once\")");
+    }
+
+    #[test]
+    fn compound_assignment_keeps_the_target_of_an_in_place_method() {
+        let code = "class Total {
+  sum = 0
+  $add_assign = (n) {
+    self.sum = self.sum + n
+    'ignored'
+  }
+}
+total = Total()
+total += 5
+total += 2
+total.sum";
+        assert_eq!(run(code), "7");
+    }
+
+    #[test]
+    fn compound_assignment_assigns_the_result_of_add() {
+        let code = "class Vector {
+  x = 0
+  $add = (other) { other }
+}
+vector = Vector()
+vector += 3
+vector";
+        assert_eq!(run(code), "3");
+    }
+
+    #[test]
+    fn compound_assignment_reports_errors_of_an_in_place_method() {
+        let code = "class Broken {
+  $add_assign = (n) { n / 0 }
+}
+broken = Broken()
+broken += 1
+broken";
+        assert_eq!(run(code), "Error(\"Division by zero\")");
     }
 }
@@ -0,0 +1,28 @@
+#![feature(get_mut_unchecked)]
+mod array;
+mod builtin;
+mod compiler;
+mod convert;
+mod embed;
+mod extension;
+mod map;
+mod pattern;
+mod resolver;
+mod symbols;
+#[cfg(test)]
+mod testing;
+
+pub mod cli;
+pub mod dump;
+pub mod interpreter;
+pub mod lint;
+pub mod lsp;
+pub mod manifest;
+pub mod module;
+pub mod registry;
+pub mod repl;
+pub mod vm;
+
+pub use convert::{FromObject, IntoObject};
+pub use embed::{Interpreter, IntoArgs, IntoNative};
+pub use object::Object;
@@ -0,0 +1,648 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::rc::Rc;
+
+use serde_json::{json, Value};
+
+use crate::dump;
+use crate::interpreter::{is_native, parameter_name};
+use lexer::span::{Position, Span};
+use lexer::token::Token;
+use parser::node::Node;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Severity {
+    Off,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    fn parse(level: &str) -> Option<Severity> {
+        match level {
+            "off" => Some(Severity::Off),
+            "warning" => Some(Severity::Warning),
+            "error" => Some(Severity::Error),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Severity::Off => "off",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+}
+
+pub const RULES: [(&str, Severity); 6] = [
+    ("undefined-variable", Severity::Error),
+    ("unused-assignment", Severity::Warning),
+    ("shadowed-parameter", Severity::Warning),
+    ("undeclared-field", Severity::Warning),
+    ("wrong-arity", Severity::Error),
+    ("unreachable-code", Severity::Warning),
+];
+
+pub struct Config {
+    severities: HashMap<&'static str, Severity>,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config { severities: RULES.iter().cloned().collect() }
+    }
+}
+
+impl Config {
+    // Reads the `[lint]` table of lim.toml, mapping rule names to `off`, `warning` or `error`.
+    pub fn new(levels: &BTreeMap<String, String>) -> Result<Config, String> {
+        let mut config = Config::default();
+        for (rule, level) in levels.iter() {
+            let rule = match RULES.iter().find(|(name, _severity)| name == rule) {
+                Some((name, _severity)) => *name,
+                None => return Err(format!("Unknown lint rule {}", rule)),
+            };
+            match Severity::parse(level) {
+                Some(severity) => config.severities.insert(rule, severity),
+                None => return Err(format!("Invalid level {} for lint rule {}, expected off, warning or error", level, rule)),
+            };
+        }
+        Ok(config)
+    }
+
+    fn severity(&self, rule: &str) -> Severity {
+        self.severities.get(rule).cloned().unwrap_or(Severity::Off)
+    }
+}
+
+#[derive(Debug)]
+pub struct Diagnostic {
+    pub rule: &'static str,
+    pub severity: Severity,
+    pub message: String,
+    pub span: Span,
+}
+
+impl Diagnostic {
+    pub fn to_json(&self, file: &str) -> Value {
+        json!({ "file": file, "rule": self.rule, "severity": self.severity.name(), "message": self.message, "span": dump::span(&self.span) })
+    }
+}
+
+#[derive(Clone, Default)]
+struct Binding {
+    parameters: Option<Vec<Node>>,
+    pending: Vec<*const Node>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Module,
+    Class,
+    Function,
+}
+
+struct Class {
+    name: String,
+    fields: HashSet<String>,
+    methods: HashMap<String, Vec<Node>>,
+}
+
+// Functions do not see the scope they are defined in, every function body is checked in a scope
+// holding only `self` and its parameters, like `call_named` runs it.
+#[derive(Clone)]
+struct Scope {
+    kind: Kind,
+    bindings: HashMap<String, Binding>,
+    outer: HashSet<String>,
+    class: Option<Rc<Class>>,
+    methods_of: Option<Rc<Class>>,
+}
+
+impl Scope {
+    fn new(kind: Kind, enclosing: Option<&Scope>) -> Scope {
+        let outer = match enclosing {
+            Some(enclosing) => enclosing.outer.iter().chain(enclosing.bindings.keys()).cloned().collect(),
+            None => HashSet::new(),
+        };
+        Scope { kind, bindings: HashMap::new(), outer, class: None, methods_of: None }
+    }
+
+    // After a match, a name is bound if any arm bound it and its pending assignments are those of every arm.
+    fn merge(&mut self, branches: Vec<Scope>) {
+        for branch in branches {
+            for (name, binding) in branch.bindings {
+                match self.bindings.get_mut(&name) {
+                    Some(existing) => {
+                        for assignment in binding.pending {
+                            if !existing.pending.contains(&assignment) {
+                                existing.pending.push(assignment);
+                            }
+                        }
+                        if existing.parameters != binding.parameters {
+                            existing.parameters = None;
+                        }
+                    }
+                    None => {
+                        self.bindings.insert(name, binding);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct Usage<'n> {
+    assigned: Vec<(&'n Node, String)>,
+    used: HashSet<*const Node>,
+}
+
+fn program(node: &Node) -> &[Node] {
+    match node {
+        Node::Program(statements) => statements,
+        _ => std::slice::from_ref(node),
+    }
+}
+
+// Lists the identifiers of a statement in the order their tokens appear in the source, a member name
+// is listed with its member node and keywords are listed without a node.
+pub fn identifiers<'n>(node: &'n Node, found: &mut Vec<(Option<&'n Node>, String)>) {
+    match node {
+        Node::Identifier(name) => found.push((Some(node), name.to_owned())),
+        Node::Member(object, name) => {
+            identifiers(object, found);
+            found.push((Some(node), name.to_owned()));
+        }
+        Node::EnumDefinition(name, variants) => {
+            found.push((None, String::from("enum")));
+            identifiers(name, found);
+            variants.iter().for_each(|variant| identifiers(variant, found));
+        }
+        Node::ClassDefinition(name, body) => {
+            found.push((None, String::from("class")));
+            identifiers(name, found);
+            identifiers(body, found);
+        }
+        Node::Prefixed(_prefix, literal) => identifiers(literal, found),
+        Node::Assignment(left, right)
+        | Node::CompoundAssignment(left, _, right)
+        | Node::Addition(left, right)
+        | Node::Substraction(left, right)
+        | Node::Multiplication(left, right)
+        | Node::Division(left, right)
+        | Node::Modulus(left, right)
+        | Node::Index(left, right)
+        | Node::Slice(left, right)
+        | Node::KeyValue(left, right)
+        | Node::MatchArm(left, right) => {
+            identifiers(left, found);
+            identifiers(right, found);
+        }
+        Node::Call(first, rest) | Node::Match(first, rest) => {
+            identifiers(first, found);
+            rest.iter().for_each(|node| identifiers(node, found));
+        }
+        Node::FunctionDefinition(first, rest) => {
+            first.iter().chain(rest.iter()).for_each(|node| identifiers(node, found));
+        }
+        Node::Program(nodes) | Node::Array(nodes) | Node::Tuple(nodes) | Node::Map(nodes) | Node::Set(nodes) | Node::FunctionArgs(nodes) => {
+            nodes.iter().for_each(|node| identifiers(node, found));
+        }
+        Node::Negation(node) | Node::Variadic(node) | Node::Parenthesized(node) => identifiers(node, found),
+        _ => {}
+    }
+}
+
+fn is_self(node: &Node) -> bool {
+    matches!(node, Node::Identifier(name) if name == "self")
+}
+
+// Assigning to an index or to a field of `self` never fails, no arm after one of them can run.
+fn is_irrefutable(pattern: &Node) -> bool {
+    match pattern {
+        Node::Index(_, _) => true,
+        Node::Member(object, _) => is_self(object),
+        _ => false,
+    }
+}
+
+// Mirrors `bind` with the arguments known at the call site, spread arguments hide their count.
+fn arity(parameters: &[Node], arguments: &[Node]) -> Option<String> {
+    let arguments: Vec<&Node> = arguments.iter().filter(|argument| **argument != Node::Empty).collect();
+    if arguments.iter().any(|argument| matches!(argument, Node::Variadic(_))) {
+        return None;
+    }
+    let named: Vec<&String> = arguments
+        .iter()
+        .filter_map(|argument| match argument {
+            Node::Assignment(name, _) => match &**name {
+                Node::Identifier(name) => Some(name),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect();
+    let positional = arguments.len() - named.len();
+    let mut filled = HashSet::new();
+    let mut accepted = 0;
+    for parameter in parameters.iter() {
+        let name = parameter_name(parameter)?;
+        if let Node::Variadic(_) = parameter {
+            accepted = positional;
+        } else if accepted < positional {
+            filled.insert(name);
+            accepted += 1;
+        }
+    }
+    if positional > accepted {
+        return Some(format!("Expected at most {} arguments, got {}", accepted, positional));
+    }
+    for name in named {
+        match parameters.iter().find(|parameter| parameter_name(parameter) == Some(name)) {
+            None | Some(Node::Variadic(_)) => return Some(format!("Unexpected argument {}", name)),
+            Some(_) if filled.contains(name) => return Some(format!("Argument {} given more than once", name)),
+            Some(_) => {
+                filled.insert(name);
+            }
+        }
+    }
+    parameters.iter().find_map(|parameter| match parameter {
+        Node::Identifier(name) if !filled.contains(name) => Some(format!("Missing argument {}", name)),
+        _ => None,
+    })
+}
+
+struct Checker<'n> {
+    config: &'n Config,
+    spans: HashMap<*const Node, Span>,
+    statement: Span,
+    usages: Vec<Usage<'n>>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'n> Checker<'n> {
+    // Reports at the first identifier of `node` found in the source, or at its statement.
+    fn report(&mut self, rule: &'static str, node: &'n Node, message: String) {
+        let severity = self.config.severity(rule);
+        if severity == Severity::Off {
+            return;
+        }
+        let mut found = vec![];
+        identifiers(node, &mut found);
+        let span = found.iter().find_map(|(node, _name)| node.and_then(|node| self.spans.get(&(node as *const Node)))).cloned().unwrap_or(self.statement);
+        self.diagnostics.push(Diagnostic { rule, severity, message, span });
+    }
+
+    fn read(&mut self, node: &'n Node, name: &str, scope: &mut Scope) {
+        match scope.bindings.get_mut(name) {
+            Some(binding) => {
+                if let Some(usage) = self.usages.last_mut() {
+                    usage.used.extend(binding.pending.drain(..));
+                }
+            }
+            None if is_native(name) || (name == "self" && scope.kind != Kind::Module) => {}
+            None if scope.outer.contains(name) => {
+                self.report("undefined-variable", node, format!("{} is not defined, functions do not see the variables of the scope defining them", name))
+            }
+            None => self.report("undefined-variable", node, format!("{} is not defined", name)),
+        }
+    }
+
+    fn assign(&mut self, node: &'n Node, name: &str, scope: &mut Scope, parameters: Option<Vec<Node>>) {
+        let mut binding = Binding { parameters, pending: vec![] };
+        if scope.kind == Kind::Function && !name.starts_with('_') {
+            if let Some(usage) = self.usages.last_mut() {
+                usage.assigned.push((node, name.to_owned()));
+                binding.pending.push(node as *const Node);
+            }
+        }
+        scope.bindings.insert(name.to_owned(), binding);
+    }
+
+    fn statements(&mut self, statements: &'n [Node], scope: &mut Scope) {
+        for statement in statements.iter() {
+            self.expression(statement, scope);
+        }
+    }
+
+    fn field(&mut self, object: &'n Node, name: &str, scope: &mut Scope) {
+        self.expression(object, scope);
+        if let (true, Some(class)) = (is_self(object), scope.class.to_owned()) {
+            if !class.fields.contains(name) {
+                self.report("undeclared-field", object, format!("Field {} is not declared in class {}", name, class.name));
+            }
+        }
+    }
+
+    fn target(&mut self, node: &'n Node, scope: &mut Scope, parameters: Option<Vec<Node>>) {
+        match node {
+            Node::Identifier(name) => self.assign(node, name, scope, parameters),
+            Node::Member(object, name) => self.field(object, name, scope),
+            Node::Index(object, index) => {
+                self.expression(object, scope);
+                self.expression(index, scope);
+            }
+            Node::Parenthesized(node) | Node::Variadic(node) => self.target(node, scope, None),
+            Node::Tuple(elements) | Node::Array(elements) => elements.iter().for_each(|element| self.target(element, scope, None)),
+            Node::Call(variant, elements) => {
+                self.expression(variant, scope);
+                elements.iter().for_each(|element| self.target(element, scope, None));
+            }
+            Node::Map(entries) => {
+                for entry in entries.iter() {
+                    if let Node::KeyValue(key, pattern) = entry {
+                        self.expression(key, scope);
+                        self.target(pattern, scope, None);
+                    }
+                }
+            }
+            node => self.expression(node, scope),
+        }
+    }
+
+    fn function(&mut self, parameters: &'n [Node], body: &'n [Node], enclosing: &Scope) {
+        let mut scope = Scope::new(Kind::Function, Some(enclosing));
+        scope.class = enclosing.methods_of.to_owned();
+        let mut seen = HashSet::new();
+        for parameter in parameters.iter() {
+            let node = match parameter {
+                Node::Assignment(node, _) | Node::Variadic(node) => node,
+                node => node,
+            };
+            if let Node::Identifier(name) = node {
+                if !seen.insert(name) {
+                    self.report("shadowed-parameter", node, format!("Parameter {} is declared more than once", name));
+                } else if name == "self" {
+                    self.report("shadowed-parameter", node, String::from("Parameter self shadows the instance the function is called on"));
+                } else if is_native(name) {
+                    self.report("shadowed-parameter", node, format!("Parameter {} shadows the builtin {}", name, name));
+                } else if enclosing.kind == Kind::Function && enclosing.bindings.contains_key(name) {
+                    self.report("shadowed-parameter", node, format!("Parameter {} shadows {} of the enclosing function", name, name));
+                }
+                scope.bindings.insert(name.to_owned(), Binding::default());
+            }
+        }
+        for parameter in parameters.iter() {
+            if let Node::Assignment(_, default) = parameter {
+                self.expression(default, &mut scope);
+            }
+        }
+        self.usages.push(Usage::default());
+        self.statements(body, &mut scope);
+        let usage = self.usages.pop().unwrap_or_default();
+        for (node, name) in usage.assigned {
+            if !usage.used.contains(&(node as *const Node)) {
+                self.report("unused-assignment", node, format!("Value assigned to {} is never read", name));
+            }
+        }
+    }
+
+    fn class(&mut self, name: &str, body: &'n Node, enclosing: &Scope) {
+        let mut class = Class { name: name.to_owned(), fields: HashSet::new(), methods: HashMap::new() };
+        for statement in program(body).iter() {
+            match statement {
+                Node::Assignment(target, value) | Node::CompoundAssignment(target, _, value) => {
+                    let field = match &**target {
+                        Node::Identifier(name) => name,
+                        Node::Member(object, name) if is_self(object) => name,
+                        _ => continue,
+                    };
+                    class.fields.insert(field.to_owned());
+                    if let Node::FunctionDefinition(parameters, _) = &**value {
+                        class.methods.insert(field.to_owned(), parameters.to_vec());
+                    }
+                }
+                Node::ClassDefinition(name, _) | Node::EnumDefinition(name, _) => {
+                    if let Node::Identifier(name) = &**name {
+                        class.fields.insert(name.to_owned());
+                    }
+                }
+                _ => {}
+            }
+        }
+        let mut scope = Scope::new(Kind::Class, Some(enclosing));
+        scope.methods_of = Some(Rc::new(class));
+        self.statements(program(body), &mut scope);
+    }
+
+    fn call(&mut self, callee: &'n Node, arguments: &'n [Node], scope: &mut Scope) {
+        self.expression(callee, scope);
+        for argument in arguments.iter() {
+            match argument {
+                Node::Assignment(name, value) if matches!(**name, Node::Identifier(_)) => self.expression(value, scope),
+                argument => self.expression(argument, scope),
+            }
+        }
+        let known = match callee {
+            Node::Identifier(name) => scope.bindings.get(name).and_then(|binding| binding.parameters.to_owned()).map(|parameters| (name, parameters)),
+            Node::Member(object, name) if is_self(object) => {
+                scope.class.as_ref().and_then(|class| class.methods.get(name).cloned()).map(|parameters| (name, parameters))
+            }
+            _ => None,
+        };
+        if let Some((name, parameters)) = known {
+            if let Some(message) = arity(&parameters, arguments) {
+                self.report("wrong-arity", callee, format!("Call to {}: {}", name, message));
+            }
+        }
+    }
+
+    fn arms(&mut self, arms: &'n [Node], scope: &mut Scope) {
+        let mut branches = vec![];
+        let mut seen: Vec<&Node> = vec![];
+        let mut exhausted = false;
+        for arm in arms.iter() {
+            if let Node::MatchArm(pattern, body) = arm {
+                if exhausted {
+                    self.report("unreachable-code", pattern, String::from("This arm is unreachable, an earlier arm matches every value"));
+                } else if seen.contains(&&**pattern) {
+                    self.report("unreachable-code", pattern, String::from("This arm is unreachable, an earlier arm has the same pattern"));
+                }
+                let mut branch = scope.clone();
+                match &**pattern {
+                    Node::Identifier(name) => self.read(pattern, name, &mut branch),
+                    pattern => self.target(pattern, &mut branch, None),
+                }
+                self.statements(program(body), &mut branch);
+                branches.push(branch);
+                seen.push(pattern);
+                exhausted = exhausted || is_irrefutable(pattern);
+            }
+        }
+        scope.merge(branches);
+    }
+
+    fn expression(&mut self, node: &'n Node, scope: &mut Scope) {
+        match node {
+            Node::Identifier(name) => self.read(node, name, scope),
+            Node::Assignment(target, value) => {
+                self.expression(value, scope);
+                let parameters = match &**value {
+                    Node::FunctionDefinition(parameters, _) => Some(parameters.to_vec()),
+                    _ => None,
+                };
+                self.target(target, scope, parameters);
+            }
+            Node::CompoundAssignment(target, _, value) => match &**target {
+                Node::Identifier(name) => {
+                    self.read(target, name, scope);
+                    self.expression(value, scope);
+                    self.assign(target, name, scope, None);
+                }
+                Node::Member(object, name) => {
+                    self.field(object, name, scope);
+                    self.expression(value, scope);
+                }
+                target => {
+                    self.expression(target, scope);
+                    self.expression(value, scope);
+                }
+            },
+            Node::FunctionDefinition(parameters, body) => self.function(parameters, body, scope),
+            Node::ClassDefinition(name, body) => {
+                if let Node::Identifier(name) = &**name {
+                    self.class(name, body, scope);
+                    scope.bindings.insert(name.to_owned(), Binding::default());
+                }
+            }
+            Node::EnumDefinition(name, _variants) => {
+                if let Node::Identifier(name) = &**name {
+                    scope.bindings.insert(name.to_owned(), Binding::default());
+                }
+            }
+            Node::Call(callee, arguments) => self.call(callee, arguments, scope),
+            Node::Match(subject, arms) => {
+                self.expression(subject, scope);
+                self.arms(arms, scope);
+            }
+            Node::Member(object, _) => self.expression(object, scope),
+            Node::Prefixed(prefix, literal) => {
+                self.expression(prefix, scope);
+                self.expression(literal, scope);
+            }
+            Node::Addition(left, right)
+            | Node::Substraction(left, right)
+            | Node::Multiplication(left, right)
+            | Node::Division(left, right)
+            | Node::Modulus(left, right)
+            | Node::Index(left, right)
+            | Node::Slice(left, right)
+            | Node::KeyValue(left, right) => {
+                self.expression(left, scope);
+                self.expression(right, scope);
+            }
+            Node::Program(nodes) | Node::Array(nodes) | Node::Tuple(nodes) | Node::Map(nodes) | Node::Set(nodes) | Node::FunctionArgs(nodes) | Node::Template(nodes) => {
+                self.statements(nodes, scope)
+            }
+            Node::Negation(node) | Node::Variadic(node) | Node::Parenthesized(node) => self.expression(node, scope),
+            _ => {}
+        }
+    }
+}
+
+// Maps the identifier nodes of a statement to the spans of their tokens, when the tokens of the
+// statement do not line up with its identifiers the statement span is used instead.
+pub fn spans(statement: &Node, span: &Span, tokens: &[(Token, Span)]) -> HashMap<*const Node, Span> {
+    let mut found = vec![];
+    identifiers(statement, &mut found);
+    let names: Vec<(&String, &Span)> = tokens
+        .iter()
+        .filter(|(_token, token_span)| token_span.start.offset >= span.start.offset && token_span.end.offset <= span.end.offset)
+        .filter_map(|(token, token_span)| match token {
+            Token::Identifier(name) => Some((name, token_span)),
+            _ => None,
+        })
+        .collect();
+    if names.len() != found.len() || names.iter().zip(found.iter()).any(|((name, _span), (_node, expected))| *name != expected) {
+        return HashMap::new();
+    }
+    found.iter().zip(names.iter()).filter_map(|((node, _name), (_token, span))| node.map(|node| (node as *const Node, **span))).collect()
+}
+
+pub fn check(statements: &[(Node, Span)], tokens: &[(Token, Span)], config: &Config) -> Vec<Diagnostic> {
+    let mut checker = Checker { config, spans: HashMap::new(), statement: Span { start: Position::start(), end: Position::start() }, usages: vec![], diagnostics: vec![] };
+    let mut scope = Scope::new(Kind::Module, None);
+    scope.bindings.insert(String::from("args"), Binding::default());
+    for (statement, span) in statements.iter() {
+        checker.spans = spans(statement, span, tokens);
+        checker.statement = *span;
+        checker.expression(statement, &mut scope);
+    }
+    let mut diagnostics = checker.diagnostics;
+    diagnostics.sort_by_key(|diagnostic| diagnostic.span.start.offset);
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::{check, Config};
+    use crate::manifest;
+    use crate::testing::fixture;
+    use lexer::lexer::lex_spanned;
+    use parser::parser::parse_spanned;
+
+    fn lint(code: &str, levels: &[(&str, &str)]) -> Vec<String> {
+        let config = Config::new(&levels.iter().map(|(rule, level)| (rule.to_string(), level.to_string())).collect()).unwrap();
+        let tokens = lex_spanned(code).unwrap();
+        let statements = parse_spanned(tokens.to_vec()).unwrap();
+        check(&statements, &tokens, &config)
+            .iter()
+            .map(|diagnostic| format!("{}:{}: {}[{}]: {}", diagnostic.span.start.line, diagnostic.span.start.column, diagnostic.severity.name(), diagnostic.rule, diagnostic.message))
+            .collect()
+    }
+
+    const LINTED: &str = "add = (a, b) { a + b }
+add(1)
+f = (x, x) {
+  y = 1
+  _z = 2
+  missing
+}
+class Point {
+  x = 0
+  move = (self) {
+    self.y = 1
+  }
+}
+one = 1
+one: {
+  one: { kind = 'one' }
+  one: { kind = 'two' }
+}";
+
+    #[test]
+    fn lint_rules() {
+        let expected = [
+            "2:1: error[wrong-arity]: Call to add: Missing argument b",
+            "3:9: warning[shadowed-parameter]: Parameter x is declared more than once",
+            "4:3: warning[unused-assignment]: Value assigned to y is never read",
+            "6:3: error[undefined-variable]: missing is not defined",
+            "10:11: warning[shadowed-parameter]: Parameter self shadows the instance the function is called on",
+            "11:5: warning[undeclared-field]: Field y is not declared in class Point",
+            "17:3: warning[unreachable-code]: This arm is unreachable, an earlier arm has the same pattern",
+        ];
+        assert_eq!(lint(LINTED, &[]), expected);
+    }
+
+    #[test]
+    fn lint_config() {
+        let levels = [("shadowed-parameter", "off"), ("unused-assignment", "error"), ("wrong-arity", "warning")];
+        let expected = [
+            "2:1: warning[wrong-arity]: Call to add: Missing argument b",
+            "4:3: error[unused-assignment]: Value assigned to y is never read",
+            "6:3: error[undefined-variable]: missing is not defined",
+            "11:5: warning[undeclared-field]: Field y is not declared in class Point",
+            "17:3: warning[unreachable-code]: This arm is unreachable, an earlier arm has the same pattern",
+        ];
+        assert_eq!(lint(LINTED, &levels), expected);
+        let config = |rule: &str, level: &str| Config::new(&BTreeMap::from([(rule.to_string(), level.to_string())])).err();
+        assert_eq!(config("unused", "off"), Some(String::from("Unknown lint rule unused")));
+        assert_eq!(config("wrong-arity", "fatal"), Some(String::from("Invalid level fatal for lint rule wrong-arity, expected off, warning or error")));
+        let directory = fixture("lint_config", &[("app/lim.toml", "[package]\nname = 'app'\n\n[lint]\nshadowed-parameter = 'off'\n")]);
+        assert_eq!(manifest::load(&directory.join("app/lim.toml")).unwrap().lint, BTreeMap::from([(String::from("shadowed-parameter"), String::from("off"))]));
+    }
+}
@@ -0,0 +1,532 @@
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::panic;
+use std::path::{Path, PathBuf};
+
+use serde_json::{json, Value};
+
+use crate::lint;
+use crate::manifest;
+use crate::symbols::{self, Analysis, Kind};
+use lexer::lexer::{is_unterminated, lex_spanned};
+use lexer::span::{Position, Span};
+use lexer::token::Token;
+use parser::format::format;
+use parser::node::Node;
+use parser::parser::parse_spanned;
+
+const ERROR: u64 = 1;
+const WARNING: u64 = 2;
+
+// A document keeps the analysis of its last version that parsed, so that completion still works
+// while the line being typed is not valid yet.
+struct Document {
+    text: String,
+    analysis: Option<Analysis>,
+}
+
+#[derive(Default)]
+pub struct Server {
+    documents: HashMap<String, Document>,
+    shutdown: bool,
+    exited: bool,
+}
+
+// LSP positions count lines and characters from 0, spans count them from 1.
+fn position(position: &Position) -> Value {
+    json!({ "line": position.line - 1, "character": position.column - 1 })
+}
+
+fn range(span: &Span) -> Value {
+    json!({ "start": position(&span.start), "end": position(&span.end) })
+}
+
+fn offset(text: &str, position: &Value) -> usize {
+    let line = position["line"].as_u64().unwrap_or(0) as usize;
+    let character = position["character"].as_u64().unwrap_or(0) as usize;
+    let before: usize = text.split('\n').take(line).map(|line| line.chars().count() + 1).sum();
+    before + character
+}
+
+fn end_of(text: &str) -> Position {
+    let mut end = Position::start();
+    text.chars().for_each(|character| end.advance(character));
+    end
+}
+
+fn path(uri: &str) -> Option<PathBuf> {
+    uri.strip_prefix("file://").map(PathBuf::from)
+}
+
+fn diagnostic(span: &Span, severity: u64, code: &str, message: &str) -> Value {
+    json!({ "range": range(span), "severity": severity, "code": code, "source": "lim", "message": message })
+}
+
+// The formatter prints any tree the parser accepts, an editor session must survive it failing on one.
+// Its panics are silenced, the hook is restored afterwards so that other panics are reported.
+fn quietly<T>(work: impl FnOnce() -> Option<T> + panic::UnwindSafe) -> Option<T> {
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(|_info| {}));
+    let result = panic::catch_unwind(work);
+    panic::set_hook(hook);
+    result.ok().flatten()
+}
+
+fn try_parse(tokens: &[(Token, Span)]) -> Option<Vec<(Node, Span)>> {
+    parse_spanned(tokens.to_vec())
+}
+
+// Finds the first token that makes the tokens before it fail to parse, a failing prefix never
+// parses again once longer.
+fn failing_token(tokens: &[(Token, Span)]) -> Span {
+    let (mut parsed, mut failed) = (0, tokens.len());
+    while failed - parsed > 1 {
+        let middle = (parsed + failed) / 2;
+        if try_parse(&tokens[..middle]).is_some() {
+            parsed = middle;
+        } else {
+            failed = middle;
+        }
+    }
+    tokens[failed - 1].1
+}
+
+// The first line after which the code cannot be lexed, for a reason other than a string still open.
+fn failing_line(text: &str) -> Span {
+    let mut end = 0;
+    for (number, line) in text.split('\n').enumerate() {
+        end += line.len() + 1;
+        let prefix = &text[..end.min(text.len())];
+        if lex_spanned(prefix).is_none() && !is_unterminated(prefix) {
+            let start = Position { line: number + 1, column: 1, offset: 0 };
+            return Span { start, end: Position { line: number + 1, column: line.chars().count() + 1, offset: 0 } };
+        }
+    }
+    let end = end_of(text);
+    Span { start: end, end }
+}
+
+fn config(uri: &str) -> lint::Config {
+    let directory = path(uri).and_then(|path| path.parent().map(Path::to_path_buf)).unwrap_or_default();
+    match manifest::find(&directory).map(|path| manifest::load(&path)) {
+        Some(Ok(project)) => lint::Config::new(&project.lint).unwrap_or_default(),
+        _ => lint::Config::default(),
+    }
+}
+
+fn analyze(uri: &str, text: &str) -> (Vec<Value>, Option<Analysis>) {
+    let tokens = match lex_spanned(text) {
+        Some(tokens) => tokens,
+        None if is_unterminated(text) => {
+            let end = end_of(text);
+            return (vec![diagnostic(&Span { start: end, end }, ERROR, "syntax", "Unterminated string")], None);
+        }
+        None => return (vec![diagnostic(&failing_line(text), ERROR, "syntax", "Invalid token")], None),
+    };
+    let statements = match try_parse(&tokens) {
+        Some(statements) => statements,
+        None => return (vec![diagnostic(&failing_token(&tokens), ERROR, "syntax", "Unexpected token")], None),
+    };
+    if statements.iter().any(|(statement, _span)| statement.is_incomplete()) {
+        let end = end_of(text);
+        return (vec![diagnostic(&Span { start: end, end }, ERROR, "syntax", "Unexpected end of input")], None);
+    }
+    let diagnostics = lint::check(&statements, &tokens, &config(uri))
+        .iter()
+        .map(|found| diagnostic(&found.span, if found.severity == lint::Severity::Error { ERROR } else { WARNING }, found.rule, &found.message))
+        .collect();
+    (diagnostics, Some(symbols::analyze(&statements, &tokens)))
+}
+
+fn symbol_kind(kind: Kind) -> u64 {
+    match kind {
+        Kind::Class => 5,
+        Kind::Method => 6,
+        Kind::Field => 8,
+        Kind::Enum => 10,
+        Kind::Function => 12,
+        Kind::Variable | Kind::Parameter => 13,
+        Kind::Variant => 22,
+    }
+}
+
+fn completion_kind(kind: Kind) -> u64 {
+    match kind {
+        Kind::Method => 2,
+        Kind::Function => 3,
+        Kind::Field => 5,
+        Kind::Variable | Kind::Parameter => 6,
+        Kind::Class => 7,
+        Kind::Enum => 13,
+        Kind::Variant => 20,
+    }
+}
+
+fn response(id: &Value, result: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "result": result })
+}
+
+fn error(id: &Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+impl Server {
+    fn update(&mut self, uri: &str, text: String) -> Value {
+        let (diagnostics, analysis) = analyze(uri, &text);
+        let document = self.documents.entry(uri.to_owned()).or_insert(Document { text: String::new(), analysis: None });
+        document.text = text;
+        if analysis.is_some() {
+            document.analysis = analysis;
+        }
+        json!({ "jsonrpc": "2.0", "method": "textDocument/publishDiagnostics", "params": { "uri": uri, "diagnostics": diagnostics } })
+    }
+
+    fn document<'a>(&'a self, params: &'a Value) -> Option<(&'a str, &'a Document, usize)> {
+        let uri = params["textDocument"]["uri"].as_str()?;
+        let document = self.documents.get(uri)?;
+        Some((uri, document, offset(&document.text, &params["position"])))
+    }
+
+    fn definition(&self, params: &Value) -> Value {
+        let (uri, document, offset) = match self.document(params) {
+            Some(found) => found,
+            None => return Value::Null,
+        };
+        let analysis = match &document.analysis {
+            Some(analysis) => analysis,
+            None => return Value::Null,
+        };
+        match analysis.at(offset) {
+            Some(occurrence) => Value::Array(analysis.definitions(occurrence).iter().map(|symbol| json!({ "uri": uri, "range": range(&symbol.span) })).collect()),
+            None => Value::Null,
+        }
+    }
+
+    fn hover(&self, params: &Value) -> Value {
+        let (_uri, document, offset) = match self.document(params) {
+            Some(found) => found,
+            None => return Value::Null,
+        };
+        let analysis = match &document.analysis {
+            Some(analysis) => analysis,
+            None => return Value::Null,
+        };
+        let occurrence = match analysis.at(offset) {
+            Some(occurrence) => occurrence,
+            None => return Value::Null,
+        };
+        let descriptions: Vec<String> = analysis.definitions(occurrence).iter().map(|symbol| analysis.describe(symbol)).collect();
+        if descriptions.is_empty() {
+            return Value::Null;
+        }
+        json!({ "contents": { "kind": "markdown", "value": format!("```lim\n{}\n```", descriptions.join("\n")) }, "range": range(&occurrence.span) })
+    }
+
+    // After `name.` the members of what `name` refers to are offered, elsewhere the visible names.
+    fn completion(&self, params: &Value) -> Value {
+        let (_uri, document, offset) = match self.document(params) {
+            Some(found) => found,
+            None => return Value::Null,
+        };
+        let analysis = match &document.analysis {
+            Some(analysis) => analysis,
+            None => return json!([]),
+        };
+        let before: String = document.text.chars().take(offset).collect();
+        let mut tokens: Vec<Token> = lex_spanned(&before).unwrap_or_default().into_iter().map(|(token, _span)| token).collect();
+        if let (Some(Token::Identifier(_)), true) = (tokens.last(), before.ends_with(|character: char| character.is_alphanumeric() || character == '_')) {
+            tokens.pop();
+        }
+        let scope = analysis.scope_at(offset);
+        let symbols = match (tokens.pop(), tokens.pop()) {
+            (Some(Token::Period), Some(Token::Identifier(object))) => analysis.members(&Node::Identifier(object), scope, offset),
+            (Some(Token::Period), _) => return json!([]),
+            _ => analysis.visible(scope),
+        };
+        Value::Array(symbols.iter().map(|symbol| json!({ "label": symbol.name, "kind": completion_kind(symbol.kind), "detail": analysis.describe(symbol) })).collect())
+    }
+
+    fn symbols(&self, params: &Value) -> Value {
+        let analysis = match params["textDocument"]["uri"].as_str().and_then(|uri| self.documents.get(uri)).and_then(|document| document.analysis.as_ref()) {
+            Some(analysis) => analysis,
+            None => return json!([]),
+        };
+        let symbol = |symbol: &symbols::Symbol, children: Vec<Value>| {
+            json!({ "name": symbol.name, "kind": symbol_kind(symbol.kind), "range": range(&symbol.range), "selectionRange": range(&symbol.span), "children": children })
+        };
+        let top: Vec<Value> = analysis
+            .symbols
+            .iter()
+            .filter(|found| found.scope == 0 && !found.member)
+            .map(|found| {
+                let children = match found.kind {
+                    Kind::Class | Kind::Enum => analysis
+                        .symbols
+                        .iter()
+                        .filter(|member| member.container.as_ref() == Some(&found.name))
+                        .map(|member| symbol(member, vec![]))
+                        .collect(),
+                    _ => vec![],
+                };
+                symbol(found, children)
+            })
+            .collect();
+        Value::Array(top)
+    }
+
+    fn formatting(&self, params: &Value) -> Value {
+        let document = match params["textDocument"]["uri"].as_str().and_then(|uri| self.documents.get(uri)) {
+            Some(document) => document,
+            None => return Value::Null,
+        };
+        let text = document.text.to_owned();
+        match quietly(move || format(&text)) {
+            Some(formatted) if formatted != document.text => {
+                let end = end_of(&document.text);
+                json!([{ "range": range(&Span { start: Position::start(), end }), "newText": formatted }])
+            }
+            Some(_) => json!([]),
+            None => Value::Null,
+        }
+    }
+
+    // Answers one message with the messages to send back, notifications may publish diagnostics.
+    pub fn handle(&mut self, message: &Value) -> Vec<Value> {
+        let id = &message["id"];
+        let params = &message["params"];
+        let method = message["method"].as_str().unwrap_or_default();
+        if self.shutdown && method != "exit" {
+            return if id.is_null() { vec![] } else { vec![error(id, -32600, "The server is shutting down")] };
+        }
+        let result = match method {
+            "initialize" => json!({
+                "capabilities": {
+                    "textDocumentSync": 1,
+                    "definitionProvider": true,
+                    "hoverProvider": true,
+                    "completionProvider": { "triggerCharacters": ["."] },
+                    "documentSymbolProvider": true,
+                    "documentFormattingProvider": true,
+                },
+                "serverInfo": { "name": "lim" },
+            }),
+            "textDocument/didOpen" => {
+                let document = &params["textDocument"];
+                return vec![self.update(document["uri"].as_str().unwrap_or_default(), document["text"].as_str().unwrap_or_default().to_owned())];
+            }
+            "textDocument/didChange" => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+                return match params["contentChanges"].as_array().and_then(|changes| changes.last()) {
+                    Some(change) => vec![self.update(uri, change["text"].as_str().unwrap_or_default().to_owned())],
+                    None => vec![],
+                };
+            }
+            "textDocument/didClose" => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+                self.documents.remove(uri);
+                return vec![json!({ "jsonrpc": "2.0", "method": "textDocument/publishDiagnostics", "params": { "uri": uri, "diagnostics": [] } })];
+            }
+            "textDocument/definition" => self.definition(params),
+            "textDocument/hover" => self.hover(params),
+            "textDocument/completion" => self.completion(params),
+            "textDocument/documentSymbol" => self.symbols(params),
+            "textDocument/formatting" => self.formatting(params),
+            "shutdown" => {
+                self.shutdown = true;
+                Value::Null
+            }
+            "exit" => {
+                self.exited = true;
+                return vec![];
+            }
+            _ if id.is_null() => return vec![],
+            _ => return vec![error(id, -32601, &format!("Unknown method {}", method))],
+        };
+        if id.is_null() {
+            vec![]
+        } else {
+            vec![response(id, result)]
+        }
+    }
+}
+
+fn read_message(input: &mut impl BufRead) -> io::Result<Option<Value>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some(value) = header.strip_prefix("Content-Length:") {
+            length = value.trim().parse::<usize>().ok();
+        }
+    }
+    let mut body = vec![0; length.unwrap_or(0)];
+    input.read_exact(&mut body)?;
+    Ok(Some(serde_json::from_slice(&body).unwrap_or(Value::Null)))
+}
+
+fn write_message(output: &mut impl Write, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+// Serves JSON-RPC messages framed by Content-Length headers until `exit`, the exit code is 0 only
+// when `shutdown` came first.
+pub fn serve(mut input: impl BufRead, mut output: impl Write) -> i32 {
+    let mut server = Server::default();
+    while let Ok(Some(message)) = read_message(&mut input) {
+        for reply in server.handle(&message) {
+            if write_message(&mut output, &reply).is_err() {
+                return 1;
+            }
+        }
+        if server.exited {
+            return if server.shutdown { 0 } else { 1 };
+        }
+    }
+    1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::serve;
+    use serde_json::{json, Value};
+
+    fn request(id: u64, method: &str, params: Value) -> Value {
+        json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params })
+    }
+
+    fn notification(method: &str, params: Value) -> Value {
+        json!({ "jsonrpc": "2.0", "method": method, "params": params })
+    }
+
+    const URI: &str = "file:///tmp/session.lim";
+
+    fn open(text: &str) -> Value {
+        notification("textDocument/didOpen", json!({ "textDocument": { "uri": URI, "text": text } }))
+    }
+
+    fn at(line: u64, character: u64) -> Value {
+        json!({ "textDocument": { "uri": URI }, "position": { "line": line, "character": character } })
+    }
+
+    // Sends the messages framed the way an editor does, returns the exit code and the replies.
+    fn session(messages: &[Value]) -> (i32, Vec<Value>) {
+        let input: String = messages.iter().map(|message| format!("Content-Length: {}\r\n\r\n{}", message.to_string().len(), message)).collect();
+        let mut output = vec![];
+        let code = serve(input.as_bytes(), &mut output);
+        let mut replies = vec![];
+        let mut rest = &output[..];
+        while !rest.is_empty() {
+            let end = rest.windows(4).position(|window| window == b"\r\n\r\n").unwrap();
+            let length: usize = std::str::from_utf8(&rest[..end]).unwrap().trim_start_matches("Content-Length: ").parse().unwrap();
+            replies.push(serde_json::from_slice(&rest[end + 4..end + 4 + length]).unwrap());
+            rest = &rest[end + 4 + length..];
+        }
+        (code, replies)
+    }
+
+    // The reply to the request `id`.
+    fn result(replies: &[Value], id: u64) -> Value {
+        replies.iter().find(|reply| reply["id"] == id).map(|reply| reply["result"].to_owned()).unwrap()
+    }
+
+    fn diagnostics(replies: &[Value]) -> Vec<Value> {
+        replies.iter().filter(|reply| reply["method"] == "textDocument/publishDiagnostics").map(|reply| reply["params"]["diagnostics"].to_owned()).collect()
+    }
+
+    const SOURCE: &str = "class Point {\n  x = 0\n  norm = () { self.x }\n}\np = Point()\nq = p.x\ny = z\nw=1";
+
+    #[test]
+    fn lsp_initialize() {
+        let (_code, replies) = session(&[request(1, "initialize", json!({}))]);
+        let capabilities = &result(&replies, 1)["capabilities"];
+        assert_eq!(capabilities["textDocumentSync"], 1);
+        assert_eq!(capabilities["completionProvider"]["triggerCharacters"], json!(["."]));
+        for provider in ["definitionProvider", "hoverProvider", "documentSymbolProvider", "documentFormattingProvider"] {
+            assert_eq!(capabilities[provider], true, "{}", provider);
+        }
+    }
+
+    #[test]
+    fn lsp_diagnostics_on_open() {
+        let (_code, replies) = session(&[open(SOURCE)]);
+        let published = diagnostics(&replies);
+        assert_eq!(published.len(), 1);
+        assert_eq!(published[0][0]["code"], "undefined-variable");
+        assert_eq!(published[0][0]["message"], "z is not defined");
+        assert_eq!(published[0][0]["range"], json!({ "start": { "line": 6, "character": 4 }, "end": { "line": 6, "character": 5 } }));
+        let (_code, replies) = session(&[open("x = (")]);
+        assert_eq!(diagnostics(&replies)[0][0]["message"], "Unexpected end of input");
+    }
+
+    // A syntax error is reported at the token the parser stopped at and the session goes on.
+    #[test]
+    fn lsp_reports_syntax_errors() {
+        let (_code, replies) = session(&[open("enum Color {\n  Red\n  1\n}"), request(1, "textDocument/hover", at(0, 0))]);
+        assert_eq!(diagnostics(&replies)[0][0]["code"], "syntax");
+        assert_eq!(diagnostics(&replies)[0][0]["range"]["start"], json!({ "line": 2, "character": 2 }));
+        assert_eq!(result(&replies, 1), Value::Null);
+    }
+
+    #[test]
+    fn lsp_definition() {
+        let (_code, replies) = session(&[open(SOURCE), request(1, "textDocument/definition", at(5, 4))]);
+        assert_eq!(result(&replies, 1), json!([{ "uri": URI, "range": { "start": { "line": 4, "character": 0 }, "end": { "line": 4, "character": 1 } } }]));
+    }
+
+    #[test]
+    fn lsp_hover() {
+        let (_code, replies) = session(&[open(SOURCE), request(1, "textDocument/hover", at(5, 4))]);
+        assert_eq!(result(&replies, 1)["contents"]["value"], "```lim\np = Point()\n```");
+    }
+
+    // The line being typed does not parse, the members come from the last version that did.
+    #[test]
+    fn lsp_completion_after_period() {
+        let change = notification("textDocument/didChange", json!({ "textDocument": { "uri": URI }, "contentChanges": [{ "text": "class Point {\n  x = 0\n  norm = () { self.x }\n}\np = Point()\np.\n" }] }));
+        let (_code, replies) = session(&[open(SOURCE), change, request(1, "textDocument/completion", at(5, 2))]);
+        let items = result(&replies, 1);
+        let labels: Vec<&str> = items.as_array().unwrap().iter().map(|item| item["label"].as_str().unwrap()).collect();
+        assert_eq!(labels, ["x", "norm"]);
+    }
+
+    #[test]
+    fn lsp_document_symbols() {
+        let (_code, replies) = session(&[open(SOURCE), request(1, "textDocument/documentSymbol", json!({ "textDocument": { "uri": URI } }))]);
+        let symbols = result(&replies, 1);
+        let names: Vec<&str> = symbols.as_array().unwrap().iter().map(|symbol| symbol["name"].as_str().unwrap()).collect();
+        assert_eq!(names, ["Point", "p", "q", "y", "w"]);
+        let members: Vec<&str> = symbols[0]["children"].as_array().unwrap().iter().map(|symbol| symbol["name"].as_str().unwrap()).collect();
+        assert_eq!(members, ["x", "norm"]);
+        assert_eq!(symbols[0]["kind"], 5);
+    }
+
+    #[test]
+    fn lsp_formatting() {
+        let (_code, replies) = session(&[open(SOURCE), request(1, "textDocument/formatting", json!({ "textDocument": { "uri": URI } }))]);
+        let edits = result(&replies, 1);
+        assert_eq!(edits[0]["newText"], "class Point {\n  x = 0\n  norm = () { self.x }\n}\np = Point()\nq = p.x\ny = z\nw = 1\n");
+        assert_eq!(edits[0]["range"], json!({ "start": { "line": 0, "character": 0 }, "end": { "line": 7, "character": 3 } }));
+        let (_code, replies) = session(&[open("x = 1\n"), request(1, "textDocument/formatting", json!({ "textDocument": { "uri": URI } }))]);
+        assert_eq!(result(&replies, 1), json!([]));
+    }
+
+    #[test]
+    fn lsp_exit_codes() {
+        let shutdown = request(1, "shutdown", Value::Null);
+        let exit = notification("exit", Value::Null);
+        let (code, replies) = session(&[shutdown.to_owned(), request(2, "textDocument/hover", at(0, 0)), exit.to_owned()]);
+        assert_eq!(code, 0);
+        assert_eq!(result(&replies, 1), Value::Null);
+        assert_eq!(replies[1]["error"]["code"], -32600);
+        assert_eq!(session(&[exit]).0, 1);
+        assert_eq!(session(&[shutdown]).0, 1);
+    }
+}
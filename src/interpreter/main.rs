@@ -1,28 +1,266 @@
-#![feature(get_mut_unchecked)]
 use std::collections::HashMap;
-use std::fs::File;
-use std::io::prelude::*;
-
-mod interpreter;
+use std::env;
+use std::fs;
+use std::io::{self, IsTerminal, Read};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
 
-use interpreter::interpret;
-use lexer::lexer::lex;
-use parser::parser::parse;
+use lim::cli::{self, Command};
+use lim::module::{self, Engine};
+use lim::{dump, lint, lsp, manifest, registry, repl, vm};
+use lexer::lexer::{lex_spanned, lex_with_comments};
+use parser::format;
+use parser::parser::parse_spanned;
 use object::Object;
-use std::rc::Rc;
 
-fn run(string: &str) -> Object {
-    (*Rc::clone(&interpret(parse(lex(string).unwrap()).unwrap(), &mut HashMap::new()))).clone()
+fn fail(message: &str) -> ! {
+    registry::unload();
+    eprintln!("error: {}", message);
+    process::exit(1);
 }
 
-fn lex_parse(string: &str) -> parser::node::Node {
-    parse(lex(string).unwrap()).unwrap()
+// Loads the project whose manifest is closest to `directory`, if any, and returns its entry point.
+fn load_project(directory: &Path) -> Option<PathBuf> {
+    let path = manifest::find(directory)?;
+    match manifest::load(&path) {
+        Ok(project) => {
+            let entry = project.entry.to_owned();
+            module::set_project(project);
+            Some(entry)
+        }
+        Err(error) => fail(&error),
+    }
 }
 
-fn main() {
-    let mut file = File::open("../../test.lim").unwrap();
+fn scope(args: Vec<String>) -> HashMap<String, Rc<Object>> {
+    let mut scope = HashMap::new();
+    let args = args.into_iter().map(|arg| Rc::new(Object::String(arg))).collect();
+    scope.insert(String::from("args"), Rc::new(Object::Array(args)));
+    scope
+}
+
+fn read_stdin() -> String {
     let mut code = String::new();
-    file.read_to_string(&mut code).unwrap();
-    // println!("{:?}", lex_parse(&code));
-    run(&code);
+    if let Err(error) = io::stdin().read_to_string(&mut code) {
+        fail(&format!("Cannot read stdin: {}", error));
+    }
+    code
+}
+
+fn read_source(file: &Path) -> String {
+    if file == Path::new("-") {
+        return read_stdin();
+    }
+    fs::read_to_string(file).unwrap_or_else(|error| fail(&format!("Cannot read {}: {}", file.display(), error)))
+}
+
+fn print_json(value: serde_json::Value) {
+    println!("{}", serde_json::to_string_pretty(&value).unwrap());
+}
+
+// Rewrites every file in its canonical form, or with `check` only lists the files that are not.
+fn format_files(files: &[PathBuf], check: bool) {
+    let mut unformatted = false;
+    for file in files.iter() {
+        let code = read_source(file);
+        let formatted = format::format(&code).unwrap_or_else(|| fail(&format!("Syntax error in {}", file.display())));
+        if formatted == code {
+            continue;
+        }
+        if check {
+            println!("{}", file.display());
+            unformatted = true;
+        } else if let Err(error) = fs::write(file, formatted) {
+            fail(&format!("Cannot write {}: {}", file.display(), error));
+        }
+    }
+    if unformatted {
+        process::exit(1);
+    }
+}
+
+// Checks every file with the lint configuration of the closest lim.toml, the exit code is 1 when
+// a rule configured as an error reported something.
+fn check_files(files: &[PathBuf], json: bool, directory: &Path) {
+    let config = match manifest::find(directory).map(|path| manifest::load(&path)) {
+        Some(Ok(project)) => lint::Config::new(&project.lint).unwrap_or_else(|error| fail(&error)),
+        Some(Err(error)) => fail(&error),
+        None => lint::Config::default(),
+    };
+    let mut reports = vec![];
+    let mut failed = false;
+    for file in files.iter() {
+        let code = read_source(file);
+        let tokens = lex_spanned(&code).unwrap_or_else(|| fail(&format!("Syntax error in {}", file.display())));
+        let statements = parse_spanned(tokens.to_vec()).unwrap_or_else(|| fail(&format!("Syntax error in {}", file.display())));
+        if statements.iter().any(|(statement, _span)| statement.is_incomplete()) {
+            fail(&format!("Unexpected end of input in {}", file.display()));
+        }
+        for diagnostic in lint::check(&statements, &tokens, &config) {
+            failed = failed || diagnostic.severity == lint::Severity::Error;
+            reports.push((file.display().to_string(), diagnostic));
+        }
+    }
+    if json {
+        let diagnostics: Vec<serde_json::Value> = reports.iter().map(|(file, diagnostic)| diagnostic.to_json(file)).collect();
+        print_json(serde_json::json!({ "version": dump::VERSION, "diagnostics": diagnostics }));
+    } else {
+        for (file, diagnostic) in reports.iter() {
+            let start = diagnostic.span.start;
+            println!("{}:{}:{}: {}[{}]: {}", file, start.line, start.column, diagnostic.severity.name(), diagnostic.rule, diagnostic.message);
+        }
+    }
+    if failed {
+        process::exit(1);
+    }
+}
+
+const ENGINES: [Engine; 2] = [Engine::Tree, Engine::Vm];
+
+// The result of a program and the bindings it leaves.
+type Run = (Rc<Object>, HashMap<String, Rc<Object>>);
+
+// Runs a file from a fresh module cache.
+fn run_with(engine: Engine, file: &Path) -> Run {
+    module::set_engine(engine);
+    module::reset();
+    let mut bindings = scope(vec![]);
+    let result = module::execute(file, &mut bindings);
+    (result, bindings)
+}
+
+// Runs every file with both engines from a fresh module cache, the exit code is 1 when their results
+// or the bindings they leave differ.
+fn compare_files(files: &[PathBuf]) {
+    let mut differ = false;
+    for file in files.iter() {
+        let file = file.canonicalize().unwrap_or_else(|error| fail(&format!("Cannot open {}: {}", file.display(), error)));
+        let runs: Vec<Run> = ENGINES.iter().map(|engine| run_with(*engine, &file)).collect();
+        let (tree, vm) = (&runs[0], &runs[1]);
+        let mut names: Vec<&String> = tree.1.keys().chain(vm.1.keys()).collect();
+        names.sort();
+        names.dedup();
+        let mut differences = vec![];
+        if !vm::equivalent(&tree.0, &vm.0) {
+            differences.push((String::from("result"), tree.0.to_owned(), vm.0.to_owned()));
+        }
+        for name in names {
+            let none = Rc::new(Object::None);
+            let (lhs, rhs) = (tree.1.get(name).unwrap_or(&none), vm.1.get(name).unwrap_or(&none));
+            if !vm::equivalent(lhs, rhs) {
+                differences.push((name.to_owned(), lhs.to_owned(), rhs.to_owned()));
+            }
+        }
+        if differences.is_empty() {
+            println!("ok {}", file.display());
+        }
+        for (name, tree, vm) in differences.iter() {
+            println!("differs {}: {} is {} with the tree-walker and {} with the vm", file.display(), name, tree.to_string(), vm.to_string());
+        }
+        differ = differ || !differences.is_empty();
+    }
+    if differ {
+        process::exit(1);
+    }
+}
+
+// Times every file with both engines and reports the fastest of `runs` runs, which is the least
+// disturbed by the rest of the machine.
+fn bench_files(files: &[PathBuf], runs: usize) {
+    println!("{:<40} {:>12} {:>12} {:>8}", "file", "tree", "vm", "speedup");
+    for file in files.iter() {
+        let path = file.canonicalize().unwrap_or_else(|error| fail(&format!("Cannot open {}: {}", file.display(), error)));
+        let mut timings = vec![];
+        for engine in ENGINES.iter() {
+            let mut fastest = Duration::MAX;
+            for _ in 0..runs {
+                let start = Instant::now();
+                let (result, _bindings) = run_with(*engine, &path);
+                fastest = fastest.min(start.elapsed());
+                if let Object::Error(message) = &*result {
+                    fail(&format!("{} failed: {}", file.display(), message));
+                }
+            }
+            timings.push(fastest);
+        }
+        let milliseconds = |duration: &Duration| format!("{:.1} ms", duration.as_secs_f64() * 1000.0);
+        let speedup = timings[0].as_secs_f64() / timings[1].as_secs_f64();
+        println!("{:<40} {:>12} {:>12} {:>7.1}x", file.display(), milliseconds(&timings[0]), milliseconds(&timings[1]), speedup);
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let command = cli::parse(&args).unwrap_or_else(|error| {
+        eprintln!("{}\n\n{}", error, cli::USAGE);
+        process::exit(2);
+    });
+    match env::var("LIM_ENGINE").as_deref() {
+        Err(_) | Ok("vm") => {}
+        Ok("tree") => module::set_engine(Engine::Tree),
+        Ok(engine) => fail(&format!("Unknown engine {}, expected tree or vm", engine)),
+    }
+    let directory = env::current_dir().unwrap_or_default();
+    let result = match command {
+        Command::Help => {
+            println!("{}", cli::USAGE);
+            return;
+        }
+        Command::Run(Some(file), args) => {
+            let file = file.canonicalize().unwrap_or_else(|error| fail(&format!("Cannot open {}: {}", file.display(), error)));
+            load_project(file.parent().unwrap());
+            module::execute(&file, &mut scope(args))
+        }
+        Command::Run(None, args) => match load_project(&directory) {
+            Some(entry) => module::execute(&entry, &mut scope(args)),
+            None if !io::stdin().is_terminal() => {
+                module::evaluate(&directory.join("<stdin>"), &read_stdin(), &mut scope(args))
+            }
+            None => {
+                repl::run(scope(args));
+                Rc::new(Object::None)
+            }
+        },
+        Command::Eval(code, args) => {
+            load_project(&directory);
+            module::evaluate(&directory.join("<eval>"), &code, &mut scope(args))
+        }
+        Command::Tokens(file) => match lex_with_comments(&read_source(&file)) {
+            Some(tokens) => return print_json(dump::tokens(&tokens)),
+            None => fail(&format!("Syntax error in {}", file.display())),
+        },
+        Command::Ast(file) => match lex_spanned(&read_source(&file)).and_then(|tokens| Some((parse_spanned(tokens.to_vec())?, tokens))) {
+            Some((statements, _tokens)) if statements.iter().any(|(statement, _span)| statement.is_incomplete()) => {
+                fail(&format!("Unexpected end of input in {}", file.display()))
+            }
+            Some((statements, tokens)) => return print_json(dump::ast(&statements, &tokens)),
+            None => fail(&format!("Syntax error in {}", file.display())),
+        },
+        Command::Fmt(files, check) => return format_files(&files, check),
+        Command::Check(files, json) => return check_files(&files, json, &directory),
+        Command::Compare(files) => {
+            compare_files(&files);
+            Rc::new(Object::None)
+        }
+        Command::Bench(files, runs) => {
+            bench_files(&files, runs);
+            Rc::new(Object::None)
+        }
+        Command::Lsp => process::exit(lsp::serve(io::stdin().lock(), io::stdout())),
+        Command::Repl(args) => {
+            load_project(&directory);
+            repl::run(scope(args));
+            Rc::new(Object::None)
+        }
+        Command::Stdin(args) => {
+            load_project(&directory);
+            module::evaluate(&directory.join("<stdin>"), &read_stdin(), &mut scope(args))
+        }
+    };
+    if let Object::Error(message) = &*result {
+        fail(message);
+    }
+    registry::unload();
 }
@@ -0,0 +1,206 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::module::EXTENSION;
+
+pub const MANIFEST: &str = "lim.toml";
+
+#[derive(Deserialize)]
+struct Manifest {
+    package: Package,
+    #[serde(default)]
+    dependencies: BTreeMap<String, Dependency>,
+    #[serde(default)]
+    lint: BTreeMap<String, String>,
+}
+
+#[derive(Deserialize)]
+struct Package {
+    name: String,
+    #[serde(default = "default_entry")]
+    entry: String,
+    #[serde(default = "default_roots")]
+    roots: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct Dependency {
+    path: String,
+}
+
+fn default_entry() -> String {
+    format!("main.{}", EXTENSION)
+}
+
+fn default_roots() -> Vec<String> {
+    vec![String::from(".")]
+}
+
+#[derive(Debug)]
+pub struct Project {
+    pub name: String,
+    pub directory: PathBuf,
+    pub entry: PathBuf,
+    pub roots: Vec<PathBuf>,
+    pub dependencies: BTreeMap<String, Project>,
+    pub lint: BTreeMap<String, String>,
+}
+
+// Walks up from `start` to the closest directory holding a manifest.
+pub fn find(start: &Path) -> Option<PathBuf> {
+    start.ancestors().map(|directory| directory.join(MANIFEST)).find(|manifest| manifest.is_file())
+}
+
+pub fn load(manifest: &Path) -> Result<Project, String> {
+    load_project(manifest, &mut BTreeMap::new(), &mut vec![])
+}
+
+// `packages` remembers where every package name was found so that two different directories
+// claiming the same name are reported instead of silently picking one.
+fn load_project(manifest: &Path, packages: &mut BTreeMap<String, PathBuf>, loading: &mut Vec<PathBuf>) -> Result<Project, String> {
+    let manifest = manifest.canonicalize().map_err(|error| format!("Cannot open {}: {}", manifest.display(), error))?;
+    let directory = manifest.parent().unwrap().to_path_buf();
+    if loading.contains(&directory) {
+        return Err(format!("Cyclic dependency on {}", directory.display()));
+    }
+    let content = fs::read_to_string(&manifest).map_err(|error| format!("Cannot read {}: {}", manifest.display(), error))?;
+    let parsed: Manifest = toml::from_str(&content).map_err(|error| format!("Invalid {}: {}", manifest.display(), error))?;
+    match packages.get(&parsed.package.name) {
+        Some(other) if *other != directory => {
+            return Err(format!("Conflicting packages named {}: {} and {}", parsed.package.name, other.display(), directory.display()));
+        }
+        _ => {
+            packages.insert(parsed.package.name.to_owned(), directory.to_owned());
+        }
+    }
+    loading.push(directory.to_owned());
+    let mut dependencies = BTreeMap::new();
+    for (name, dependency) in parsed.dependencies.iter() {
+        let project = load_project(&directory.join(&dependency.path).join(MANIFEST), packages, loading)?;
+        if project.name != *name {
+            return Err(format!("Dependency {} of {} points to package {}", name, parsed.package.name, project.name));
+        }
+        dependencies.insert(name.to_owned(), project);
+    }
+    loading.pop();
+    Ok(Project {
+        name: parsed.package.name,
+        entry: directory.join(&parsed.package.entry),
+        roots: parsed.package.roots.iter().map(|root| directory.join(root)).collect(),
+        directory,
+        dependencies,
+        lint: parsed.lint,
+    })
+}
+
+impl Project {
+    // The project owning a file is the most nested one whose directory contains it.
+    pub fn owner(&self, file: &Path) -> Option<&Project> {
+        match self.dependencies.values().filter_map(|dependency| dependency.owner(file)).next() {
+            Some(owner) => Some(owner),
+            None if file.starts_with(&self.directory) => Some(self),
+            None => None,
+        }
+    }
+
+    fn local(&self, name: &str) -> Result<Option<PathBuf>, String> {
+        let file = Path::new(name).with_extension(EXTENSION);
+        let candidates: Vec<PathBuf> = self.roots.iter().map(|root| root.join(&file)).filter(|candidate| candidate.is_file()).collect();
+        match candidates.len() {
+            0 => Ok(None),
+            1 => Ok(candidates.into_iter().next()),
+            _ => Err(format!(
+                "Import {} is ambiguous in {}: {}",
+                name,
+                self.name,
+                candidates.iter().map(|candidate| candidate.display().to_string()).collect::<Vec<String>>().join(", ")
+            )),
+        }
+    }
+
+    // `dependency` imports the dependency's entry point, `dependency/module` a module in its roots,
+    // anything else is looked up in this project's roots.
+    pub fn resolve(&self, name: &str) -> Result<Option<PathBuf>, String> {
+        let mut segments = name.splitn(2, '/');
+        let first = segments.next().unwrap_or_default();
+        let local = self.local(name)?;
+        match self.dependencies.get(first) {
+            Some(dependency) => {
+                if let Some(local) = local {
+                    return Err(format!("Import {} matches both dependency {} and {}", name, first, local.display()));
+                }
+                match segments.next() {
+                    Some(rest) => dependency.local(rest),
+                    None => Ok(Some(dependency.entry.to_owned())),
+                }
+            }
+            None => Ok(local),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{load, Project};
+    use crate::module;
+    use crate::testing::{fixture, run_at};
+
+    fn project(directory: &Path) -> Result<Project, String> {
+        load(&directory.join("app/lim.toml"))
+    }
+
+    #[test]
+    fn manifests_resolve_imports_through_the_project() {
+        let directory = fixture(
+            "manifests_resolve_imports_through_the_project",
+            &[
+                ("app/lim.toml", "[package]\nname = 'app'\nentry = 'src/main.lim'\nroots = ['src', 'lib']\n\n[dependencies]\nutils = { path = '../utils' }\n"),
+                ("app/lib/helper.lim", "name = 'helper'\n"),
+                ("utils/lim.toml", "[package]\nname = 'utils'\n"),
+                ("utils/main.lim", "strings = import('strings')\nname = 'utils'\n"),
+                ("utils/strings.lim", "name = 'strings'\n"),
+            ],
+        );
+        let project = project(&directory).unwrap();
+        assert_eq!(project.entry, directory.join("app/src/main.lim"));
+        assert_eq!(project.dependencies["utils"].directory, directory.join("utils"));
+        module::set_project(project);
+        let code = "[import('helper').name, import('utils').name, import('utils').strings.name, import('utils/strings').name]";
+        assert_eq!(run_at(&directory.join("app/src/main.lim"), code), "[helper, utils, strings, strings]");
+    }
+
+    #[test]
+    fn manifests_report_conflicts_and_cycles() {
+        let manifest = |name: &str, dependencies: &str| format!("[package]\nname = '{}'\n\n[dependencies]\n{}\n", name, dependencies);
+        let directory = fixture("manifests_report_conflicts_and_cycles", &[("app/lim.toml", &manifest("app", "a = { path = '../a' }")), ("a/lim.toml", &manifest("a", "app = { path = '../app' }"))]);
+        assert_eq!(project(&directory).unwrap_err(), format!("Cyclic dependency on {}", directory.join("app").display()));
+        let directory = fixture("manifests_report_conflicting_names", &[("app/lim.toml", &manifest("app", "a = { path = '../a' }\nb = { path = '../b' }")), ("a/lim.toml", &manifest("a", "")), ("b/lim.toml", &manifest("a", ""))]);
+        assert_eq!(project(&directory).unwrap_err(), format!("Conflicting packages named a: {} and {}", directory.join("a").display(), directory.join("b").display()));
+        let directory = fixture("manifests_report_renamed_dependencies", &[("app/lim.toml", &manifest("app", "b = { path = '../a' }")), ("a/lim.toml", &manifest("a", ""))]);
+        assert_eq!(project(&directory).unwrap_err(), "Dependency b of app points to package a");
+    }
+
+    #[test]
+    fn manifests_report_ambiguous_imports() {
+        let directory = fixture(
+            "manifests_report_ambiguous_imports",
+            &[
+                ("app/lim.toml", "[package]\nname = 'app'\nroots = ['src', 'lib']\n\n[dependencies]\nutils = { path = '../utils' }\n"),
+                ("app/src/helper.lim", ""),
+                ("app/lib/helper.lim", ""),
+                ("app/src/utils.lim", ""),
+                ("utils/lim.toml", "[package]\nname = 'utils'\n"),
+            ],
+        );
+        let project = project(&directory).unwrap();
+        let (src, lib) = (directory.join("app/src"), directory.join("app/lib"));
+        assert_eq!(project.resolve("helper").unwrap_err(), format!("Import helper is ambiguous in app: {}, {}", src.join("helper.lim").display(), lib.join("helper.lim").display()));
+        assert_eq!(project.resolve("utils").unwrap_err(), format!("Import utils matches both dependency utils and {}", src.join("utils.lim").display()));
+        assert_eq!(project.resolve("missing"), Ok(None));
+    }
+}
@@ -0,0 +1,282 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::rc::Rc;
+
+use crate::interpreter::{call, get, is_truthy};
+use object::{Object, Table};
+
+pub const MAP_METHODS: [&str; 8] = ["len", "get", "set", "has", "remove", "keys", "values", "items"];
+pub const SET_METHODS: [&str; 8] = ["len", "add", "has", "remove", "values", "union", "intersection", "difference"];
+
+fn has_field(object: &Rc<Object>, name: &str) -> bool {
+    match &**object {
+        Object::Instance(fields) => fields.contains_key(name),
+        _ => false,
+    }
+}
+
+fn hash_of<T: Hash>(value: T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+// Floats holding an integral value hash like the equal Integer so that `1` and `1.0` are the same key,
+// NaN never equals itself and is therefore refused as a key.
+pub fn hash(key: &Rc<Object>) -> Result<u64, Rc<Object>> {
+    match &**key {
+        Object::None => Ok(0),
+        Object::Integer(number) => Ok(hash_of(number)),
+        Object::Float(number) => {
+            if number.is_nan() {
+                Err(Rc::new(Object::Error(String::from("NaN cannot be used as a key"))))
+            } else if number.fract() == 0.0 && *number >= i64::MIN as f64 && *number <= i64::MAX as f64 {
+                Ok(hash_of(*number as i64))
+            } else {
+                Ok(hash_of(number.to_bits()))
+            }
+        }
+        Object::String(string) => Ok(hash_of(string)),
+        Object::EnumVariant(name, payload) => {
+            let payload = payload.iter().map(hash).collect::<Result<Vec<u64>, Rc<Object>>>()?;
+            Ok(hash_of((name, payload)))
+        }
+        Object::Instance(_) if has_field(key, "$hash") => {
+            let method = get(key, String::from("$hash"));
+            match *call(&method, &vec![], key) {
+                Object::Integer(number) => Ok(hash_of(number)),
+                _ => Err(Rc::new(Object::Error(String::from("$hash must return an Integer")))),
+            }
+        }
+        _ => Err(Rc::new(Object::Error(format!("{} is not hashable", key.to_string())))),
+    }
+}
+
+pub fn key_eq(lhs: &Rc<Object>, rhs: &Rc<Object>) -> bool {
+    match (&**lhs, &**rhs) {
+        (Object::None, Object::None) => true,
+        (Object::Integer(lhs), Object::Integer(rhs)) => lhs == rhs,
+        (Object::Integer(lhs), Object::Float(rhs)) => *lhs as f64 == *rhs,
+        (Object::Float(lhs), Object::Integer(rhs)) => *lhs == *rhs as f64,
+        (Object::Float(lhs), Object::Float(rhs)) => lhs == rhs,
+        (Object::String(lhs), Object::String(rhs)) => lhs == rhs,
+        (Object::EnumVariant(lhs, lhs_payload), Object::EnumVariant(rhs, rhs_payload)) => {
+            lhs == rhs && lhs_payload.len() == rhs_payload.len() && lhs_payload.iter().zip(rhs_payload.iter()).all(|(lhs, rhs)| key_eq(lhs, rhs))
+        }
+        (Object::Instance(_), _) if has_field(lhs, "$eq") => {
+            let method = get(lhs, String::from("$eq"));
+            is_truthy(&call(&method, &vec![rhs.to_owned()], lhs))
+        }
+        (Object::Instance(_), Object::Instance(_)) => Rc::ptr_eq(lhs, rhs),
+        _ => false,
+    }
+}
+
+pub fn lookup(table: &Table, key: &Rc<Object>) -> Result<Option<Rc<Object>>, Rc<Object>> {
+    Ok(table.get(hash(key)?, &mut |other| key_eq(key, other)))
+}
+
+pub fn insert(table: &mut Table, key: Rc<Object>, value: Rc<Object>) -> Result<(), Rc<Object>> {
+    let hash = hash(&key)?;
+    let lookup_key = key.to_owned();
+    table.insert(hash, key, value, &mut |other| key_eq(&lookup_key, other));
+    Ok(())
+}
+
+fn result(value: Result<Rc<Object>, Rc<Object>>) -> Rc<Object> {
+    value.unwrap_or_else(|error| error)
+}
+
+fn argument<'a>(args: &'a [Rc<Object>], index: usize, method: &str) -> Result<&'a Rc<Object>, Rc<Object>> {
+    args.get(index).ok_or_else(|| Rc::new(Object::Error(format!("{} expects {} arguments", method, index + 1))))
+}
+
+fn table(collection: &Rc<Object>) -> &Table {
+    match &**collection {
+        Object::Map(table) | Object::Set(table) => table,
+        _ => unreachable!(),
+    }
+}
+
+// The table is only borrowed mutably for the change itself, never while user code runs.
+fn update<T>(collection: &Rc<Object>, change: impl FnOnce(&mut Table) -> T) -> T {
+    let mut collection = collection.to_owned();
+    match unsafe { Rc::get_mut_unchecked(&mut collection) } {
+        Object::Map(table) | Object::Set(table) => change(table),
+        _ => unreachable!(),
+    }
+}
+
+// Finds the index of a key in a map or set. `$hash` and `$eq` can change the collection they are
+// looked up in, so no borrow of its table is held while they run.
+fn locate(collection: &Rc<Object>, key: &Rc<Object>) -> Result<(u64, Option<usize>), Rc<Object>> {
+    let hash = hash(key)?;
+    for (index, other) in table(collection).candidates(hash) {
+        if key_eq(key, &other) {
+            // The comparison may have moved or removed the entry.
+            let index = match table(collection).key_at(index) {
+                Some(stored) if Rc::ptr_eq(stored, &other) => Some(index),
+                _ => table(collection).position(&other),
+            };
+            return Ok((hash, index));
+        }
+    }
+    Ok((hash, None))
+}
+
+// `map[key] = value` as well as `map.set(key, value)`.
+pub fn set(map: &Rc<Object>, key: Rc<Object>, value: Rc<Object>) -> Result<(), Rc<Object>> {
+    let (hash, index) = locate(map, &key)?;
+    update(map, |table| match index {
+        Some(index) => table.set_at(index, value),
+        None => table.push(hash, key, value),
+    });
+    Ok(())
+}
+
+pub fn call_map_method(name: &str, instance: &Rc<Object>, args: &[Rc<Object>]) -> Rc<Object> {
+    result((|| match name {
+        "len" => Ok(Rc::new(Object::Integer(table(instance).len() as i64))),
+        "get" => {
+            let default = args.get(1).cloned().unwrap_or_else(|| Rc::new(Object::None));
+            let (_, index) = locate(instance, argument(args, 0, name)?)?;
+            Ok(index.map_or(default, |index| table(instance).value_at(index)))
+        }
+        "set" => {
+            set(instance, argument(args, 0, name)?.to_owned(), argument(args, 1, name)?.to_owned())?;
+            Ok(Rc::new(Object::None))
+        }
+        "has" => Ok(Rc::new(Object::Integer(locate(instance, argument(args, 0, name)?)?.1.is_some() as i64))),
+        "remove" => {
+            let key = argument(args, 0, name)?;
+            match locate(instance, key)? {
+                (_, Some(index)) => Ok(update(instance, |table| table.remove_at(index))),
+                (_, None) => Err(Rc::new(Object::Error(format!("Key {} does not exist", key.to_string())))),
+            }
+        }
+        "keys" => Ok(Rc::new(Object::Array(table(instance).keys().cloned().collect()))),
+        "values" => Ok(Rc::new(Object::Array(table(instance).values().cloned().collect()))),
+        "items" => Ok(Rc::new(Object::Array(table(instance).iter().map(|(key, value)| Rc::new(Object::Array(vec![key.to_owned(), value.to_owned()]))).collect()))),
+        _ => Err(Rc::new(Object::Error(format!("Map has no method {}", name)))),
+    })())
+}
+
+pub fn call_set_method(name: &str, instance: &Rc<Object>, args: &[Rc<Object>]) -> Rc<Object> {
+    let other = |args: &[Rc<Object>]| match &**argument(args, 0, name)? {
+        Object::Set(other) => Ok(other.to_owned()),
+        other => Err(Rc::new(Object::Error(format!("{} is not a Set", other.to_string())))),
+    };
+    result((|| match name {
+        "len" => Ok(Rc::new(Object::Integer(table(instance).len() as i64))),
+        "add" => {
+            for arg in args.iter() {
+                if let (hash, None) = locate(instance, arg)? {
+                    update(instance, |table| table.push(hash, arg.to_owned(), Rc::new(Object::None)));
+                }
+            }
+            Ok(Rc::new(Object::None))
+        }
+        "has" => Ok(Rc::new(Object::Integer(locate(instance, argument(args, 0, name)?)?.1.is_some() as i64))),
+        "remove" => {
+            let key = argument(args, 0, name)?;
+            match locate(instance, key)? {
+                (_, Some(index)) => update(instance, |table| table.remove_at(index)),
+                (_, None) => return Err(Rc::new(Object::Error(format!("Element {} does not exist", key.to_string())))),
+            };
+            Ok(Rc::new(Object::None))
+        }
+        "values" => Ok(Rc::new(Object::Array(table(instance).keys().cloned().collect()))),
+        // Both work on copies, comparing their elements cannot change them.
+        "union" => {
+            let mut union = table(instance).to_owned();
+            for key in other(args)?.keys() {
+                insert(&mut union, key.to_owned(), Rc::new(Object::None))?;
+            }
+            Ok(Rc::new(Object::Set(union)))
+        }
+        "intersection" | "difference" => {
+            let (keys, other): (Vec<_>, _) = (table(instance).keys().cloned().collect(), other(args)?);
+            let mut rtn = Table::new();
+            for key in keys {
+                if lookup(&other, &key)?.is_some() == (name == "intersection") {
+                    insert(&mut rtn, key, Rc::new(Object::None))?;
+                }
+            }
+            Ok(Rc::new(Object::Set(rtn)))
+        }
+        _ => Err(Rc::new(Object::Error(format!("Set has no method {}", name)))),
+    })())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::run;
+
+    #[test]
+    fn maps_keep_insertion_order() {
+        assert_eq!(run("m = {'b': 2, 'a': 1, 3: 'three'}\n[m, m.keys(), m.values()]"), "[{b: 2, a: 1, 3: three}, [b, a, 3], [2, 1, three]]");
+        assert_eq!(run("m = {'b': 2, 'a': 1}\nm['c'] = 3\nm['b'] = 20\nm.items()"), "[[b, 20], [a, 1], [c, 3]]");
+    }
+
+    #[test]
+    fn maps_look_up_keys() {
+        assert_eq!(run("m = {'a': 1, 3: 'three', 1.0: 'one'}\n[m['a'], m[3], m[1], m.get('z'), m.has('a'), m.has('z')]"), "[1, three, one, None, 1, 0]");
+        assert_eq!(run("{'a': 1}['z']"), "Error(\"Key z does not exist\")");
+        assert_eq!(run("{[1]: 1}"), "Error(\"[1] is not hashable\")");
+    }
+
+    #[test]
+    fn maps_remove_keys() {
+        assert_eq!(run("m = {'a': 1, 'b': 2, 'c': 3}\n[m.remove('b'), m, m.len()]"), "[2, {a: 1, c: 3}, 2]");
+        assert_eq!(run("{'a': 1}.remove('z')"), "Error(\"Key z does not exist\")");
+    }
+
+    #[test]
+    fn sets_hold_distinct_values_in_insertion_order() {
+        assert_eq!(run("s = {3, 1, 2, 1}\n[s, s.has(1), s.has(5), s.len()]"), "[set[3, 1, 2], 1, 0, 3]");
+        assert_eq!(run("s = {3, 1, 2}\ns.remove(1)\ns.add(0)\ns.add(3)\ns"), "set[3, 2, 0]");
+        assert_eq!(run("s = set[1, 2, 3]\n[s.union(set[4]), s.intersection(set[2, 3, 5]), s.difference(set[2])]"), "[set[1, 2, 3, 4], set[2, 3], set[1, 3]]");
+    }
+
+    // Sets are shown the way the `set` prefix builds them.
+    #[test]
+    fn sets_show_as_the_set_prefix() {
+        assert_eq!(run("[set[], {1, 'a'}]"), "[set[], set[1, a]]");
+        for code in ["set[]", "{3, 1, 2}"] {
+            let shown = run(code);
+            assert_eq!(run(&shown), shown);
+        }
+    }
+
+    #[test]
+    fn empty_maps_and_sets_are_falsy() {
+        assert_eq!(run("[{}, set[], {1: 1}, set[1]].filter((value) { value }).len()"), "2");
+        assert_eq!(run("[[1].filter((x) { {} }), [1].filter((x) { set[] }), [1].filter((x) { {1: 1} }), [1].filter((x) { set[1] })]"), "[[], [], [1], [1]]");
+    }
+
+    // `$hash` adds an entry to the map its key is looked up in and `$eq` removes it again, moving the
+    // entry being compared.
+    #[test]
+    fn hash_and_eq_can_change_their_map() {
+        let code = "class Key {
+  id = 1
+  table = None
+  $hash = () {
+    self.table[self.id + 100] = 'hashed'
+    self.id
+  }
+  $eq = (other) {
+    self.table.remove(self.id + 100)
+    1
+  }
+}
+table = {}
+key = Key()
+key.table = table
+table[key] = 'first'
+table[key] = 'second'
+set = {key}
+[table.get(key), table.has(key), table.len(), set.has(key), table.has(101)]";
+        assert_eq!(run(code), "[second, 1, 1, 1, 0]");
+    }
+}
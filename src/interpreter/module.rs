@@ -0,0 +1,276 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::interpreter::{self, interpret};
+use crate::manifest::Project;
+use crate::vm;
+use lexer::lexer::lex;
+use object::Object;
+use parser::node::Node;
+use parser::parser::parse;
+
+pub const EXTENSION: &str = "lim";
+
+// The tree-walking interpreter is kept as the reference for the bytecode virtual machine.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Engine {
+    Tree,
+    Vm,
+}
+
+thread_local! {
+    static MODULES: RefCell<HashMap<PathBuf, Rc<Object>>> = RefCell::new(HashMap::new());
+    static LOADING: RefCell<Vec<PathBuf>> = const { RefCell::new(vec![]) };
+    static SEARCH_PATH: RefCell<Vec<PathBuf>> = const { RefCell::new(vec![]) };
+    static PROJECT: RefCell<Option<Rc<Project>>> = const { RefCell::new(None) };
+    static ENGINE: Cell<Engine> = const { Cell::new(Engine::Vm) };
+}
+
+fn error(message: String) -> Rc<Object> {
+    Rc::new(Object::Error(message))
+}
+
+pub fn add_search_path(path: PathBuf) {
+    SEARCH_PATH.with(|search_path| search_path.borrow_mut().push(path));
+}
+
+pub fn set_engine(engine: Engine) {
+    ENGINE.with(|current| current.set(engine));
+}
+
+pub fn engine() -> Engine {
+    ENGINE.with(|engine| engine.get())
+}
+
+// Forgets the modules imported and the changes made to natives so far, so that the next program
+// starts afresh.
+pub fn reset() {
+    MODULES.with(|modules| modules.borrow_mut().clear());
+    interpreter::reset_natives();
+}
+
+pub fn set_project(project: Project) {
+    PROJECT.with(|current| *current.borrow_mut() = Some(Rc::new(project)));
+}
+
+// Asks the project owning the importing file, so that dependencies resolve their own imports.
+fn resolve_in_project(name: &str) -> Result<Option<PathBuf>, String> {
+    let project = match PROJECT.with(|project| project.borrow().to_owned()) {
+        Some(project) => project,
+        None => return Ok(None),
+    };
+    let importer = LOADING.with(|loading| loading.borrow().last().cloned());
+    let owner = importer.as_ref().and_then(|importer| project.owner(importer)).unwrap_or(&project);
+    owner.resolve(name)
+}
+
+// Explicit roots come first, then the entries of LIM_PATH in order.
+fn search_path() -> Vec<PathBuf> {
+    let mut roots = SEARCH_PATH.with(|search_path| search_path.borrow().to_vec());
+    if let Some(paths) = env::var_os("LIM_PATH") {
+        roots.extend(env::split_paths(&paths));
+    }
+    roots
+}
+
+fn importer_directory() -> PathBuf {
+    LOADING.with(|loading| match loading.borrow().last().and_then(|file| file.parent()) {
+        Some(directory) => directory.to_path_buf(),
+        None => env::current_dir().unwrap_or_default(),
+    })
+}
+
+fn with_extension(name: &str) -> PathBuf {
+    let path = PathBuf::from(name);
+    if path.extension().is_some() {
+        path
+    } else {
+        path.with_extension(EXTENSION)
+    }
+}
+
+// Names starting with `.` are relative to the importing file, other names are looked up in the project
+// described by `lim.toml` and then in the search path.
+pub fn resolve(name: &str) -> Result<PathBuf, String> {
+    let file = with_extension(name);
+    let candidates = if name.starts_with('.') {
+        vec![importer_directory().join(&file)]
+    } else if file.is_absolute() {
+        vec![file.to_owned()]
+    } else if let Some(path) = resolve_in_project(name)? {
+        vec![path]
+    } else {
+        search_path().iter().map(|root| root.join(&file)).collect()
+    };
+    for candidate in candidates.iter() {
+        if candidate.is_file() {
+            return candidate.canonicalize().map_err(|error| format!("Cannot open module {}: {}", name, error));
+        }
+    }
+    Err(format!("Module {} not found", name))
+}
+
+fn module_name(path: &Path) -> String {
+    path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default()
+}
+
+// An error value produced by a top-level statement is uncaught, it stops the program and becomes
+// its result. Binding an error to a name is not one, the assignment itself evaluates to None, but
+// a failed destructuring, index or member assignment is.
+fn run(ast: Node, scope: &mut HashMap<String, Rc<Object>>) -> Rc<Object> {
+    if engine() == Engine::Vm {
+        return vm::run(ast, scope);
+    }
+    let statements = match ast {
+        Node::Program(statements) => statements,
+        statement => vec![statement],
+    };
+    let mut rtn = Rc::new(Object::None);
+    for statement in statements {
+        if statement == Node::Empty {
+            continue;
+        }
+        rtn = interpret(statement, scope);
+        if let Object::Error(_) = *rtn {
+            return rtn;
+        }
+    }
+    rtn
+}
+
+// Runs source code as if it was read from `path` so that relative imports inside it resolve next to it.
+pub fn evaluate(path: &Path, code: &str, scope: &mut HashMap<String, Rc<Object>>) -> Rc<Object> {
+    let ast = match lex(code).and_then(parse) {
+        Some(ast) => ast,
+        None => return error(format!("Syntax error in {}", path.display())),
+    };
+    let incomplete = match &ast {
+        Node::Program(statements) => statements.iter().any(|statement| statement.is_incomplete()),
+        statement => statement.is_incomplete(),
+    };
+    if incomplete {
+        return error(format!("Unexpected end of input in {}", path.display()));
+    }
+    LOADING.with(|loading| loading.borrow_mut().push(path.to_path_buf()));
+    let rtn = run(ast, scope);
+    LOADING.with(|loading| loading.borrow_mut().pop());
+    rtn
+}
+
+pub fn execute(path: &Path, scope: &mut HashMap<String, Rc<Object>>) -> Rc<Object> {
+    match fs::read_to_string(path) {
+        Ok(code) => evaluate(path, &code, scope),
+        Err(read_error) => error(format!("Cannot read {}: {}", path.display(), read_error)),
+    }
+}
+
+pub fn import(name: &str) -> Rc<Object> {
+    let path = match resolve(name) {
+        Ok(path) => path,
+        Err(message) => return error(message),
+    };
+    if let Some(module) = MODULES.with(|modules| modules.borrow().get(&path).cloned()) {
+        return module;
+    }
+    let cycle = LOADING.with(|loading| {
+        let loading = loading.borrow();
+        loading.iter().position(|file| *file == path).map(|start| {
+            let mut files: Vec<String> = loading[start..].iter().map(|file| file.display().to_string()).collect();
+            files.push(path.display().to_string());
+            files.join(" -> ")
+        })
+    });
+    if let Some(cycle) = cycle {
+        return error(format!("Cyclic import: {}", cycle));
+    }
+    let mut scope = HashMap::new();
+    let rtn = execute(&path, &mut scope);
+    if let Object::Error(_) = *rtn {
+        return rtn;
+    }
+    let module = Rc::new(Object::Module(module_name(&path), scope));
+    MODULES.with(|modules| modules.borrow_mut().insert(path, module.to_owned()));
+    module
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::env;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use std::rc::Rc;
+
+    use super::{execute, reset, set_engine};
+    use crate::testing::{fixture, run, run_at, ENGINES};
+    use crate::vm;
+    use object::Object;
+
+    type Run = (Rc<Object>, HashMap<String, Rc<Object>>);
+
+    #[test]
+    fn syntax_errors_are_errors() {
+        assert_eq!(run("enum Color {\n  Red\n  1\n}"), "Error(\"Syntax error in <test>\")");
+        assert_eq!(run("x = 1\nx: { a: b }"), "Error(\"Syntax error in <test>\")");
+        assert_eq!(run("[a, ,] = [1]"), "Error(\"Syntax error in <test>\")");
+    }
+
+    #[test]
+    fn imports_run_a_module_once() {
+        let directory = fixture("imports_run_a_module_once", &[("counter.lim", "items = []\nincrement = (n) { n + 1 }\n"), ("nested/user.lim", "counter = import('../counter')\n")]);
+        let code = "first = import('./counter')
+second = import('./counter')
+first.items.push(5)
+user = import('./nested/user')
+[second.items, user.counter.items, first.increment(1)]";
+        assert_eq!(run_at(&directory.join("<test>"), code), "[[5], [5], 2]");
+        assert_eq!(run_at(&directory.join("<test>"), "import('./counter').items"), "[]");
+    }
+
+    #[test]
+    fn imports_report_cycles_and_missing_modules() {
+        let directory = fixture("imports_report_cycles_and_missing_modules", &[("a.lim", "import('./b')\n"), ("b.lim", "import('./a')\n"), ("broken.lim", "1 / 0\n")]);
+        let (a, b) = (directory.join("a.lim"), directory.join("b.lim"));
+        let cycle = format!("Error(\"Cyclic import: {} -> {} -> {}\")", a.display(), b.display(), a.display());
+        assert_eq!(run_at(&directory.join("<test>"), "import('./a')"), cycle);
+        assert_eq!(run_at(&directory.join("<test>"), "import('./missing')"), "Error(\"Module ./missing not found\")");
+        assert_eq!(run_at(&directory.join("<test>"), "import('./broken')"), "Error(\"Division by zero\")");
+        assert_eq!(run_at(&directory.join("<test>"), "import(1)"), "Error(\"import expects a module name\")");
+    }
+
+    // Every program of examples/corpus runs on both engines without an uncaught error, and they agree
+    // on its result and on every binding it leaves.
+    #[test]
+    fn corpus() {
+        let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../examples/corpus");
+        let mut files: Vec<PathBuf> = fs::read_dir(&directory).unwrap().map(|entry| entry.unwrap().path()).filter(|path| path.extension().is_some_and(|extension| extension == "lim")).collect();
+        files.sort();
+        assert!(!files.is_empty());
+        for file in files.iter() {
+            let runs: Vec<Run> = ENGINES
+                .iter()
+                .map(|engine| {
+                    set_engine(*engine);
+                    reset();
+                    let mut scope = HashMap::new();
+                    (execute(file, &mut scope), scope)
+                })
+                .collect();
+            let (tree, vm) = (&runs[0], &runs[1]);
+            assert!(!matches!(*vm.0, Object::Error(_)), "{} failed: {}", file.display(), vm.0.to_string());
+            assert!(vm::equivalent(&tree.0, &vm.0), "{}: the result is {} with the tree-walker and {} with the vm", file.display(), tree.0.to_string(), vm.0.to_string());
+            let mut names: Vec<&String> = tree.1.keys().chain(vm.1.keys()).collect();
+            names.sort();
+            names.dedup();
+            for name in names {
+                let none = Rc::new(Object::None);
+                let (lhs, rhs) = (tree.1.get(name).unwrap_or(&none), vm.1.get(name).unwrap_or(&none));
+                assert!(vm::equivalent(lhs, rhs), "{}: {} is {} with the tree-walker and {} with the vm", file.display(), name, lhs.to_string(), rhs.to_string());
+            }
+        }
+    }
+}
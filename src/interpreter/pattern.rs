@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::interpreter::{get, interpret, set_index, set_member};
+use crate::map;
+use object::Object;
+use parser::node::Node;
+
+pub enum Target {
+    Variable(String),
+    Member(Rc<Object>, String),
+    Index(Rc<Object>, Rc<Object>),
+}
+
+pub fn mismatch(pattern: &str, value: &Rc<Object>) -> Rc<Object> {
+    Rc::new(Object::Error(format!("Cannot destructure {} with {} pattern", value.to_string(), pattern)))
+}
+
+// The values the elements of an array pattern destructure, in order, the rest element gets an array.
+pub fn split(elements: usize, rest: Option<usize>, many: bool, value: &Rc<Object>) -> Result<Vec<Rc<Object>>, Rc<Object>> {
+    let values = match &**value {
+        Object::Array(values) => values,
+        _ => return Err(mismatch("an array", value)),
+    };
+    if many {
+        return Err(Rc::new(Object::Error(String::from("Only one rest element is allowed in a pattern"))));
+    }
+    let fits = match rest {
+        Some(_) => values.len() + 1 >= elements,
+        None => values.len() == elements,
+    };
+    if !fits {
+        return Err(Rc::new(Object::Error(format!("Cannot destructure {} values into {} elements", values.len(), elements))));
+    }
+    let mut split = vec![];
+    for index in 0..elements {
+        match rest {
+            Some(rest) if index == rest => split.push(Rc::new(Object::Array(values[rest..values.len() + rest + 1 - elements].to_vec()))),
+            Some(rest) if index > rest => split.push(values[values.len() + index - elements].to_owned()),
+            _ => split.push(values[index].to_owned()),
+        }
+    }
+    Ok(split)
+}
+
+fn destructure_elements(elements: &[Node], value: &Rc<Object>, scope: &mut HashMap<String, Rc<Object>>, targets: &mut Vec<(Target, Rc<Object>)>) -> Result<(), Rc<Object>> {
+    let variadic = |element: &&Node| matches!(element, Node::Variadic(_));
+    let rest = elements.iter().position(|element| variadic(&element));
+    let many = elements.iter().filter(variadic).count() > 1;
+    for (element, value) in elements.iter().zip(split(elements.len(), rest, many, value)?) {
+        let element: &Node = match element {
+            Node::Variadic(element) => element,
+            element => element,
+        };
+        destructure(element, &value, scope, targets)?;
+    }
+    Ok(())
+}
+
+// Walks a pattern against a value, collecting the assignments to perform without performing them
+// so that a pattern that does not fit leaves the scope untouched.
+pub fn destructure(pattern: &Node, value: &Rc<Object>, scope: &mut HashMap<String, Rc<Object>>, targets: &mut Vec<(Target, Rc<Object>)>) -> Result<(), Rc<Object>> {
+    match pattern {
+        Node::Identifier(name) => targets.push((Target::Variable(name.to_owned()), value.to_owned())),
+        Node::Member(instance, name) => {
+            let instance = interpret(*instance.to_owned(), scope);
+            if let Object::Enum(_, _) = *instance {
+                return variant(&instance, name, value);
+            }
+            targets.push((Target::Member(instance, name.to_owned()), value.to_owned()));
+        }
+        Node::Index(target, index) => {
+            let target = interpret(*target.to_owned(), scope);
+            let index = interpret(*index.to_owned(), scope);
+            targets.push((Target::Index(target, index), value.to_owned()));
+        }
+        Node::Call(variant, elements) => {
+            let payload = payload(&interpret(*variant.to_owned(), scope), value)?;
+            destructure_elements(elements, &payload, scope, targets)?;
+        }
+        Node::Parenthesized(pattern) => destructure(pattern, value, scope, targets)?,
+        Node::Tuple(elements) | Node::Array(elements) => destructure_elements(elements, value, scope, targets)?,
+        Node::Map(entries) => {
+            let table = match &**value {
+                Object::Map(table) => table,
+                _ => return Err(mismatch("a map", value)),
+            };
+            for entry in entries.iter() {
+                if let Node::KeyValue(key, pattern) = entry {
+                    let key = interpret(*key.to_owned(), scope);
+                    match map::lookup(table, &key)? {
+                        Some(value) => destructure(pattern, &value, scope, targets)?,
+                        None => return Err(Rc::new(Object::Error(format!("Key {} does not exist", key.to_string())))),
+                    }
+                }
+            }
+        }
+        Node::Literal(_, _) | Node::Number(_) | Node::Negation(_) => {
+            let expected = interpret(pattern.to_owned(), scope);
+            if !map::key_eq(&expected, value) {
+                return Err(Rc::new(Object::Error(format!("{} does not match {}", value.to_string(), expected.to_string()))));
+            }
+        }
+        _ => return Err(Rc::new(Object::Error(format!("Invalid pattern {:?}", pattern)))),
+    }
+    Ok(())
+}
+
+pub fn variant(enumeration: &Rc<Object>, name: &str, value: &Rc<Object>) -> Result<(), Rc<Object>> {
+    let expected = get(enumeration, name.to_owned());
+    if let Object::Error(_) = *expected {
+        return Err(expected);
+    }
+    if map::key_eq(&expected, value) {
+        Ok(())
+    } else {
+        Err(Rc::new(Object::Error(format!("{} does not match {}", value.to_string(), expected.to_string()))))
+    }
+}
+
+// The payload of a value that is the given variant, as an array its elements destructure.
+pub fn payload(variant: &Rc<Object>, value: &Rc<Object>) -> Result<Rc<Object>, Rc<Object>> {
+    let name = match &**variant {
+        Object::EnumVariant(name, payload) if payload.is_empty() => name,
+        Object::Error(_) => return Err(variant.to_owned()),
+        _ => return Err(Rc::new(Object::Error(format!("Invalid pattern {}", variant.to_string())))),
+    };
+    match &**value {
+        Object::EnumVariant(other, payload) if other == name => Ok(Rc::new(Object::Array(payload.to_owned()))),
+        _ => Err(Rc::new(Object::Error(format!("{} does not match {}", value.to_string(), name)))),
+    }
+}
+
+pub fn assign(targets: Vec<(Target, Rc<Object>)>, scope: &mut HashMap<String, Rc<Object>>) -> Rc<Object> {
+    for (target, value) in targets {
+        let result = match target {
+            Target::Variable(name) => {
+                scope.insert(name, value);
+                continue;
+            }
+            Target::Member(instance, name) => set_member(instance, name, value),
+            Target::Index(target, index) => set_index(target, &index, value),
+        };
+        if let Object::Error(_) = *result {
+            return result;
+        }
+    }
+    Rc::new(Object::None)
+}
+
+pub fn bind(pattern: &Node, value: &Rc<Object>, scope: &mut HashMap<String, Rc<Object>>) -> Rc<Object> {
+    let mut targets = vec![];
+    match destructure(pattern, value, scope, &mut targets) {
+        Ok(()) => assign(targets, scope),
+        Err(error) => error,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::run;
+
+    #[test]
+    fn rest_elements_are_spelled_either_way() {
+        assert_eq!(run("[first, rest...] = [1, 2, 3]\n[first, rest]"), "[1, [2, 3]]");
+        assert_eq!(run("a, *middle, b = 1, 2, 3, 4\n[a, middle, b]"), "[1, [2, 3], 4]");
+        assert_eq!(run("head, tail... = [1]\n[head, tail]"), "[1, []]");
+        assert_eq!(run("([first, rest..., last...] = [1, 2])"), "Error(\"Only one rest element is allowed in a pattern\")");
+    }
+
+    const SHAPES: &str = "enum Shape {
+  Circle
+  Square
+  Empty
+}
+";
+
+    #[test]
+    fn enum_variants_carry_a_payload() {
+        assert_eq!(run(&format!("{}[Shape.Circle(2), Shape.Square(3, 4), Shape.Empty, Shape.Empty()]", SHAPES)), "[Shape.Circle(2), Shape.Square(3, 4), Shape.Empty, Shape.Empty]");
+        assert_eq!(run(&format!("{}[(Shape.Circle(2) = Shape.Circle(2)), (Shape.Circle(2) = Shape.Circle(3))]", SHAPES)), "[None, Error(\"3 does not match 2\")]");
+        assert_eq!(run(&format!("{}{{Shape.Circle(2): 'two'}}[Shape.Circle(2)]", SHAPES)), "two");
+    }
+
+    #[test]
+    fn enum_payloads_destructure() {
+        let code = format!(
+            "{}square = Shape.Square(3, 4)
+Shape.Square(width, height) = square
+Shape.Square(side, rest...) = square
+[width, height, side, rest]",
+            SHAPES
+        );
+        assert_eq!(run(&code), "[3, 4, 3, [4]]");
+        assert_eq!(run(&format!("{}(Shape.Circle(radius) = Shape.Square(3, 4))", SHAPES)), "Error(\"Shape.Square(3, 4) does not match Shape.Circle\")");
+        assert_eq!(run(&format!("{}(Shape.Circle(radius) = Shape.Circle(1, 2))", SHAPES)), "Error(\"Cannot destructure 2 values into 1 elements\")");
+        assert_eq!(run(&format!("{}(Shape.Round(radius) = Shape.Circle(1))", SHAPES)), "Error(\"Variant Round does not exist for enum Shape\")");
+        assert_eq!(run("(print(radius) = 1)"), "Error(\"Invalid pattern Linked(\\\"print\\\")\")");
+    }
+
+    #[test]
+    fn match_arms_destructure_payloads() {
+        let area = |shape: &str| {
+            run(&format!(
+                "{}area = 0
+{}: {{
+  Shape.Circle(radius): {{ area = radius * radius * 3 }}
+  Shape.Square(width, height): {{ area = width * height }}
+  Shape.Empty: {{ area = 'empty' }}
+}}
+area",
+                SHAPES, shape
+            ))
+        };
+        assert_eq!(area("Shape.Circle(2)"), "12");
+        assert_eq!(area("Shape.Square(2, 5)"), "10");
+        assert_eq!(area("Shape.Empty"), "empty");
+        assert_eq!(area("Shape.Square(2)"), "0");
+    }
+}
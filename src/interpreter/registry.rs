@@ -0,0 +1,142 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ffi::CStr;
+use std::rc::Rc;
+
+use libloading::Library;
+use lim_abi::{LimAbiVersion, LimNative, LimNativeInfo, LIM_ABI_VERSION, LIM_NATIVE_PREFIX};
+use object::Object;
+
+use crate::extension;
+
+// Every shared library is opened once and every symbol looked up once. The function pointers stay
+// valid for as long as their library is held here, so they are dropped before the libraries are.
+#[derive(Default)]
+struct Registry {
+    libraries: HashMap<String, Library>,
+    functions: HashMap<(String, String), LimNative>,
+}
+
+thread_local! {
+    static REGISTRY: RefCell<Registry> = RefCell::new(Registry::default());
+}
+
+impl Registry {
+    fn library(&mut self, path: &str) -> Result<&Library, String> {
+        if !self.libraries.contains_key(path) {
+            let library = Library::new(path).map_err(|error| format!("Cannot load native library {}: {}", path, error))?;
+            check_version(path, &library)?;
+            self.libraries.insert(path.to_owned(), library);
+        }
+        Ok(&self.libraries[path])
+    }
+
+    fn function(&mut self, path: &str, symbol: &str) -> Result<LimNative, String> {
+        let key = (path.to_owned(), symbol.to_owned());
+        if let Some(function) = self.functions.get(&key) {
+            return Ok(*function);
+        }
+        let library = self.library(path)?;
+        // Natives declared with `#[lim_native]` are found through their description.
+        let info = format!("{}{}", LIM_NATIVE_PREFIX, symbol);
+        let function = unsafe {
+            match library.get::<*const LimNativeInfo>(info.as_bytes()) {
+                Ok(info) => (**info).function,
+                Err(_) => *library
+                    .get::<LimNative>(symbol.as_bytes())
+                    .map_err(|error| format!("Cannot find {} in native library {}: {}", symbol, path, error))?,
+            }
+        };
+        self.functions.insert(key, function);
+        Ok(function)
+    }
+}
+
+fn check_version(path: &str, library: &Library) -> Result<(), String> {
+    let version = unsafe {
+        let version = library
+            .get::<LimAbiVersion>(b"lim_abi_version")
+            .map_err(|_| format!("Cannot load native library {}: it does not export lim_abi_version", path))?;
+        version()
+    };
+    supports(path, version)
+}
+
+// Natives only see values through the ABI, a library built against another version of it cannot be
+// called safely.
+pub fn supports(path: &str, version: u32) -> Result<(), String> {
+    if version != LIM_ABI_VERSION {
+        return Err(format!("Cannot load native library {}: it was built for ABI version {}, lim supports {}", path, version, LIM_ABI_VERSION));
+    }
+    Ok(())
+}
+
+// Failures are not cached, a library missing now may be built before the next call.
+pub fn call(path: &str, symbol: &str, args: &[Rc<Object>]) -> Rc<Object> {
+    let function = REGISTRY.with(|registry| registry.borrow_mut().function(path, symbol));
+    match function {
+        Ok(function) => extension::call(function, args),
+        Err(message) => Rc::new(Object::Error(message)),
+    }
+}
+
+fn name(info: &LimNativeInfo) -> &CStr {
+    unsafe { CStr::from_ptr(info.name) }
+}
+
+// The standard natives are linked into the binary, libraries are only loaded for extensions.
+pub fn linked() -> Vec<String> {
+    lim_std::NATIVES.iter().map(|info| name(info).to_string_lossy().into_owned()).collect()
+}
+
+pub fn call_linked(symbol: &str, args: &[Rc<Object>]) -> Rc<Object> {
+    match lim_std::NATIVES.iter().find(|info| name(info).to_bytes() == symbol.as_bytes()) {
+        Some(info) => extension::call(info.function, args),
+        None => Rc::new(Object::Error(format!("Unknown native {}", symbol))),
+    }
+}
+
+// Closes every library opened so far, called once the program is done with them.
+pub fn unload() {
+    REGISTRY.with(|registry| {
+        let mut registry = registry.borrow_mut();
+        registry.functions.clear();
+        registry.libraries.clear();
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::{call, call_linked, linked, supports};
+    use crate::testing::run;
+    use lim_abi::LIM_ABI_VERSION;
+    use object::Object;
+
+    #[test]
+    fn registry_reports_libraries_it_cannot_load() {
+        let missing = env::temp_dir().join("lim-tests/missing.so").display().to_string();
+        for _ in 0..2 {
+            match &*call(&missing, "sum", &[]) {
+                Object::Error(message) => assert!(message.starts_with(&format!("Cannot load native library {}: ", missing)), "{}", message),
+                value => panic!("loaded {}", value.to_string()),
+            }
+        }
+        assert_eq!(run(&format!("native('{}', 'sum')(1)", missing)).split(':').next(), Some(&*format!("Error(\"Cannot load native library {}", missing)));
+        assert_eq!(run("native(1, 'sum')"), "Error(\"native expects a library path and a symbol name\")");
+        assert!(linked().contains(&String::from("print")));
+        assert_eq!(call_linked("missing", &[]).to_string(), "Error(\"Unknown native missing\")");
+    }
+
+    #[test]
+    fn registry_checks_the_abi_version() {
+        assert_eq!(supports("lib.so", LIM_ABI_VERSION), Ok(()));
+        let expected = format!("Cannot load native library lib.so: it was built for ABI version {}, lim supports {}", LIM_ABI_VERSION + 1, LIM_ABI_VERSION);
+        assert_eq!(supports("lib.so", LIM_ABI_VERSION + 1), Err(expected));
+        // Any library that is not an extension, like the C math library.
+        if cfg!(target_os = "linux") {
+            assert_eq!(call("libm.so.6", "cos", &[]).to_string(), "Error(\"Cannot load native library libm.so.6: it does not export lim_abi_version\")");
+        }
+    }
+}
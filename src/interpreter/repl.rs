@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+use std::env;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use rustyline::error::ReadlineError;
+use rustyline::Editor;
+
+use crate::module;
+use lexer::lexer::{is_unterminated, lex};
+use object::Object;
+use parser::node::Node;
+use parser::parser::{is_incomplete, parse};
+
+const HELP: &str = ":ast [<code>]     Show the syntax tree of the code, or of the last input
+:tokens [<code>]  Show the tokens of the code, or of the last input
+:help             Show this message
+:quit             Leave, Ctrl-D works too";
+
+fn history_file() -> Option<PathBuf> {
+    env::var_os("HOME").map(|home| PathBuf::from(home).join(".lim_history"))
+}
+
+// Lines are read until the code is a complete program, an unterminated string or an open block
+// continues on the next line.
+pub fn needs_more(code: &str) -> bool {
+    if is_unterminated(code) {
+        return true;
+    }
+    match lex(code) {
+        Some(tokens) => is_incomplete(tokens),
+        None => false,
+    }
+}
+
+fn meta(command: &str, argument: &str, last: &str) {
+    let code = if argument.is_empty() { last } else { argument };
+    match command {
+        ":tokens" => match lex(code) {
+            Some(tokens) => println!("{:?}", tokens),
+            None => println!("error: Syntax error"),
+        },
+        ":ast" => match lex(code).and_then(parse) {
+            Some(ast) => println!("{:?}", ast),
+            None => println!("error: Syntax error"),
+        },
+        ":help" => println!("{}", HELP),
+        _ => println!("Unknown command {}, try :help", command),
+    }
+}
+
+// Assignments are silent, any other statement shows its value unless it is None.
+pub fn shown(code: &str, value: &Rc<Object>) -> Option<String> {
+    let assigns = match lex(code).and_then(parse) {
+        Some(Node::Program(statements)) => matches!(statements.iter().rfind(|statement| **statement != Node::Empty), Some(Node::Assignment(_, _)) | Some(Node::CompoundAssignment(_, _, _))),
+        _ => false,
+    };
+    match &**value {
+        Object::Error(message) => Some(format!("error: {}", message)),
+        Object::None => None,
+        _ if assigns => None,
+        _ => Some(value.to_string()),
+    }
+}
+
+pub fn run(mut scope: HashMap<String, Rc<Object>>) {
+    let mut editor = Editor::<()>::new();
+    let history = history_file();
+    if let Some(history) = &history {
+        let _ = editor.load_history(history);
+    }
+    let path = env::current_dir().unwrap_or_default().join("<repl>");
+    let mut buffer = String::new();
+    let mut last = String::new();
+    loop {
+        let prompt = if buffer.is_empty() { "> " } else { "... " };
+        match editor.readline(prompt) {
+            Ok(line) => {
+                if buffer.is_empty() && line.trim_start().starts_with(':') {
+                    editor.add_history_entry(line.as_str());
+                    let line = line.trim();
+                    let (command, argument) = line.split_at(line.find(' ').unwrap_or(line.len()));
+                    if command == ":quit" || command == ":q" {
+                        break;
+                    }
+                    meta(command, argument.trim(), &last);
+                    continue;
+                }
+                buffer.push_str(&line);
+                buffer.push('\n');
+                if needs_more(&buffer) {
+                    continue;
+                }
+                editor.add_history_entry(buffer.trim_end());
+                let value = module::evaluate(&path, &buffer, &mut scope);
+                if let Some(shown) = shown(&buffer, &value) {
+                    println!("{}", shown);
+                }
+                last = std::mem::take(&mut buffer);
+            }
+            Err(ReadlineError::Interrupted) => buffer.clear(),
+            Err(ReadlineError::Eof) => break,
+            Err(error) => {
+                eprintln!("error: {}", error);
+                break;
+            }
+        }
+    }
+    if let Some(history) = &history {
+        let _ = editor.save_history(history);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::path::Path;
+
+    use super::{needs_more, shown};
+    use crate::module;
+    use crate::testing::run;
+
+    #[test]
+    fn evaluate_rejects_incomplete_input() {
+        assert_eq!(run("f = (x) {"), "Error(\"Unexpected end of input in <test>\")");
+        assert_eq!(run("[1, 2"), "Error(\"Unexpected end of input in <test>\")");
+    }
+
+    // Feeds lines to the REPL the way its loop does and returns what it shows for every input.
+    fn session_of(lines: &[&str]) -> Vec<Option<String>> {
+        module::reset();
+        let (mut scope, mut buffer, mut outputs) = (HashMap::new(), String::new(), vec![]);
+        for line in lines {
+            buffer.push_str(line);
+            buffer.push('\n');
+            if needs_more(&buffer) {
+                continue;
+            }
+            let value = module::evaluate(Path::new("<repl>"), &buffer, &mut scope);
+            outputs.push(shown(&buffer, &value));
+            buffer.clear();
+        }
+        assert!(buffer.is_empty(), "unfinished input: {}", buffer);
+        outputs
+    }
+
+    #[test]
+    fn repl_continues_open_input() {
+        assert!(needs_more("f = (x) {\n"));
+        assert!(needs_more("[1,\n"));
+        assert!(needs_more("s = '''first\n"));
+        assert!(needs_more("class Point {\n  x = 0\n"));
+        assert!(!needs_more("x = 1\n"));
+        assert!(!needs_more("f = (x) {\n  x\n}\n"));
+        assert!(!needs_more("x = )\n"));
+    }
+
+    #[test]
+    fn repl_shows_values_but_not_assignments() {
+        let shown = session_of(&["double = (x) {", "  x * 2", "}", "[double(4),", "  5]", "total = 1", "total += 2", "total", "1 / 0", "None"]);
+        let expected = [None, Some("[8, 5]"), None, None, Some("3"), Some("error: Division by zero"), None];
+        assert_eq!(shown, expected.iter().map(|shown| shown.map(String::from)).collect::<Vec<Option<String>>>());
+    }
+}
@@ -0,0 +1,239 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::interpreter::parameter_name;
+use parser::node::Node;
+
+// Identifiers are interned once per thread, compiled code refers to them by index.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Symbol(usize);
+
+#[derive(Default)]
+struct Interner {
+    names: Vec<Rc<str>>,
+    symbols: HashMap<Rc<str>, Symbol>,
+}
+
+thread_local! {
+    static INTERNER: RefCell<Interner> = RefCell::new(Interner::default());
+}
+
+pub fn intern(name: &str) -> Symbol {
+    INTERNER.with(|interner| {
+        let mut interner = interner.borrow_mut();
+        if let Some(symbol) = interner.symbols.get(name) {
+            return *symbol;
+        }
+        let symbol = Symbol(interner.names.len());
+        let name: Rc<str> = Rc::from(name);
+        interner.names.push(name.to_owned());
+        interner.symbols.insert(name, symbol);
+        symbol
+    })
+}
+
+impl Symbol {
+    pub fn name(self) -> Rc<str> {
+        INTERNER.with(|interner| interner.borrow().names[self.0].to_owned())
+    }
+}
+
+// Lim has no closures, a function only sees `self`, its parameters and its own variables and a class
+// body only its own bindings. Every variable therefore lives at depth 0, in a slot of the frame using
+// it, and the names a frame never binds can only be natives.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Resolved {
+    Local(usize),
+    Global(Symbol),
+}
+
+// The slots of a module, class body or function. Module frames are open: the scope they run in may
+// already bind any name, so every name they use gets a slot.
+#[derive(Debug, Default)]
+pub struct Frame {
+    pub slots: Vec<Option<Symbol>>,
+    indices: HashMap<Symbol, usize>,
+    open: bool,
+}
+
+impl Frame {
+    pub fn declare(&mut self, name: Symbol) -> usize {
+        if let Some(slot) = self.indices.get(&name) {
+            return *slot;
+        }
+        self.slots.push(Some(name));
+        self.indices.insert(name, self.slots.len() - 1);
+        self.slots.len() - 1
+    }
+
+    pub fn temporary(&mut self) -> usize {
+        self.slots.push(None);
+        self.slots.len() - 1
+    }
+
+    pub fn resolve(&mut self, name: Symbol) -> Resolved {
+        match self.indices.get(&name) {
+            Some(slot) => Resolved::Local(*slot),
+            None if self.open => Resolved::Local(self.declare(name)),
+            None => Resolved::Global(name),
+        }
+    }
+
+    // Names bound by a pattern, members and indexes assign into existing values instead.
+    fn targets(&mut self, pattern: &Node) {
+        match pattern {
+            Node::Identifier(name) => {
+                self.declare(intern(name));
+            }
+            Node::Parenthesized(pattern) | Node::Variadic(pattern) => self.targets(pattern),
+            Node::Tuple(elements) | Node::Array(elements) => elements.iter().for_each(|element| self.targets(element)),
+            Node::Call(variant, elements) => {
+                self.declarations(variant);
+                elements.iter().for_each(|element| self.targets(element));
+            }
+            Node::Map(entries) => {
+                for entry in entries.iter() {
+                    if let Node::KeyValue(key, pattern) = entry {
+                        self.declarations(key);
+                        self.targets(pattern);
+                    }
+                }
+            }
+            pattern => self.declarations(pattern),
+        }
+    }
+
+    // Declares every name the code of this frame binds, function bodies and class bodies are frames
+    // of their own.
+    fn declarations(&mut self, node: &Node) {
+        match node {
+            Node::Assignment(target, value) => {
+                self.declarations(value);
+                self.targets(target);
+            }
+            Node::CompoundAssignment(target, _, value) => {
+                self.declarations(value);
+                match &**target {
+                    Node::Identifier(name) => {
+                        self.declare(intern(name));
+                    }
+                    target => self.declarations(target),
+                }
+            }
+            Node::EnumDefinition(name, _) | Node::ClassDefinition(name, _) => {
+                if let Node::Identifier(name) = &**name {
+                    self.declare(intern(name));
+                }
+            }
+            Node::MatchArm(pattern, body) => {
+                match &**pattern {
+                    Node::Identifier(_) => {}
+                    pattern => self.targets(pattern),
+                }
+                self.declarations(body);
+            }
+            Node::Call(callee, args) => {
+                self.declarations(callee);
+                for arg in args.iter() {
+                    match arg {
+                        Node::Assignment(name, value) if matches!(**name, Node::Identifier(_)) => self.declarations(value),
+                        arg => self.declarations(arg),
+                    }
+                }
+            }
+            Node::Addition(left, right)
+            | Node::Substraction(left, right)
+            | Node::Multiplication(left, right)
+            | Node::Division(left, right)
+            | Node::Modulus(left, right)
+            | Node::Index(left, right)
+            | Node::Slice(left, right)
+            | Node::KeyValue(left, right)
+            | Node::Prefixed(left, right) => {
+                self.declarations(left);
+                self.declarations(right);
+            }
+            Node::Match(subject, arms) => {
+                self.declarations(subject);
+                arms.iter().for_each(|arm| self.declarations(arm));
+            }
+            Node::Program(nodes) | Node::Array(nodes) | Node::Tuple(nodes) | Node::Map(nodes) | Node::Set(nodes) | Node::FunctionArgs(nodes) | Node::Template(nodes) => {
+                nodes.iter().for_each(|node| self.declarations(node))
+            }
+            Node::Member(node, _) | Node::Negation(node) | Node::Variadic(node) | Node::Parenthesized(node) => self.declarations(node),
+            _ => {}
+        }
+    }
+}
+
+pub fn resolve_module(statements: &[Node]) -> Frame {
+    let mut frame = Frame { open: true, ..Frame::default() };
+    statements.iter().for_each(|statement| frame.declarations(statement));
+    frame
+}
+
+// `self` is slot 0 and the parameters follow it in order.
+pub fn resolve_function(parameters: &[Node], body: &[Node]) -> Frame {
+    let mut frame = Frame::default();
+    frame.declare(intern("self"));
+    for parameter in parameters.iter() {
+        if let Some(name) = parameter_name(parameter) {
+            frame.declare(intern(name));
+        }
+    }
+    for parameter in parameters.iter() {
+        if let Node::Assignment(_, default) = parameter {
+            frame.declarations(default);
+        }
+    }
+    body.iter().for_each(|statement| frame.declarations(statement));
+    frame
+}
+
+pub fn resolve_class(body: &Node) -> Frame {
+    let mut frame = Frame::default();
+    frame.declare(intern("self"));
+    frame.declarations(body);
+    frame
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{intern, resolve_class, resolve_function, resolve_module, Frame, Resolved};
+    use crate::testing::statements;
+    use parser::node::Node;
+
+    fn slots(frame: &Frame) -> Vec<String> {
+        frame.slots.iter().map(|slot| slot.map_or(String::from("_"), |name| name.name().to_string())).collect()
+    }
+
+    #[test]
+    fn resolver_gives_functions_a_closed_frame() {
+        let function = match &statements("f = (a, b = c, rest...) {\n  [d, e] = a\n  d += print(b)\n  g = (h) { h }\n}")[0] {
+            Node::Assignment(_, function) => *function.to_owned(),
+            _ => unreachable!(),
+        };
+        let (parameters, body) = match function {
+            Node::FunctionDefinition(parameters, body) => (parameters, body),
+            _ => unreachable!(),
+        };
+        let mut frame = resolve_function(&parameters, &body);
+        assert_eq!(slots(&frame), ["self", "a", "b", "rest", "d", "e", "g"]);
+        assert_eq!(frame.resolve(intern("e")), Resolved::Local(5));
+        assert_eq!(frame.resolve(intern("print")), Resolved::Global(intern("print")));
+        assert_eq!(frame.resolve(intern("h")), Resolved::Global(intern("h")));
+        assert_eq!(frame.temporary(), 7);
+        assert_eq!(slots(&frame), ["self", "a", "b", "rest", "d", "e", "g", "_"]);
+    }
+
+    #[test]
+    fn resolver_gives_modules_an_open_frame() {
+        let mut frame = resolve_module(&statements("class Point {\n  x = 0\n}\nenum Color {\n  Red\n}\nColor.Red: {\n  [y]: { z = y }\n}\nf(w = 1)"));
+        assert_eq!(slots(&frame), ["Point", "Color", "y", "z"]);
+        assert_eq!(frame.resolve(intern("print")), Resolved::Local(4));
+        assert_eq!(frame.resolve(intern("print")), Resolved::Local(4));
+        let class = resolve_class(&Node::Program(statements("x = 0\nmove = (dx) { self.x += dx }")));
+        assert_eq!(slots(&class), ["self", "x", "move"]);
+    }
+}
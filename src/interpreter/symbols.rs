@@ -0,0 +1,320 @@
+use std::collections::HashMap;
+
+use crate::lint;
+use lexer::span::{Position, Span};
+use lexer::token::Token;
+use parser::format::format_node;
+use parser::node::Node;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    Variable,
+    Function,
+    Parameter,
+    Class,
+    Enum,
+    Variant,
+    Field,
+    Method,
+}
+
+// `member` symbols, like enum variants, are only reachable through their container.
+#[derive(Debug)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: Kind,
+    pub span: Span,
+    pub range: Span,
+    pub scope: usize,
+    pub container: Option<String>,
+    pub member: bool,
+    pub definition: Node,
+}
+
+#[derive(Debug)]
+pub struct Occurrence {
+    pub name: String,
+    pub span: Span,
+    pub scope: usize,
+    pub member_of: Option<Node>,
+}
+
+// `class` is the class `self` refers to in a scope, `body_of` is set on the scope of a class body.
+struct Scope {
+    parent: Option<usize>,
+    class: Option<String>,
+    body_of: Option<String>,
+}
+
+#[derive(Default)]
+pub struct Analysis {
+    pub symbols: Vec<Symbol>,
+    pub occurrences: Vec<Occurrence>,
+    scopes: Vec<Scope>,
+}
+
+fn program(node: &Node) -> &[Node] {
+    match node {
+        Node::Program(statements) => statements,
+        _ => std::slice::from_ref(node),
+    }
+}
+
+struct Builder {
+    analysis: Analysis,
+    spans: HashMap<*const Node, Span>,
+    statement: Span,
+}
+
+impl Builder {
+    fn scope(&mut self, parent: usize, class: Option<String>, body_of: Option<String>) -> usize {
+        self.analysis.scopes.push(Scope { parent: Some(parent), class, body_of });
+        self.analysis.scopes.len() - 1
+    }
+
+    fn occurrence(&mut self, node: &Node, name: &str, scope: usize, member_of: Option<Node>) {
+        if let Some(span) = self.spans.get(&(node as *const Node)) {
+            self.analysis.occurrences.push(Occurrence { name: name.to_owned(), span: *span, scope, member_of });
+        }
+    }
+
+    // The symbol is returned for the callers defining a field, a method or a variant to set its container.
+    fn define(&mut self, node: &Node, name: &str, kind: Kind, scope: usize, definition: &Node) -> Option<&mut Symbol> {
+        let span = self.spans.get(&(node as *const Node)).cloned()?;
+        let range = if scope == 0 { self.statement } else { span };
+        self.analysis.symbols.push(Symbol { name: name.to_owned(), kind, span, range, scope, container: None, member: false, definition: definition.to_owned() });
+        self.analysis.symbols.last_mut()
+    }
+
+    // Names assigned in a class body are its fields and methods, elsewhere they are variables and functions.
+    fn targets(&mut self, target: &Node, value: &Node, scope: usize, assignment: &Node) {
+        let body_of = self.analysis.scopes[scope].body_of.to_owned();
+        let function = matches!(value, Node::FunctionDefinition(_, _));
+        let kind = match (&body_of, function) {
+            (Some(_), true) => Kind::Method,
+            (Some(_), false) => Kind::Field,
+            (None, true) => Kind::Function,
+            (None, false) => Kind::Variable,
+        };
+        match target {
+            Node::Identifier(name) => {
+                if let Some(symbol) = self.define(target, name, kind, scope, assignment) {
+                    symbol.container = body_of;
+                }
+                self.occurrence(target, name, scope, None);
+            }
+            Node::Member(object, name) if body_of.is_some() && matches!(&**object, Node::Identifier(name) if name == "self") => {
+                if let Some(symbol) = self.define(target, name, kind, scope, assignment) {
+                    symbol.container = body_of;
+                    symbol.member = true;
+                }
+                self.walk(target, scope);
+            }
+            Node::Parenthesized(target) | Node::Variadic(target) => self.targets(target, &Node::Empty, scope, assignment),
+            Node::Tuple(targets) | Node::Array(targets) => targets.iter().for_each(|target| self.targets(target, &Node::Empty, scope, assignment)),
+            Node::Call(variant, targets) => {
+                self.walk(variant, scope);
+                targets.iter().for_each(|target| self.targets(target, &Node::Empty, scope, assignment));
+            }
+            Node::Map(entries) => {
+                for entry in entries.iter() {
+                    if let Node::KeyValue(key, target) = entry {
+                        self.walk(key, scope);
+                        self.targets(target, &Node::Empty, scope, assignment);
+                    }
+                }
+            }
+            target => self.walk(target, scope),
+        }
+    }
+
+    fn function(&mut self, parameters: &[Node], body: &[Node], scope: usize) {
+        let class = self.analysis.scopes[scope].body_of.to_owned();
+        let scope = self.scope(scope, class, None);
+        for parameter in parameters.iter() {
+            let name: &Node = match parameter {
+                Node::Assignment(name, _) | Node::Variadic(name) => name,
+                name => name,
+            };
+            if let Node::Identifier(identifier) = name {
+                self.define(name, identifier, Kind::Parameter, scope, parameter);
+                self.occurrence(name, identifier, scope, None);
+            }
+            if let Node::Assignment(_, default) = parameter {
+                self.walk(default, scope);
+            }
+        }
+        body.iter().for_each(|statement| self.walk(statement, scope));
+    }
+
+    fn walk(&mut self, node: &Node, scope: usize) {
+        match node {
+            Node::Identifier(name) => self.occurrence(node, name, scope, None),
+            Node::Member(object, name) => {
+                self.walk(object, scope);
+                self.occurrence(node, name, scope, Some(*object.to_owned()));
+            }
+            Node::Assignment(target, value) => {
+                self.walk(value, scope);
+                self.targets(target, value, scope, node);
+            }
+            Node::FunctionDefinition(parameters, body) => self.function(parameters, body, scope),
+            Node::ClassDefinition(name, body) => {
+                if let Node::Identifier(class) = &**name {
+                    self.define(name, class, Kind::Class, scope, node);
+                    self.occurrence(name, class, scope, None);
+                    let body_scope = self.scope(scope, Some(class.to_owned()), Some(class.to_owned()));
+                    program(body).iter().for_each(|statement| self.walk(statement, body_scope));
+                }
+            }
+            Node::EnumDefinition(name, variants) => {
+                if let Node::Identifier(enumeration) = &**name {
+                    self.define(name, enumeration, Kind::Enum, scope, node);
+                    self.occurrence(name, enumeration, scope, None);
+                    for variant in variants.iter() {
+                        if let Node::Identifier(variant_name) = variant {
+                            if let Some(symbol) = self.define(variant, variant_name, Kind::Variant, scope, node) {
+                                symbol.container = Some(enumeration.to_owned());
+                                symbol.member = true;
+                            }
+                        }
+                    }
+                }
+            }
+            Node::CompoundAssignment(left, _, right)
+            | Node::Addition(left, right)
+            | Node::Substraction(left, right)
+            | Node::Multiplication(left, right)
+            | Node::Division(left, right)
+            | Node::Modulus(left, right)
+            | Node::Index(left, right)
+            | Node::Slice(left, right)
+            | Node::KeyValue(left, right)
+            | Node::MatchArm(left, right)
+            | Node::Prefixed(left, right) => {
+                self.walk(left, scope);
+                self.walk(right, scope);
+            }
+            Node::Call(first, rest) | Node::Match(first, rest) => {
+                self.walk(first, scope);
+                rest.iter().for_each(|node| self.walk(node, scope));
+            }
+            Node::Program(nodes) | Node::Array(nodes) | Node::Tuple(nodes) | Node::Map(nodes) | Node::Set(nodes) | Node::FunctionArgs(nodes) | Node::Template(nodes) => {
+                nodes.iter().for_each(|node| self.walk(node, scope))
+            }
+            Node::Negation(node) | Node::Variadic(node) | Node::Parenthesized(node) => self.walk(node, scope),
+            _ => {}
+        }
+    }
+}
+
+pub fn analyze(statements: &[(Node, Span)], tokens: &[(Token, Span)]) -> Analysis {
+    let mut builder = Builder { analysis: Analysis::default(), spans: HashMap::new(), statement: statements.first().map(|(_statement, span)| *span).unwrap_or(Span { start: Position::start(), end: Position::start() }) };
+    builder.analysis.scopes.push(Scope { parent: None, class: None, body_of: None });
+    for (statement, span) in statements.iter() {
+        builder.spans = lint::spans(statement, span, tokens);
+        builder.statement = *span;
+        builder.walk(statement, 0);
+    }
+    builder.analysis
+}
+
+impl Analysis {
+    fn parents(&self, scope: usize) -> Vec<usize> {
+        let mut scopes = vec![scope];
+        while let Some(parent) = self.scopes.get(*scopes.last().unwrap()).and_then(|scope| scope.parent) {
+            scopes.push(parent);
+        }
+        scopes
+    }
+
+    // The occurrence under the cursor, a cursor right after an identifier still points at it.
+    pub fn at(&self, offset: usize) -> Option<&Occurrence> {
+        self.occurrences.iter().find(|occurrence| occurrence.span.start.offset <= offset && offset <= occurrence.span.end.offset)
+    }
+
+    // The innermost scope around an offset, taken from the closest occurrence before it.
+    pub fn scope_at(&self, offset: usize) -> usize {
+        self.occurrences.iter().rfind(|occurrence| occurrence.span.start.offset <= offset).map_or(0, |occurrence| occurrence.scope)
+    }
+
+    // Searches the scope and then its parents, in a scope the last definition before the offset wins.
+    pub fn lookup(&self, name: &str, scope: usize, offset: usize) -> Option<&Symbol> {
+        for scope in self.parents(scope) {
+            let candidates: Vec<&Symbol> = self.symbols.iter().filter(|symbol| symbol.scope == scope && symbol.name == name && !symbol.member).collect();
+            if let Some(symbol) = candidates.iter().rfind(|symbol| symbol.span.start.offset <= offset).or_else(|| candidates.first()) {
+                return Some(symbol);
+            }
+        }
+        None
+    }
+
+    pub fn visible(&self, scope: usize) -> Vec<&Symbol> {
+        let scopes = self.parents(scope);
+        let mut visible: Vec<&Symbol> = vec![];
+        for symbol in self.symbols.iter().filter(|symbol| scopes.contains(&symbol.scope) && !symbol.member) {
+            if !visible.iter().any(|other| other.name == symbol.name) {
+                visible.push(symbol);
+            }
+        }
+        visible
+    }
+
+    // The class or enum an expression refers to: `self`, a class or enum name, or a variable holding `Class()`.
+    fn container(&self, object: &Node, scope: usize, offset: usize) -> Option<String> {
+        match object {
+            Node::Identifier(name) if name == "self" => self.parents(scope).iter().find_map(|scope| self.scopes[*scope].class.to_owned()),
+            Node::Identifier(name) => {
+                let symbol = self.lookup(name, scope, offset)?;
+                match (&symbol.kind, &symbol.definition) {
+                    (Kind::Class, _) | (Kind::Enum, _) => Some(symbol.name.to_owned()),
+                    (_, Node::Assignment(_, value)) => match &**value {
+                        Node::Call(callee, _) => self.container(callee, scope, offset),
+                        _ => None,
+                    },
+                    _ => None,
+                }
+            }
+            Node::Call(callee, _) | Node::Parenthesized(callee) => self.container(callee, scope, offset),
+            _ => None,
+        }
+    }
+
+    // Members of the container of `object`, or every declared member when it is not known.
+    pub fn members(&self, object: &Node, scope: usize, offset: usize) -> Vec<&Symbol> {
+        let container = self.container(object, scope, offset);
+        let mut members: Vec<&Symbol> = vec![];
+        for symbol in self.symbols.iter().filter(|symbol| symbol.container.is_some()) {
+            let matches = match &container {
+                Some(container) => symbol.container.as_ref() == Some(container),
+                None => true,
+            };
+            if matches && !members.iter().any(|other| other.name == symbol.name && other.container == symbol.container) {
+                members.push(symbol);
+            }
+        }
+        members
+    }
+
+    pub fn definitions(&self, occurrence: &Occurrence) -> Vec<&Symbol> {
+        let offset = occurrence.span.start.offset;
+        match &occurrence.member_of {
+            Some(object) => self.members(object, occurrence.scope, offset).into_iter().filter(|symbol| symbol.name == occurrence.name).collect(),
+            None => self.lookup(&occurrence.name, occurrence.scope, offset).into_iter().collect(),
+        }
+    }
+
+    // A short description of a symbol for hovers, its defining expression where it has one.
+    pub fn describe(&self, symbol: &Symbol) -> String {
+        let formatted = |node: &Node| format_node(node).map(|text| String::from(text.trim_end())).unwrap_or_default();
+        match symbol.kind {
+            Kind::Class => {
+                let fields: Vec<&str> = self.symbols.iter().filter(|member| member.container.as_ref() == Some(&symbol.name) && member.kind != Kind::Variant).map(|member| member.name.as_str()).collect();
+                format!("class {} {{ {} }}", symbol.name, fields.join(", "))
+            }
+            Kind::Variant => format!("{}.{}", symbol.container.to_owned().unwrap_or_default(), symbol.name),
+            Kind::Parameter => format!("parameter {}", formatted(&symbol.definition)),
+            _ => formatted(&symbol.definition),
+        }
+    }
+}
@@ -0,0 +1,52 @@
+// Helpers shared by the tests of the modules.
+
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::module::{self, Engine};
+use lexer::lexer::lex;
+use parser::node::Node;
+use parser::parser::parse;
+
+pub const ENGINES: [Engine; 2] = [Engine::Tree, Engine::Vm];
+
+// Every program runs on both engines, which must agree, the result is shown the way `print` would.
+pub fn run(code: &str) -> String {
+    run_at(Path::new("<test>"), code)
+}
+
+// Relative imports of the program resolve next to `path`.
+pub fn run_at(path: &Path, code: &str) -> String {
+    let results: Vec<String> = ENGINES
+        .iter()
+        .map(|engine| {
+            module::set_engine(*engine);
+            module::reset();
+            module::evaluate(path, code, &mut HashMap::new()).to_string()
+        })
+        .collect();
+    assert_eq!(results[0], results[1], "the engines disagree on:\n{}", code);
+    results[1].to_owned()
+}
+
+// Writes the files of a test to a fresh directory, named after the test, and returns its path.
+pub fn fixture(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let directory = env::temp_dir().join("lim-tests").join(name);
+    let _ = fs::remove_dir_all(&directory);
+    for (path, content) in files {
+        let path = directory.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+    fs::create_dir_all(&directory).unwrap();
+    directory.canonicalize().unwrap()
+}
+
+pub fn statements(code: &str) -> Vec<Node> {
+    match lex(code).and_then(parse) {
+        Some(Node::Program(statements)) => statements,
+        _ => panic!("cannot parse {}", code),
+    }
+}
@@ -0,0 +1,28 @@
+use std::process::{Command, Output};
+
+// Runs `lim -e` on both engines, which must agree, and returns the exit code, stdout and stderr.
+fn eval(code: &str) -> (Option<i32>, String, String) {
+    let outputs: Vec<(Option<i32>, String, String)> = ["tree", "vm"]
+        .iter()
+        .map(|engine| {
+            let Output { status, stdout, stderr } = Command::new(env!("CARGO_BIN_EXE_lim")).args(["-e", code]).env("LIM_ENGINE", engine).output().unwrap();
+            (status.code(), String::from_utf8(stdout).unwrap(), String::from_utf8(stderr).unwrap())
+        })
+        .collect();
+    assert_eq!(outputs[0], outputs[1], "the engines disagree on:\n{}", code);
+    outputs[1].to_owned()
+}
+
+#[test]
+fn uncaught_errors_exit_with_their_message() {
+    assert_eq!(eval("print('before')\n1 / 0\nprint('after')"), (Some(1), String::from("before\n"), String::from("error: Division by zero\n")));
+    assert_eq!(eval("x = 1 / 0\nprint('after')"), (Some(0), String::from("after\n"), String::new()));
+}
+
+// Binding an error to a name goes on, failing to assign does not.
+#[test]
+fn failed_assignments_are_uncaught() {
+    assert_eq!(eval("[a, b] = [1]"), (Some(1), String::new(), String::from("error: Cannot destructure 1 values into 2 elements\n")));
+    assert_eq!(eval("a = [1]\na[5] = 2\nprint('after')"), (Some(1), String::new(), String::from("error: Index 5 out of range for array of length 1\n")));
+    assert_eq!(eval("a = 1\na.b = 2\nprint('after')"), (Some(1), String::new(), String::from("error: Cannot set b on 1\n")));
+}
@@ -0,0 +1,525 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::{Rc, Weak};
+
+use crate::compiler::{compile_function, compile_module, Chunk, Op};
+use crate::interpreter::{call_named, call_prefix, class, compound, get, get_index, get_slice, is_prefix, native, negate, operate, parameter_name, set_index, set_member};
+use crate::map;
+use crate::pattern;
+use object::{Object, Table};
+use parser::node::Node;
+
+// A function object with its compiled body. The weak reference keeps the address of the object from being
+// reused without keeping the function alive, the entries of dropped functions are swept from time to time.
+type Compiled = (Weak<Object>, Rc<Chunk>);
+
+// The cache is swept when it has grown to twice its size after the previous sweep, and at least to this.
+const SWEEP_AT: usize = 64;
+
+thread_local! {
+    static FUNCTIONS: RefCell<HashMap<*const Object, Compiled>> = RefCell::new(HashMap::new());
+    static SWEPT: Cell<usize> = const { Cell::new(0) };
+}
+
+enum Target {
+    Slot(usize),
+    Member(Rc<Object>, String),
+    Index(Rc<Object>, Rc<Object>),
+}
+
+fn error(message: String) -> Rc<Object> {
+    Rc::new(Object::Error(message))
+}
+
+// `marks` remember the stack height and pending targets when a pattern starts, a pattern that does not
+// fit goes back to them.
+struct Frame<'a> {
+    chunk: &'a Chunk,
+    locals: &'a mut Vec<Option<Rc<Object>>>,
+    stack: Vec<Rc<Object>>,
+    marks: Vec<(usize, usize)>,
+    pending: Vec<(Target, Rc<Object>)>,
+    error: Rc<Object>,
+    pc: usize,
+}
+
+impl<'a> Frame<'a> {
+    fn pop(&mut self) -> Rc<Object> {
+        self.stack.pop().unwrap()
+    }
+
+    fn top(&mut self) -> &mut Object {
+        let top = self.stack.last_mut().unwrap();
+        unsafe { Rc::get_mut_unchecked(top) }
+    }
+
+    fn fail(&mut self, error: Rc<Object>, to: usize) {
+        self.error = error;
+        self.pc = to;
+    }
+
+    // Replaces the `count` values of an expression with the error it evaluates to.
+    fn abort(&mut self, count: usize, error: Rc<Object>, to: usize) {
+        self.stack.truncate(self.stack.len() - count);
+        self.stack.push(error);
+        self.pc = to;
+    }
+
+    fn load(&self, slot: usize) -> Rc<Object> {
+        match &self.locals[slot] {
+            Some(value) => value.to_owned(),
+            None => self.chunk.slots[slot].and_then(|name| native(&name.name())).unwrap_or_else(|| Rc::new(Object::None)),
+        }
+    }
+
+    fn assign(&mut self, targets: Vec<(Target, Rc<Object>)>) -> Rc<Object> {
+        for (target, value) in targets {
+            let result = match target {
+                Target::Slot(slot) => {
+                    self.locals[slot] = Some(value);
+                    continue;
+                }
+                Target::Member(instance, name) => set_member(instance, name, value),
+                Target::Index(target, index) => set_index(target, &index, value),
+            };
+            if let Object::Error(_) = *result {
+                return result;
+            }
+        }
+        Rc::new(Object::None)
+    }
+
+    fn run(&mut self) -> Rc<Object> {
+        loop {
+            let op = self.chunk.code[self.pc];
+            self.pc += 1;
+            match op {
+                Op::Constant(index) => self.stack.push(self.chunk.constants[index].to_owned()),
+                Op::None => self.stack.push(Rc::new(Object::None)),
+                Op::Load(slot) => {
+                    let value = self.load(slot);
+                    self.stack.push(value);
+                }
+                Op::Global(name) => self.stack.push(native(&name.name()).unwrap_or_else(|| Rc::new(Object::None))),
+                Op::Store(slot) => self.locals[slot] = Some(self.pop()),
+                Op::Pop => {
+                    self.pop();
+                }
+                Op::Dup => self.stack.push(self.stack.last().unwrap().to_owned()),
+                Op::Get(name) => {
+                    let object = self.pop();
+                    self.stack.push(get(&object, name.name().to_string()));
+                }
+                Op::SetMember(name) => {
+                    let value = self.pop();
+                    let instance = self.pop();
+                    self.stack.push(set_member(instance, name.name().to_string(), value));
+                }
+                Op::Operate(operator) => {
+                    let rhs = self.pop();
+                    let lhs = self.pop();
+                    self.stack.push(operate(operator, lhs, rhs));
+                }
+                Op::Compound(operator) => {
+                    let rhs = self.pop();
+                    let lhs = self.pop();
+                    self.stack.push(compound(operator, lhs, rhs));
+                }
+                Op::Negate => {
+                    let value = self.pop();
+                    self.stack.push(negate(value));
+                }
+                Op::Array(count) => {
+                    let elements = self.stack.split_off(self.stack.len() - count);
+                    self.stack.push(Rc::new(Object::Array(elements)));
+                }
+                Op::Append => {
+                    let element = self.pop();
+                    if let Object::Array(elements) = self.top() {
+                        elements.push(element);
+                    }
+                }
+                Op::Extend(into, below, exit) => {
+                    let spread = self.pop();
+                    match &*spread {
+                        Object::Array(spread) => {
+                            if let Object::Array(elements) = self.top() {
+                                elements.extend(spread.iter().cloned());
+                            }
+                        }
+                        _ => self.abort(below + 1, error(format!("Cannot spread {} {}", spread.to_string(), into)), exit),
+                    }
+                }
+                Op::Map => self.stack.push(Rc::new(Object::Map(Table::new()))),
+                Op::Set => self.stack.push(Rc::new(Object::Set(Table::new()))),
+                Op::Insert(exit) => {
+                    let value = self.pop();
+                    let key = self.pop();
+                    if let Object::Map(table) = self.top() {
+                        if let Err(error) = map::insert(table, key, value) {
+                            self.abort(1, error, exit);
+                        }
+                    }
+                }
+                Op::Add(exit) => {
+                    let element = self.pop();
+                    if let Object::Set(table) = self.top() {
+                        if let Err(error) = map::insert(table, element, Rc::new(Object::None)) {
+                            self.abort(1, error, exit);
+                        }
+                    }
+                }
+                Op::JumpUnlessPrefix(to) => {
+                    if !is_prefix(self.stack.last().unwrap()) {
+                        self.pc = to;
+                    }
+                }
+                Op::Prefix(count) => {
+                    let args = self.stack.split_off(self.stack.len() - count);
+                    let prefix = self.pop();
+                    self.stack.push(call_prefix(&prefix, &args));
+                }
+                Op::GetIndex => {
+                    let index = self.pop();
+                    let target = self.pop();
+                    self.stack.push(get_index(&target, &index));
+                }
+                Op::GetSlice => {
+                    let end = self.pop();
+                    let start = self.pop();
+                    let target = self.pop();
+                    self.stack.push(get_slice(&target, &start, &end));
+                }
+                Op::SetIndex => {
+                    let value = self.pop();
+                    let index = self.pop();
+                    let target = self.pop();
+                    self.stack.push(set_index(target, &index, value));
+                }
+                Op::CurrentIndex(exit) => {
+                    let length = self.stack.len();
+                    let current = get_index(&self.stack[length - 2], &self.stack[length - 1]);
+                    match *current {
+                        Object::Error(_) => self.abort(2, current, exit),
+                        _ => self.stack.push(current),
+                    }
+                }
+                Op::Call(count) => {
+                    let args = self.stack.split_off(self.stack.len() - count);
+                    let callee = self.pop();
+                    self.stack.push(call_named(&callee, &args, &vec![], &callee));
+                }
+                Op::CallNamed(arguments) => {
+                    let names = &self.chunk.arguments[arguments];
+                    let values = self.stack.split_off(self.stack.len() - names.len());
+                    let named: Vec<(String, Rc<Object>)> = names.iter().map(|name| name.name().to_string()).zip(values).collect();
+                    let args = match &*self.pop() {
+                        Object::Array(args) => args.to_owned(),
+                        _ => vec![],
+                    };
+                    let callee = self.pop();
+                    self.stack.push(call_named(&callee, &args, &named, &callee));
+                }
+                Op::Jump(to) => self.pc = to,
+                Op::Matches(to) => {
+                    let candidate = self.pop();
+                    let subject = self.pop();
+                    if !map::key_eq(&subject, &candidate) {
+                        self.pc = to;
+                    }
+                }
+                Op::Default(slot, to) => {
+                    if self.locals[slot].is_some() {
+                        self.pc = to;
+                    }
+                }
+                Op::Class(index) => {
+                    let body = &self.chunk.classes[index];
+                    let mut locals = vec![None; body.slots.len()];
+                    locals[0] = Some(Rc::new(Object::Instance(HashMap::new())));
+                    execute(body, &mut locals);
+                    let mut fields = HashMap::new();
+                    for (slot, value) in body.slots.iter().zip(locals) {
+                        if let (Some(name), Some(value)) = (slot, value) {
+                            fields.insert(name.name().to_string(), value);
+                        }
+                    }
+                    self.stack.push(class(fields));
+                }
+                Op::Mark => self.marks.push((self.stack.len(), self.pending.len())),
+                Op::Reset => {
+                    let (height, pending) = self.marks.pop().unwrap();
+                    self.stack.truncate(height);
+                    self.pending.truncate(pending);
+                }
+                Op::Raise => self.stack.push(self.error.to_owned()),
+                Op::Assign => {
+                    let (_height, pending) = self.marks.pop().unwrap();
+                    let targets = self.pending.split_off(pending);
+                    let result = self.assign(targets);
+                    self.stack.push(result);
+                }
+                Op::Bind(slot) => {
+                    let value = self.pop();
+                    self.pending.push((Target::Slot(slot), value));
+                }
+                Op::BindMember(name, fail) => {
+                    let instance = self.pop();
+                    let value = self.pop();
+                    let name = name.name().to_string();
+                    if let Object::Enum(_, _) = *instance {
+                        if let Err(error) = pattern::variant(&instance, &name, &value) {
+                            self.fail(error, fail);
+                        }
+                    } else {
+                        self.pending.push((Target::Member(instance, name), value));
+                    }
+                }
+                Op::Payload(fail) => {
+                    let variant = self.pop();
+                    let value = self.pop();
+                    match pattern::payload(&variant, &value) {
+                        Ok(payload) => self.stack.push(payload),
+                        Err(error) => self.fail(error, fail),
+                    }
+                }
+                Op::BindIndex => {
+                    let index = self.pop();
+                    let target = self.pop();
+                    let value = self.pop();
+                    self.pending.push((Target::Index(target, index), value));
+                }
+                Op::Destructure { elements, rest, many, fail } => {
+                    let value = self.pop();
+                    match pattern::split(elements, rest, many, &value) {
+                        Ok(values) => self.stack.extend(values.into_iter().rev()),
+                        Err(error) => self.fail(error, fail),
+                    }
+                }
+                Op::DestructureMap(fail) => {
+                    let value = self.stack.last().unwrap().to_owned();
+                    if !matches!(*value, Object::Map(_)) {
+                        self.fail(pattern::mismatch("a map", &value), fail);
+                    }
+                }
+                Op::Lookup(fail) => {
+                    let key = self.pop();
+                    let table = self.pop();
+                    let found = match &*table {
+                        Object::Map(table) => map::lookup(table, &key),
+                        _ => Ok(None),
+                    };
+                    match found {
+                        Ok(Some(value)) => self.stack.push(value),
+                        Ok(None) => self.fail(error(format!("Key {} does not exist", key.to_string())), fail),
+                        Err(error) => self.fail(error, fail),
+                    }
+                }
+                Op::Expect(fail) => {
+                    let expected = self.pop();
+                    let value = self.pop();
+                    if !map::key_eq(&expected, &value) {
+                        self.fail(error(format!("{} does not match {}", value.to_string(), expected.to_string())), fail);
+                    }
+                }
+                Op::Throw(fail) => {
+                    let error = self.pop();
+                    self.fail(error, fail);
+                }
+                Op::ReturnIfError => {
+                    if let Object::Error(_) = **self.stack.last().unwrap() {
+                        return self.pop();
+                    }
+                }
+                Op::Return => return self.pop(),
+            }
+        }
+    }
+}
+
+fn execute(chunk: &Chunk, locals: &mut Vec<Option<Rc<Object>>>) -> Rc<Object> {
+    let mut frame = Frame { chunk, locals, stack: vec![], marks: vec![], pending: vec![], error: Rc::new(Object::None), pc: 0 };
+    frame.run()
+}
+
+// Binds arguments to slots the same way `interpreter::bind` binds them to names.
+fn bind(chunk: &Chunk, args: &[Rc<Object>], named: &[(String, Rc<Object>)], locals: &mut [Option<Rc<Object>>]) -> Result<(), Rc<Object>> {
+    let mut positional = args.iter();
+    let mut accepted = 0;
+    for (parameter, slot) in chunk.parameters.iter() {
+        let slot = match slot {
+            Some(slot) => *slot,
+            None => return Err(error(format!("Invalid parameter {:?}", parameter))),
+        };
+        if let Node::Variadic(_) = parameter {
+            locals[slot] = Some(Rc::new(Object::Array(positional.by_ref().cloned().collect())));
+            accepted = args.len();
+        } else if let Some(arg) = positional.next() {
+            locals[slot] = Some(arg.to_owned());
+            accepted += 1;
+        }
+    }
+    if args.len() > accepted {
+        return Err(error(format!("Expected at most {} arguments, got {}", accepted, args.len())));
+    }
+    for (name, value) in named.iter() {
+        match chunk.parameters.iter().find(|(parameter, _slot)| parameter_name(parameter) == Some(name)) {
+            None | Some((Node::Variadic(_), _)) => return Err(error(format!("Unexpected argument {}", name))),
+            Some((_parameter, slot)) => {
+                let slot = slot.unwrap();
+                if locals[slot].is_some() {
+                    return Err(error(format!("Argument {} given more than once", name)));
+                }
+                locals[slot] = Some(value.to_owned());
+            }
+        }
+    }
+    for (parameter, slot) in chunk.parameters.iter() {
+        if let (Node::Identifier(name), Some(slot)) = (parameter, slot) {
+            if locals[*slot].is_none() {
+                return Err(error(format!("Missing argument {}", name)));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn compiled(function: &Rc<Object>, parameters: &[Node], body: &[Node]) -> Rc<Chunk> {
+    FUNCTIONS.with(|functions| {
+        let mut functions = functions.borrow_mut();
+        if let Some((_function, chunk)) = functions.get(&Rc::as_ptr(function)) {
+            return chunk.to_owned();
+        }
+        if functions.len() >= SWEEP_AT.max(2 * SWEPT.with(Cell::get)) {
+            functions.retain(|_address, (function, _chunk)| function.strong_count() > 0);
+            SWEPT.with(|swept| swept.set(functions.len()));
+        }
+        let chunk = Rc::new(compile_function(parameters, body));
+        functions.insert(Rc::as_ptr(function), (Rc::downgrade(function), chunk.to_owned()));
+        chunk
+    })
+}
+
+// Calls a function object, its body is compiled on the first call.
+pub fn call(function: &Rc<Object>, args: &[Rc<Object>], named: &[(String, Rc<Object>)], instance: &Rc<Object>) -> Rc<Object> {
+    let chunk = match &**function {
+        Object::Function(parameters, body) => compiled(function, parameters, body),
+        _ => return call_named(function, &args.to_vec(), &named.to_vec(), instance),
+    };
+    let mut locals = vec![None; chunk.slots.len()];
+    locals[0] = Some(instance.to_owned());
+    if let Err(error) = bind(&chunk, args, named, &mut locals) {
+        return error;
+    }
+    execute(&chunk, &mut locals)
+}
+
+// Runs the statements of a module with the bindings of `scope` and writes its bindings back to it.
+pub fn run(ast: Node, scope: &mut HashMap<String, Rc<Object>>) -> Rc<Object> {
+    let statements = match ast {
+        Node::Program(statements) => statements,
+        statement => vec![statement],
+    };
+    let chunk = compile_module(&statements);
+    let mut locals: Vec<Option<Rc<Object>>> = chunk.slots.iter().map(|slot| slot.and_then(|name| scope.get(&*name.name()).cloned())).collect();
+    let rtn = execute(&chunk, &mut locals);
+    for (slot, value) in chunk.slots.iter().zip(locals) {
+        if let (Some(name), Some(value)) = (slot, value) {
+            scope.insert(name.name().to_string(), value);
+        }
+    }
+    rtn
+}
+
+fn same_fields(lhs: &HashMap<String, Rc<Object>>, rhs: &HashMap<String, Rc<Object>>, depth: usize) -> bool {
+    lhs.len() == rhs.len() && lhs.iter().all(|(name, value)| rhs.get(name).is_some_and(|other| same(value, other, depth)))
+}
+
+fn same(lhs: &Rc<Object>, rhs: &Rc<Object>, depth: usize) -> bool {
+    if depth == 0 || Rc::ptr_eq(lhs, rhs) {
+        return true;
+    }
+    match (&**lhs, &**rhs) {
+        (Object::Float(lhs), Object::Float(rhs)) => lhs == rhs || (lhs.is_nan() && rhs.is_nan()),
+        (Object::Array(lhs), Object::Array(rhs)) => lhs.len() == rhs.len() && lhs.iter().zip(rhs.iter()).all(|(lhs, rhs)| same(lhs, rhs, depth - 1)),
+        (Object::Map(lhs), Object::Map(rhs)) | (Object::Set(lhs), Object::Set(rhs)) => {
+            lhs.len() == rhs.len() && lhs.iter().zip(rhs.iter()).all(|((lhs_key, lhs), (rhs_key, rhs))| same(lhs_key, rhs_key, depth - 1) && same(lhs, rhs, depth - 1))
+        }
+        (Object::Class(lhs), Object::Class(rhs)) | (Object::Instance(lhs), Object::Instance(rhs)) => same_fields(lhs, rhs, depth - 1),
+        (Object::Module(lhs_name, lhs), Object::Module(rhs_name, rhs)) => lhs_name == rhs_name && same_fields(lhs, rhs, depth - 1),
+        (Object::BoundFunction(lhs_instance, lhs), Object::BoundFunction(rhs_instance, rhs)) => same(lhs_instance, rhs_instance, depth - 1) && same(lhs, rhs, depth - 1),
+        (Object::Function(lhs_parameters, lhs), Object::Function(rhs_parameters, rhs)) => lhs_parameters == rhs_parameters && lhs == rhs,
+        (lhs, rhs) => std::mem::discriminant(lhs) == std::mem::discriminant(rhs) && lhs.to_string() == rhs.to_string(),
+    }
+}
+
+// Structural equality used to compare the results of both engines, instances compare their fields
+// and functions their code. Cyclic values are only compared up to a fixed depth.
+pub fn equivalent(lhs: &Rc<Object>, rhs: &Rc<Object>) -> bool {
+    same(lhs, rhs, 16)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::path::Path;
+    use std::rc::Rc;
+
+    use super::{call, equivalent, run, FUNCTIONS, SWEEP_AT};
+    use crate::interpreter::interpret;
+    use crate::module::{self, Engine};
+    use crate::testing::statements;
+    use object::Object;
+    use parser::node::Node;
+
+    // The cache of compiled bodies neither keeps functions alive nor grows with the functions that were dropped.
+    #[test]
+    fn vm_forgets_the_functions_that_are_dropped() {
+        let function = || Rc::new(Object::Function(vec![], statements("1")));
+        let first = function();
+        assert_eq!(call(&first, &[], &[], &Rc::new(Object::None)).to_string(), "1");
+        let weak = Rc::downgrade(&first);
+        drop(first);
+        assert_eq!(weak.strong_count(), 0);
+        for _ in 0..1000 {
+            call(&function(), &[], &[], &Rc::new(Object::None));
+        }
+        assert!(FUNCTIONS.with(|functions| functions.borrow().len()) <= SWEEP_AT);
+    }
+
+    #[test]
+    fn vm_runs_in_the_scope_it_is_given() {
+        let mut scope = HashMap::new();
+        scope.insert(String::from("x"), Rc::new(Object::Integer(2)));
+        let rtn = run(Node::Program(statements("y = x * 3\nz = [x, y]\ny")), &mut scope);
+        assert_eq!(rtn.to_string(), "6");
+        let bindings: Vec<String> = ["x", "y", "z"].iter().map(|name| scope[*name].to_string()).collect();
+        assert_eq!(bindings, ["2", "6", "[2, 6]"]);
+        let rtn = run(Node::Program(statements("a = 1\n1 / 0\nb = 2")), &mut scope);
+        assert_eq!(rtn.to_string(), "Error(\"Division by zero\")");
+        assert!(scope.contains_key("a") && !scope.contains_key("b"));
+    }
+
+    // Both engines build equal values as different objects, NaN and cycles included.
+    #[test]
+    fn vm_results_are_compared_structurally() {
+        let evaluate = |engine: Engine, code: &str| {
+            let code = format!("class Point {{\n  x = 0\n}}\nitems = [Point(), 0.0 / 0.0, (n) {{ n }}, {{1: [2]}}]\nitems.push(items)\n{}", code);
+            module::set_engine(engine);
+            module::evaluate(Path::new("<test>"), &code, &mut HashMap::new())
+        };
+        let (tree, vm) = (evaluate(Engine::Tree, "items"), evaluate(Engine::Vm, "items"));
+        assert!(!Rc::ptr_eq(&tree, &vm) && equivalent(&tree, &vm));
+        let changed = evaluate(Engine::Vm, "items[0].x = 1\nitems");
+        assert!(!equivalent(&tree, &changed));
+        assert!(equivalent(&evaluate(Engine::Tree, "items[0].x = 1\nitems"), &changed));
+    }
+
+    #[test]
+    fn nodes_without_a_value_are_errors_on_both_engines() {
+        let slice = Node::Slice(Box::new(Node::Number(String::from("1"))), Box::new(Node::Empty));
+        let expected = "Error(\"Cannot evaluate Slice\")";
+        assert_eq!(interpret(slice.to_owned(), &mut HashMap::new()).to_string(), expected);
+        assert_eq!(run(Node::Program(vec![slice]), &mut HashMap::new()).to_string(), expected);
+    }
+}
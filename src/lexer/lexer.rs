@@ -1,21 +1,60 @@
+use super::span::{Position, Span};
 use super::token::Token;
 
 pub fn lex(code: &str) -> Option<Vec<Token>> {
+    Some(lex_spanned(code)?.into_iter().map(|(token, _span)| token).collect())
+}
+
+pub fn lex_spanned(code: &str) -> Option<Vec<(Token, Span)>> {
+    Some(lex_with_comments(code)?.into_iter().filter(|(token, _span)| !matches!(token, Token::Comment(_))).collect())
+}
+
+// Comments are dropped before parsing, tools that need them, like the formatter, keep them with their spans.
+pub fn lex_with_comments(code: &str) -> Option<Vec<(Token, Span)>> {
+    tokenize(code).ok()
+}
+
+// True when the code ends inside a token, like a string whose closing quote is on a later line.
+pub fn is_unterminated(code: &str) -> bool {
+    matches!(tokenize(code), Err(Some(Token::Literal(_, _))))
+}
+
+// Fails with the token being built when the input ends before it is complete.
+fn tokenize(code: &str) -> Result<Vec<(Token, Span)>, Option<Token>> {
     let mut characters: Vec<char> = code.chars().collect();
-    let mut tokens = vec![];
+    let mut tokens: Vec<(Token, Span)> = vec![];
+    let mut position = Position::start();
     while !characters.is_empty() {
         let character = characters.remove(0);
+        let start = position;
+        position.advance(character);
         if let Some(mut token) = Token::start_of(character) {
-            while !characters.is_empty() && token.continues(characters[0])? {
+            while !characters.is_empty() && token.continues(characters[0]).ok_or(None)? {
+                position.advance(characters[0]);
                 token.append(characters.remove(0));
             }
             if token.is_complete() {
-                token.clean();
-                tokens.push(token);
+                token.clean().ok_or(None)?;
+                if !characters.is_empty() && token.prefixes(characters[0], tokens.last().map(|(token, _span)| token)) {
+                    if let Token::Identifier(name) = token {
+                        token = Token::Prefix(name);
+                    }
+                }
+                if let (Token::Period, [.., (Token::Period, first), (Token::Period, second)]) = (&token, tokens.as_slice()) {
+                    if first.end == second.start && second.end == start {
+                        let start = first.start;
+                        tokens.truncate(tokens.len() - 2);
+                        tokens.push((Token::Ellipsis, Span { start, end: position }));
+                        continue;
+                    }
+                }
+                tokens.push((token, Span { start, end: position }));
+            } else if characters.is_empty() {
+                return Err(Some(token));
             } else {
-                return None;
+                return Err(None);
             }
         }
     }
-    Some(tokens)
+    Ok(tokens)
 }
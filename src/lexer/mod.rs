@@ -1,3 +1,4 @@
 pub mod lexer;
+pub mod span;
 pub mod tests;
 pub mod token;
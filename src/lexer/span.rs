@@ -0,0 +1,29 @@
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+    pub offset: usize,
+}
+
+impl Position {
+    pub fn start() -> Position {
+        Position { line: 1, column: 1, offset: 0 }
+    }
+
+    pub fn advance(&mut self, character: char) {
+        self.offset += 1;
+        if character == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+    }
+}
+
+// Lines and columns start at 1, offsets count characters from 0 and `end` is exclusive.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
@@ -1,5 +1,5 @@
 #[allow(unused_imports)]
-use crate::lexer;
+use crate as lexer;
 
 #[cfg(test)]
 mod tests {
@@ -436,4 +436,272 @@ mod tests {
             ])
         );
     }
+
+    #[test]
+    fn prefixed_string_literal() {
+        assert_eq!(
+            super::lexer::lexer::lex("f'Hello {name}'"),
+            Some(vec![super::lexer::token::Token::Prefix(String::from("f")), super::lexer::token::Token::Literal(String::from("Hello {name}"), '\''),])
+        );
+    }
+
+    #[test]
+    fn prefixed_brace_literal() {
+        assert_eq!(
+            super::lexer::lexer::lex("ordered{}"),
+            Some(vec![super::lexer::token::Token::Prefix(String::from("ordered")), super::lexer::token::Token::OpenBrace, super::lexer::token::Token::CloseBrace,])
+        );
+    }
+
+    #[test]
+    fn identifier_separated_from_literal_is_not_a_prefix() {
+        assert_eq!(
+            super::lexer::lexer::lex("f 'foo'"),
+            Some(vec![super::lexer::token::Token::Identifier(String::from("f")), super::lexer::token::Token::Literal(String::from("foo"), '\''),])
+        );
+    }
+
+    #[test]
+    fn class_name_followed_by_brace_is_not_a_prefix() {
+        assert_eq!(
+            super::lexer::lexer::lex("class Foo{}"),
+            Some(vec![
+                super::lexer::token::Token::Identifier(String::from("class")),
+                super::lexer::token::Token::Identifier(String::from("Foo")),
+                super::lexer::token::Token::OpenBrace,
+                super::lexer::token::Token::CloseBrace,
+            ])
+        );
+    }
+
+    #[test]
+    fn string_literal_with_escaped_newline() {
+        assert_eq!(super::lexer::lexer::lex("'f\\no'"), Some(vec![super::lexer::token::Token::Literal(String::from("f\no"), '\''),]));
+    }
+
+    #[test]
+    fn string_literal_with_escaped_tab() {
+        assert_eq!(super::lexer::lexer::lex("'f\\to'"), Some(vec![super::lexer::token::Token::Literal(String::from("f\to"), '\''),]));
+    }
+
+    #[test]
+    fn string_literal_with_escaped_backslash() {
+        assert_eq!(super::lexer::lexer::lex("'f\\\\'"), Some(vec![super::lexer::token::Token::Literal(String::from("f\\"), '\''),]));
+    }
+
+    #[test]
+    fn string_literal_with_escaped_unicode() {
+        assert_eq!(super::lexer::lexer::lex("\"\\u{1F600}\""), Some(vec![super::lexer::token::Token::Literal(String::from("\u{1F600}"), '"'),]));
+    }
+
+    #[test]
+    fn string_literal_with_invalid_escape() {
+        assert_eq!(super::lexer::lexer::lex("'f\\qo'"), None);
+    }
+
+    #[test]
+    fn string_literal_with_invalid_unicode_escape() {
+        assert_eq!(super::lexer::lexer::lex("'\\u{110000}'"), None);
+    }
+
+    #[test]
+    fn raw_string_literal() {
+        assert_eq!(super::lexer::lexer::lex("r'f\\no\\'"), Some(vec![super::lexer::token::Token::Literal(String::from("f\\no\\"), '\''),]));
+    }
+
+    #[test]
+    fn empty_string_literal() {
+        assert_eq!(super::lexer::lexer::lex("''"), Some(vec![super::lexer::token::Token::Literal(String::from(""), '\''),]));
+    }
+
+    #[test]
+    fn multiline_string_literal() {
+        assert_eq!(
+            super::lexer::lexer::lex("'''f'o\n\"o\"'''"),
+            Some(vec![super::lexer::token::Token::Literal(String::from("f'o\n\"o\""), '\''),])
+        );
+    }
+
+    #[test]
+    fn raw_multiline_string_literal() {
+        assert_eq!(super::lexer::lexer::lex("r\"\"\"f\\o\no\"\"\""), Some(vec![super::lexer::token::Token::Literal(String::from("f\\o\no"), '"'),]));
+    }
+
+    #[test]
+    fn unterminated_multiline_string_literal() {
+        assert_eq!(super::lexer::lexer::lex("'''foo''"), None);
+    }
+
+    #[test]
+    fn hexadecimal_number() {
+        assert_eq!(super::lexer::lexer::lex("0xFF"), Some(vec![super::lexer::token::Token::Number(String::from("0xFF")),]));
+    }
+
+    #[test]
+    fn octal_number() {
+        assert_eq!(super::lexer::lexer::lex("0o17"), Some(vec![super::lexer::token::Token::Number(String::from("0o17")),]));
+    }
+
+    #[test]
+    fn binary_number() {
+        assert_eq!(super::lexer::lexer::lex("0b1010"), Some(vec![super::lexer::token::Token::Number(String::from("0b1010")),]));
+    }
+
+    #[test]
+    fn binary_number_followed_by_decimal_digit() {
+        assert_eq!(
+            super::lexer::lexer::lex("0b102"),
+            Some(vec![super::lexer::token::Token::Number(String::from("0b10")), super::lexer::token::Token::Number(String::from("2")),])
+        );
+    }
+
+    #[test]
+    fn radix_prefix_without_digits() {
+        assert_eq!(super::lexer::lexer::lex("0x"), None);
+    }
+
+    #[test]
+    fn number_with_digit_separators() {
+        assert_eq!(super::lexer::lexer::lex("1_000_000"), Some(vec![super::lexer::token::Token::Number(String::from("1_000_000")),]));
+    }
+
+    #[test]
+    fn number_ending_with_digit_separator() {
+        assert_eq!(super::lexer::lexer::lex("1_"), None);
+    }
+
+    #[test]
+    fn scientific_notation() {
+        assert_eq!(super::lexer::lexer::lex("1.5e-3"), Some(vec![super::lexer::token::Token::Number(String::from("1.5e-3")),]));
+    }
+
+    #[test]
+    fn scientific_notation_without_period() {
+        assert_eq!(super::lexer::lexer::lex("2E10"), Some(vec![super::lexer::token::Token::Number(String::from("2E10")),]));
+    }
+
+    #[test]
+    fn scientific_notation_without_exponent() {
+        assert_eq!(super::lexer::lexer::lex("2e+"), None);
+    }
+
+    #[test]
+    fn hexadecimal_number_subtraction() {
+        assert_eq!(
+            super::lexer::lexer::lex("0xe-1"),
+            Some(vec![
+                super::lexer::token::Token::Number(String::from("0xe")),
+                super::lexer::token::Token::Minus,
+                super::lexer::token::Token::Number(String::from("1")),
+            ])
+        );
+    }
+
+    #[test]
+    fn compound_assignations() {
+        assert_eq!(
+            super::lexer::lexer::lex("x += 1 -= 2 *= 3 /= 4 %= 5"),
+            Some(vec![
+                super::lexer::token::Token::Identifier(String::from("x")),
+                super::lexer::token::Token::PlusEqual,
+                super::lexer::token::Token::Number(String::from("1")),
+                super::lexer::token::Token::MinusEqual,
+                super::lexer::token::Token::Number(String::from("2")),
+                super::lexer::token::Token::TimesEqual,
+                super::lexer::token::Token::Number(String::from("3")),
+                super::lexer::token::Token::DivisionEqual,
+                super::lexer::token::Token::Number(String::from("4")),
+                super::lexer::token::Token::ModulusEqual,
+                super::lexer::token::Token::Number(String::from("5")),
+            ])
+        );
+    }
+
+    #[test]
+    fn addition_followed_by_assignation() {
+        assert_eq!(
+            super::lexer::lexer::lex("x + = 1"),
+            Some(vec![
+                super::lexer::token::Token::Identifier(String::from("x")),
+                super::lexer::token::Token::Plus,
+                super::lexer::token::Token::Equal,
+                super::lexer::token::Token::Number(String::from("1")),
+            ])
+        );
+    }
+
+    #[test]
+    fn unterminated_string() {
+        assert!(super::lexer::lexer::is_unterminated("x = '''first\nsecond"));
+        assert!(!super::lexer::lexer::is_unterminated("x = 'done'"));
+        assert!(!super::lexer::lexer::is_unterminated("x = 1_"));
+    }
+
+    #[test]
+    fn token_spans() {
+        use super::lexer::span::{Position, Span};
+        assert_eq!(
+            super::lexer::lexer::lex_spanned("x = 'a'\ny"),
+            Some(vec![
+                (
+                    super::lexer::token::Token::Identifier(String::from("x")),
+                    Span { start: Position { line: 1, column: 1, offset: 0 }, end: Position { line: 1, column: 2, offset: 1 } }
+                ),
+                (
+                    super::lexer::token::Token::Equal,
+                    Span { start: Position { line: 1, column: 3, offset: 2 }, end: Position { line: 1, column: 4, offset: 3 } }
+                ),
+                (
+                    super::lexer::token::Token::Literal(String::from("a"), '\''),
+                    Span { start: Position { line: 1, column: 5, offset: 4 }, end: Position { line: 1, column: 8, offset: 7 } }
+                ),
+                (
+                    super::lexer::token::Token::NewLine,
+                    Span { start: Position { line: 1, column: 8, offset: 7 }, end: Position { line: 2, column: 1, offset: 8 } }
+                ),
+                (
+                    super::lexer::token::Token::Identifier(String::from("y")),
+                    Span { start: Position { line: 2, column: 1, offset: 8 }, end: Position { line: 2, column: 2, offset: 9 } }
+                ),
+            ]),
+        );
+    }
+
+    #[test]
+    fn comments_are_skipped() {
+        assert_eq!(
+            super::lexer::lexer::lex("x = 4 # four\n# alone"),
+            Some(vec![
+                super::lexer::token::Token::Identifier(String::from("x")),
+                super::lexer::token::Token::Equal,
+                super::lexer::token::Token::Number(String::from("4")),
+                super::lexer::token::Token::NewLine,
+            ]),
+        );
+    }
+
+    #[test]
+    fn comments_are_kept_for_tools() {
+        let tokens: Vec<super::lexer::token::Token> = super::lexer::lexer::lex_with_comments("x # it's x").unwrap().into_iter().map(|(token, _span)| token).collect();
+        assert_eq!(tokens, vec![super::lexer::token::Token::Identifier(String::from("x")), super::lexer::token::Token::Comment(String::from(" it's x"))]);
+    }
+
+    #[test]
+    fn ellipsis() {
+        use super::lexer::span::{Position, Span};
+        assert_eq!(
+            super::lexer::lexer::lex_spanned("rest..."),
+            Some(vec![
+                (
+                    super::lexer::token::Token::Identifier(String::from("rest")),
+                    Span { start: Position { line: 1, column: 1, offset: 0 }, end: Position { line: 1, column: 5, offset: 4 } }
+                ),
+                (super::lexer::token::Token::Ellipsis, Span { start: Position { line: 1, column: 5, offset: 4 }, end: Position { line: 1, column: 8, offset: 7 } }),
+            ]),
+        );
+        assert_eq!(
+            super::lexer::lexer::lex(".. ."),
+            Some(vec![super::lexer::token::Token::Period, super::lexer::token::Token::Period, super::lexer::token::Token::Period]),
+        );
+    }
 }
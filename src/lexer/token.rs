@@ -1,6 +1,7 @@
 #[derive(Clone, Debug, PartialEq)]
 pub enum Token {
     Identifier(String),
+    Prefix(String),
     Number(String),
     Literal(String, char),
     Equal,
@@ -9,9 +10,16 @@ pub enum Token {
     Times,
     Division,
     Modulus,
+    PlusEqual,
+    MinusEqual,
+    TimesEqual,
+    DivisionEqual,
+    ModulusEqual,
     Comma,
     Colon,
     Period,
+    // Three adjacent periods, `rest...`.
+    Ellipsis,
     SemiColon,
     NewLine,
     OpenParenthesis,
@@ -20,6 +28,7 @@ pub enum Token {
     CloseBrace,
     OpenBracket,
     CloseBracket,
+    Comment(String),
 }
 
 impl Token {
@@ -42,6 +51,8 @@ impl Token {
             Some(Token::Colon)
         } else if character == '.' {
             Some(Token::Period)
+        } else if character == '#' {
+            Some(Token::Comment(String::new()))
         } else if character == ';' {
             Some(Token::SemiColon)
         } else if character == '\n' {
@@ -69,17 +80,53 @@ impl Token {
         }
     }
 
+    pub fn prefixes(&self, character: char, previous: Option<&Token>) -> bool {
+        match self {
+            Token::Identifier(_) => match character {
+                '"' | '\'' => true,
+                '{' => match previous {
+                    Some(Token::Identifier(keyword)) => keyword != "class" && keyword != "enum",
+                    _ => true,
+                },
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
     pub fn continues(&self, character: char) -> Option<bool> {
         match self {
             Token::Period => Some(character.is_digit(10)),
-            Token::Identifier(_) => Some(character.is_alphabetic() || character.is_digit(10) || character == '_'),
+            Token::Comment(_) => Some(character != '\n'),
+            Token::Plus | Token::Minus | Token::Times | Token::Division | Token::Modulus => Some(character == '='),
+            Token::Identifier(string) => {
+                if string == "r" && (character == '"' || character == '\'') {
+                    Some(true)
+                } else {
+                    Some(character.is_alphabetic() || character.is_ascii_digit() || character == '_')
+                }
+            }
             Token::Literal(string, delimiter) => {
-                let escaped_delimiter = format!("\\{}", delimiter);
-                Some(string.matches(*delimiter).count() - string.matches(&escaped_delimiter).count() != 2)
+                if character == *delimiter && string.trim_start_matches('r').chars().all(|quote| quote == *delimiter) && string.trim_start_matches('r').len() == 2 {
+                    Some(true)
+                } else {
+                    Some(!Token::is_closed(string, *delimiter))
+                }
             }
             Token::Number(string) => {
-                if character.is_digit(10) {
+                let radix = Token::radix(string);
+                if string == "0" && "xobXOB".contains(character) {
                     Some(true)
+                } else if radix != 10 {
+                    Some(character.is_digit(radix) || character == '_')
+                } else if character.is_ascii_digit() || character == '_' {
+                    Some(true)
+                } else if character == 'e' || character == 'E' {
+                    Some(!string.contains(['e', 'E']))
+                } else if character == '+' || character == '-' {
+                    Some(string.ends_with('e') || string.ends_with('E'))
+                } else if string.contains(['e', 'E']) {
+                    Some(false)
                 } else if string.contains(".") {
                     if character == '.' || string.len() == 1 {
                         None
@@ -96,36 +143,118 @@ impl Token {
 
     pub fn append(&mut self, character: char) {
         match self {
-            Token::Identifier(string) => string.push(character),
+            Token::Identifier(string) => {
+                if character == '"' || character == '\'' {
+                    *self = Token::Literal(format!("{}{}", string, character), character);
+                } else {
+                    string.push(character)
+                }
+            }
             Token::Number(string) => string.push(character),
-            Token::Literal(string, _) => string.push(character),
+            Token::Literal(string, _) | Token::Comment(string) => string.push(character),
             Token::Period => {
                 *self = Token::Number(String::from(format!(".{}", character)));
             }
+            Token::Plus => *self = Token::PlusEqual,
+            Token::Minus => *self = Token::MinusEqual,
+            Token::Times => *self = Token::TimesEqual,
+            Token::Division => *self = Token::DivisionEqual,
+            Token::Modulus => *self = Token::ModulusEqual,
             _ => unreachable!(),
         }
     }
 
     pub fn is_complete(&self) -> bool {
         match self {
-            Token::Number(string) => string.len() > 1 || string.chars().next().unwrap() != '.',
-            Token::Literal(string, delimiter) => {
-                let escaped_delimiter = format!("\\{}", delimiter);
-                string.matches(*delimiter).count() - string.matches(&escaped_delimiter).count() == 2
+            Token::Number(string) => {
+                let radix = Token::radix(string);
+                let digits = if radix == 10 { string.len() } else { string.len() - 2 };
+                let unfinished = if radix == 10 { "_eE+-" } else { "_" };
+                (string.len() > 1 || !string.starts_with('.')) && digits > 0 && !string.ends_with(|last| unfinished.contains(last))
             }
+            Token::Literal(string, delimiter) => Token::is_closed(string, *delimiter),
             _ => true,
         }
     }
 
-    pub fn clean(&mut self) {
+    pub fn clean(&mut self) -> Option<()> {
         match self {
             Token::Literal(string, delimiter) => {
-                string.remove(0);
-                string.remove(string.len() - 1);
-                let escaped_delimiter = format!("\\{}", delimiter);
-                *string = string.replace(&escaped_delimiter, &delimiter.to_string());
+                let raw = string.starts_with('r');
+                let quoted = string.trim_start_matches('r');
+                let quotes = if Token::is_triple_quoted(quoted, *delimiter) { 3 } else { 1 };
+                let body: String = quoted.chars().skip(quotes).take(quoted.chars().count() - 2 * quotes).collect();
+                *string = if raw { body } else { Token::unescape(&body)? };
             }
             _ => {}
         }
+        Some(())
+    }
+
+    pub fn radix(string: &str) -> u32 {
+        match string.get(0..2) {
+            Some("0x") | Some("0X") => 16,
+            Some("0o") | Some("0O") => 8,
+            Some("0b") | Some("0B") => 2,
+            _ => 10,
+        }
+    }
+
+    fn is_triple_quoted(quoted: &str, delimiter: char) -> bool {
+        quoted.len() >= 3 && quoted.chars().take(3).all(|quote| quote == delimiter)
+    }
+
+    // A literal is closed once its opening quotes are matched by unescaped closing quotes at its very end,
+    // backslashes only escape the next character outside of raw strings.
+    fn is_closed(string: &str, delimiter: char) -> bool {
+        let raw = string.starts_with('r');
+        let quoted: Vec<char> = string.trim_start_matches('r').chars().collect();
+        let quotes = if Token::is_triple_quoted(string.trim_start_matches('r'), delimiter) { 3 } else { 1 };
+        let mut index = quotes;
+        while index + quotes <= quoted.len() {
+            if !raw && quoted[index] == '\\' {
+                index += 2;
+            } else if quoted[index..index + quotes].iter().all(|quote| *quote == delimiter) {
+                return index + quotes == quoted.len();
+            } else {
+                index += 1;
+            }
+        }
+        false
+    }
+
+    fn unescape(body: &str) -> Option<String> {
+        let mut string = String::new();
+        let mut characters = body.chars();
+        while let Some(character) = characters.next() {
+            if character != '\\' {
+                string.push(character);
+                continue;
+            }
+            match characters.next()? {
+                'n' => string.push('\n'),
+                't' => string.push('\t'),
+                'r' => string.push('\r'),
+                '0' => string.push('\0'),
+                '\\' => string.push('\\'),
+                '\'' => string.push('\''),
+                '"' => string.push('"'),
+                'u' => {
+                    if characters.next()? != '{' {
+                        return None;
+                    }
+                    let mut code = String::new();
+                    loop {
+                        match characters.next()? {
+                            '}' => break,
+                            digit => code.push(digit),
+                        }
+                    }
+                    string.push(std::char::from_u32(u32::from_str_radix(&code, 16).ok()?)?);
+                }
+                _ => return None,
+            }
+        }
+        Some(string)
     }
 }
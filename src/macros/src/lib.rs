@@ -0,0 +1,116 @@
+// `#[lim_native]`, see the `lim-native` crate.
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Attribute, Error, Expr, FnArg, ItemFn, Lit, Meta, Pat, Type};
+
+struct Parameter {
+    name: syn::Ident,
+    ty: Type,
+    rest: bool,
+}
+
+// The `///` comments of the function, as `rustdoc` joins them.
+fn doc(attributes: &[Attribute]) -> String {
+    let mut lines = vec![];
+    for attribute in attributes.iter().filter(|attribute| attribute.path().is_ident("doc")) {
+        if let Meta::NameValue(meta) = &attribute.meta {
+            if let Expr::Lit(expression) = &meta.value {
+                if let Lit::Str(line) = &expression.lit {
+                    let line = line.value();
+                    lines.push(line.strip_prefix(' ').unwrap_or(&line).to_owned());
+                }
+            }
+        }
+    }
+    lines.join("\n").trim().to_owned()
+}
+
+// Typed parameters bound to plain names, `#[rest]` collects the remaining arguments into the last.
+fn parameters(function: &mut ItemFn) -> Result<Vec<Parameter>, Error> {
+    let count = function.sig.inputs.len();
+    let mut parameters = vec![];
+    for (index, input) in function.sig.inputs.iter_mut().enumerate() {
+        let input = match input {
+            FnArg::Typed(input) => input,
+            FnArg::Receiver(receiver) => return Err(Error::new_spanned(receiver, "a lim native cannot take self")),
+        };
+        let name = match &*input.pat {
+            Pat::Ident(pattern) => pattern.ident.to_owned(),
+            pattern => return Err(Error::new_spanned(pattern, "a lim native parameter must be a name")),
+        };
+        let rest = input.attrs.iter().any(|attribute| attribute.path().is_ident("rest"));
+        input.attrs.retain(|attribute| !attribute.path().is_ident("rest"));
+        if rest && index + 1 != count {
+            return Err(Error::new_spanned(&input.pat, "only the last parameter can be #[rest]"));
+        }
+        parameters.push(Parameter { name, ty: (*input.ty).to_owned(), rest });
+    }
+    Ok(parameters)
+}
+
+fn expand(mut function: ItemFn) -> Result<proc_macro2::TokenStream, Error> {
+    let signature = &function.sig;
+    if !signature.generics.params.is_empty() {
+        return Err(Error::new_spanned(&signature.generics, "a lim native cannot be generic"));
+    }
+    if let Some(asyncness) = signature.asyncness {
+        return Err(Error::new_spanned(asyncness, "a lim native cannot be async"));
+    }
+    let parameters = parameters(&mut function)?;
+    let ident = &function.sig.ident;
+    let visibility = &function.vis;
+    let name = ident.to_string();
+    let wrapper = format_ident!("__lim_native_{}", ident);
+    let info = format_ident!("lim_native_{}", ident);
+    let variadic = parameters.last().is_some_and(|parameter| parameter.rest);
+    let arity = parameters.len() - variadic as usize;
+    let names: Vec<&syn::Ident> = parameters.iter().map(|parameter| &parameter.name).collect();
+    let conversions = parameters.iter().enumerate().map(|(index, Parameter { name: binding, ty, rest })| {
+        if *rest {
+            quote! { let #binding: #ty = ::lim_native::__private::rest(__arguments, #index, #name)?; }
+        } else {
+            quote! { let #binding: #ty = ::lim_native::__private::argument(__arguments, #index, #name)?; }
+        }
+    });
+    let name_literal = syn::LitStr::new(&format!("{}\0", name), Span::call_site());
+    let doc_literal = syn::LitStr::new(&format!("{}\0", doc(&function.attrs)), Span::call_site());
+    Ok(quote! {
+        #function
+
+        #[doc(hidden)]
+        #[allow(non_snake_case)]
+        #visibility unsafe extern "C" fn #wrapper(
+            api: *const ::lim_native::abi::LimApi,
+            args: *const *const ::lim_native::abi::LimValue,
+            count: usize,
+        ) -> *mut ::lim_native::abi::LimValue {
+            ::lim_native::__private::call(api, args, count, #name, #arity, #variadic, |__arguments| {
+                #(#conversions)*
+                Ok(::lim_native::IntoLim::into_lim(#ident(#(#names),*), __arguments.0))
+            })
+        }
+
+        #[no_mangle]
+        #[allow(non_upper_case_globals)]
+        #visibility static #info: ::lim_native::abi::LimNativeInfo = ::lim_native::abi::LimNativeInfo {
+            name: #name_literal.as_ptr() as *const ::std::os::raw::c_char,
+            doc: #doc_literal.as_ptr() as *const ::std::os::raw::c_char,
+            arity: #arity,
+            variadic: #variadic,
+            function: #wrapper,
+        };
+    })
+}
+
+// Declares a native with a typed signature. The function stays callable from Rust, the macro adds
+// a `LimNative` checking and converting the arguments and its `LimNativeInfo`, exported as
+// `lim_native_<name>` with the name, arity and `///` documentation of the function.
+#[proc_macro_attribute]
+pub fn lim_native(attribute: TokenStream, item: TokenStream) -> TokenStream {
+    if !attribute.is_empty() {
+        return Error::new(Span::call_site(), "#[lim_native] takes no arguments").to_compile_error().into();
+    }
+    let function = parse_macro_input!(item as ItemFn);
+    expand(function).unwrap_or_else(|error| error.to_compile_error()).into()
+}
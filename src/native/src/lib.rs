@@ -0,0 +1,291 @@
+// Typed natives over the C ABI of `lim-abi`. `#[lim_native]` turns a Rust function into a
+// `LimNative` described by a `LimNativeInfo`, the same native is linked into the interpreter or
+// loaded from a shared library.
+//
+//     /// Adds an integer to a float.
+//     #[lim_native]
+//     pub fn add(a: i64, b: f64) -> Result<f64, LimError> {
+//         Ok(a as f64 + b)
+//     }
+use std::fmt;
+use std::os::raw::c_char;
+use std::slice;
+
+pub use lim_abi as abi;
+pub use lim_macros::lim_native;
+
+use lim_abi::{LimApi, LimValue, LIM_ARRAY, LIM_ERROR, LIM_FLOAT, LIM_INTEGER, LIM_NONE, LIM_STRING};
+
+// The error a native fails with, lim code sees it as an error value with this message.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LimError(pub String);
+
+impl LimError {
+    pub fn new(message: impl Into<String>) -> Self {
+        LimError(message.into())
+    }
+}
+
+impl fmt::Display for LimError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str(&self.0)
+    }
+}
+
+impl From<String> for LimError {
+    fn from(message: String) -> Self {
+        LimError(message)
+    }
+}
+
+impl From<&str> for LimError {
+    fn from(message: &str) -> Self {
+        LimError(message.to_owned())
+    }
+}
+
+// A value lent to a native, valid until the native returns.
+#[derive(Clone, Copy)]
+pub struct Value<'a> {
+    api: &'a LimApi,
+    handle: *const LimValue,
+}
+
+impl<'a> Value<'a> {
+    pub fn kind(&self) -> u32 {
+        unsafe { (self.api.kind)(self.handle) }
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self.kind() {
+            LIM_NONE => "None",
+            LIM_INTEGER => "Integer",
+            LIM_FLOAT => "Float",
+            LIM_STRING => "String",
+            LIM_ARRAY => "Array",
+            LIM_ERROR => "Error",
+            _ => "Object",
+        }
+    }
+
+    // The string lim would print.
+    pub fn display(&self) -> String {
+        unsafe {
+            let string = (self.api.display)(self.handle);
+            let text = text(self.api.as_string, string);
+            (self.api.release)(string);
+            text.unwrap_or_default()
+        }
+    }
+
+    pub fn handle(&self) -> *const LimValue {
+        self.handle
+    }
+
+    fn expected(&self, name: &str) -> String {
+        format!("expected {}, got {}", name, self.type_name())
+    }
+}
+
+unsafe fn text(read: unsafe extern "C" fn(*const LimValue, *mut usize) -> *const c_char, value: *const LimValue) -> Option<String> {
+    let mut length = 0;
+    let bytes = read(value, &mut length);
+    if bytes.is_null() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(slice::from_raw_parts(bytes as *const u8, length)).into_owned())
+}
+
+// Reads an argument, the error names what was expected.
+pub trait FromLim<'a>: Sized {
+    fn from_lim(value: Value<'a>) -> Result<Self, String>;
+}
+
+// Builds the value a native returns, owned by the caller.
+pub trait IntoLim {
+    fn into_lim(self, api: &LimApi) -> *mut LimValue;
+}
+
+impl<'a> FromLim<'a> for Value<'a> {
+    fn from_lim(value: Value<'a>) -> Result<Self, String> {
+        Ok(value)
+    }
+}
+
+impl<'a> FromLim<'a> for i64 {
+    fn from_lim(value: Value<'a>) -> Result<Self, String> {
+        let mut integer = 0;
+        match unsafe { (value.api.as_integer)(value.handle, &mut integer) } {
+            0 => Err(value.expected("Integer")),
+            _ => Ok(integer),
+        }
+    }
+}
+
+// Integers widen to floats the way arithmetic mixing them does.
+impl<'a> FromLim<'a> for f64 {
+    fn from_lim(value: Value<'a>) -> Result<Self, String> {
+        let mut float = 0.0;
+        match unsafe { (value.api.as_float)(value.handle, &mut float) } {
+            0 => Err(value.expected("Float")),
+            _ => Ok(float),
+        }
+    }
+}
+
+impl<'a> FromLim<'a> for String {
+    fn from_lim(value: Value<'a>) -> Result<Self, String> {
+        unsafe { text(value.api.as_string, value.handle) }.ok_or_else(|| value.expected("String"))
+    }
+}
+
+impl<'a, T: FromLim<'a>> FromLim<'a> for Option<T> {
+    fn from_lim(value: Value<'a>) -> Result<Self, String> {
+        match value.kind() {
+            LIM_NONE => Ok(None),
+            _ => T::from_lim(value).map(Some),
+        }
+    }
+}
+
+impl<'a, T: FromLim<'a>> FromLim<'a> for Vec<T> {
+    fn from_lim(value: Value<'a>) -> Result<Self, String> {
+        if value.kind() != LIM_ARRAY {
+            return Err(value.expected("Array"));
+        }
+        let api = value.api;
+        let length = unsafe { (api.array_length)(value.handle) };
+        let mut elements = Vec::with_capacity(length);
+        for index in 0..length {
+            let element = unsafe { (api.array_get)(value.handle, index) };
+            // The element is kept alive by the array, which outlives the call.
+            let converted = T::from_lim(Value { api, handle: element });
+            unsafe { (api.release)(element) };
+            elements.push(converted.map_err(|error| format!("{} at index {}", error, index))?);
+        }
+        Ok(elements)
+    }
+}
+
+impl IntoLim for () {
+    fn into_lim(self, api: &LimApi) -> *mut LimValue {
+        unsafe { (api.none)() }
+    }
+}
+
+impl IntoLim for i64 {
+    fn into_lim(self, api: &LimApi) -> *mut LimValue {
+        unsafe { (api.integer)(self) }
+    }
+}
+
+impl IntoLim for f64 {
+    fn into_lim(self, api: &LimApi) -> *mut LimValue {
+        unsafe { (api.float)(self) }
+    }
+}
+
+// Lim has no booleans, 0 is falsy and 1 truthy.
+impl IntoLim for bool {
+    fn into_lim(self, api: &LimApi) -> *mut LimValue {
+        unsafe { (api.integer)(self as i64) }
+    }
+}
+
+impl IntoLim for &str {
+    fn into_lim(self, api: &LimApi) -> *mut LimValue {
+        unsafe { (api.string)(self.as_ptr() as *const c_char, self.len()) }
+    }
+}
+
+impl IntoLim for String {
+    fn into_lim(self, api: &LimApi) -> *mut LimValue {
+        self.as_str().into_lim(api)
+    }
+}
+
+impl<T: IntoLim> IntoLim for Option<T> {
+    fn into_lim(self, api: &LimApi) -> *mut LimValue {
+        match self {
+            Some(value) => value.into_lim(api),
+            None => ().into_lim(api),
+        }
+    }
+}
+
+impl<T: IntoLim> IntoLim for Vec<T> {
+    fn into_lim(self, api: &LimApi) -> *mut LimValue {
+        let elements: Vec<*mut LimValue> = self.into_iter().map(|element| element.into_lim(api)).collect();
+        unsafe {
+            let array = (api.array)(elements.as_ptr() as *const *const LimValue, elements.len());
+            elements.into_iter().for_each(|element| (api.release)(element));
+            array
+        }
+    }
+}
+
+impl<T: IntoLim, E: fmt::Display> IntoLim for Result<T, E> {
+    fn into_lim(self, api: &LimApi) -> *mut LimValue {
+        match self {
+            Ok(value) => value.into_lim(api),
+            Err(error) => error_value(api, &error.to_string()),
+        }
+    }
+}
+
+fn error_value(api: &LimApi, message: &str) -> *mut LimValue {
+    unsafe { (api.error)(message.as_ptr() as *const c_char, message.len()) }
+}
+
+// Used by the code `#[lim_native]` generates.
+#[doc(hidden)]
+pub mod __private {
+    use std::panic::{self, AssertUnwindSafe};
+    use std::slice;
+
+    use super::{error_value, FromLim, Value};
+    use lim_abi::{LimApi, LimValue};
+
+    pub type Arguments<'a> = (&'a LimApi, &'a [*const LimValue]);
+
+    // Checks the argument count and turns errors and panics, which must not unwind into the
+    // interpreter, into error values.
+    pub unsafe fn call(
+        api: *const LimApi,
+        args: *const *const LimValue,
+        count: usize,
+        name: &str,
+        arity: usize,
+        variadic: bool,
+        body: impl FnOnce(Arguments) -> Result<*mut LimValue, String>,
+    ) -> *mut LimValue {
+        let api = &*api;
+        let args = if args.is_null() { &[] } else { slice::from_raw_parts(args, count) };
+        if count < arity || (!variadic && count > arity) {
+            let expected = if variadic { format!("at least {}", arity) } else { arity.to_string() };
+            let noun = if arity == 1 { "argument" } else { "arguments" };
+            return error_value(api, &format!("{} expects {} {}, got {}", name, expected, noun, count));
+        }
+        match panic::catch_unwind(AssertUnwindSafe(|| body((api, args)))) {
+            Ok(Ok(value)) => value,
+            Ok(Err(message)) => error_value(api, &message),
+            Err(payload) => {
+                let reason = payload
+                    .downcast_ref::<&str>()
+                    .map(|reason| reason.to_string())
+                    .or_else(|| payload.downcast_ref::<String>().cloned())
+                    .unwrap_or_default();
+                error_value(api, &format!("{} panicked: {}", name, reason))
+            }
+        }
+    }
+
+    pub fn argument<'a, T: FromLim<'a>>((api, args): Arguments<'a>, index: usize, name: &str) -> Result<T, String> {
+        T::from_lim(Value { api, handle: args[index] }).map_err(|error| format!("Argument {} of {}: {}", index + 1, name, error))
+    }
+
+    // The arguments from `start` on, for a variadic last parameter.
+    pub fn rest<'a, T: FromLim<'a>>((api, args): Arguments<'a>, start: usize, name: &str) -> Result<Vec<T>, String> {
+        (start..args.len()).map(|index| argument((api, args), index, name)).collect()
+    }
+}
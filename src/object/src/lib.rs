@@ -1,3 +1,5 @@
 mod object;
+mod table;
 
 pub use object::{Object};
+pub use table::{Table};
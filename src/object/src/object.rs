@@ -1,3 +1,4 @@
+use crate::table::Table;
 use parser::node::Node;
 use std::collections::HashMap;
 use std::rc::Rc;
@@ -11,12 +12,22 @@ pub enum Object {
     Error(String),
     String(String),
     Native(String, String),
+    // A native linked into the interpreter, by name.
+    Linked(String),
+    Builtin(String),
+    // A Rust function an embedding `Interpreter` registered, by name and index.
+    Host(String, usize),
     Enum(String, Vec<Node>),
-    EnumVariant(String),
+    // A variant by its qualified name, `Shape.Circle`, with the values it was called with.
+    EnumVariant(String, Vec<Rc<Object>>),
     Function(Vec<Node>, Vec<Node>),
     BoundFunction(Rc<Object>, Rc<Object>),
     Class(HashMap<String, Rc<Object>>),
     Instance(HashMap<String, Rc<Object>>),
+    Array(Vec<Rc<Object>>),
+    Map(Table),
+    Set(Table),
+    Module(String, HashMap<String, Rc<Object>>),
 }
 
 impl Object {
@@ -26,8 +37,14 @@ impl Object {
             Object::String(string) => format!("{}", string),
             Object::Float(number) => format!("{}", number),
             Object::Enum(name, variations) => format!("enum {:?}, variations {:?}", name, variations),
+            Object::EnumVariant(name, payload) if payload.is_empty() => name.to_owned(),
+            Object::EnumVariant(name, payload) => format!("{}({})", name, payload.iter().map(|value| value.to_string()).collect::<Vec<String>>().join(", ")),
             Object::None => String::from("None"),
+            Object::Array(elements) => format!("[{}]", elements.iter().map(|element| element.to_string()).collect::<Vec<String>>().join(", ")),
+            Object::Map(table) => format!("{{{}}}", table.iter().map(|(key, value)| format!("{}: {}", key.to_string(), value.to_string())).collect::<Vec<String>>().join(", ")),
+            Object::Set(table) => format!("set[{}]", table.keys().map(|key| key.to_string()).collect::<Vec<String>>().join(", ")),
             Object::Function(_args, _body) => format!("{:?}", self),
+            Object::Module(name, _fields) => format!("module {}", name),
             _ => format!("{:?}", self),
         }
     }
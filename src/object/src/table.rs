@@ -0,0 +1,93 @@
+use crate::object::Object;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+#[derive(Debug, Clone, Default)]
+pub struct Table {
+    entries: Vec<(u64, Rc<Object>, Rc<Object>)>,
+    buckets: HashMap<u64, Vec<usize>>,
+}
+
+impl Table {
+    pub fn new() -> Table {
+        Table::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn find(&self, hash: u64, eq: &mut dyn FnMut(&Rc<Object>) -> bool) -> Option<usize> {
+        self.buckets.get(&hash)?.iter().find(|index| eq(&self.entries[**index].1)).copied()
+    }
+
+    pub fn get(&self, hash: u64, eq: &mut dyn FnMut(&Rc<Object>) -> bool) -> Option<Rc<Object>> {
+        self.find(hash, eq).map(|index| self.entries[index].2.to_owned())
+    }
+
+    pub fn insert(&mut self, hash: u64, key: Rc<Object>, value: Rc<Object>, eq: &mut dyn FnMut(&Rc<Object>) -> bool) {
+        match self.find(hash, eq) {
+            Some(index) => self.entries[index].2 = value,
+            None => self.push(hash, key, value),
+        }
+    }
+
+    pub fn remove(&mut self, hash: u64, eq: &mut dyn FnMut(&Rc<Object>) -> bool) -> Option<Rc<Object>> {
+        let index = self.find(hash, eq)?;
+        Some(self.remove_at(index))
+    }
+
+    // The keys sharing a hash with their index, copied so that comparing them can run code changing
+    // the table.
+    pub fn candidates(&self, hash: u64) -> Vec<(usize, Rc<Object>)> {
+        self.buckets.get(&hash).map_or(vec![], |bucket| bucket.iter().map(|index| (*index, self.entries[*index].1.to_owned())).collect())
+    }
+
+    // Where a key object is stored, by identity.
+    pub fn position(&self, key: &Rc<Object>) -> Option<usize> {
+        self.entries.iter().position(|(_, other, _)| Rc::ptr_eq(key, other))
+    }
+
+    pub fn key_at(&self, index: usize) -> Option<&Rc<Object>> {
+        self.entries.get(index).map(|(_, key, _)| key)
+    }
+
+    pub fn value_at(&self, index: usize) -> Rc<Object> {
+        self.entries[index].2.to_owned()
+    }
+
+    pub fn set_at(&mut self, index: usize, value: Rc<Object>) {
+        self.entries[index].2 = value;
+    }
+
+    // Adds an entry whose key is known not to be in the table.
+    pub fn push(&mut self, hash: u64, key: Rc<Object>, value: Rc<Object>) {
+        self.buckets.entry(hash).or_default().push(self.entries.len());
+        self.entries.push((hash, key, value));
+    }
+
+    pub fn remove_at(&mut self, index: usize) -> Rc<Object> {
+        let (_, _, value) = self.entries.remove(index);
+        self.buckets.clear();
+        for (index, (hash, _, _)) in self.entries.iter().enumerate() {
+            self.buckets.entry(*hash).or_default().push(index);
+        }
+        value
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&Rc<Object>, &Rc<Object>)> {
+        self.entries.iter().map(|(_, key, value)| (key, value))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &Rc<Object>> {
+        self.entries.iter().map(|(_, key, _)| key)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &Rc<Object>> {
+        self.entries.iter().map(|(_, _, value)| value)
+    }
+}
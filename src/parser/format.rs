@@ -0,0 +1,423 @@
+use lexer::lexer::lex_with_comments;
+use lexer::span::Span;
+use lexer::token::Token;
+
+use crate::node::Node;
+use crate::parser::parse_spanned;
+
+pub const INDENT: &str = "  ";
+pub const MAX_WIDTH: usize = 100;
+
+// A comment is anchored to the number of significant tokens before it, the printer writes the same
+// significant tokens in the same order so it can put every comment back after the same token.
+#[derive(Debug, PartialEq)]
+pub struct Comment {
+    pub anchor: usize,
+    pub text: String,
+    pub own_line: bool,
+}
+
+fn is_significant(token: &Token) -> bool {
+    !matches!(token, Token::Comma | Token::NewLine | Token::SemiColon | Token::Comment(_))
+}
+
+pub fn comments(tokens: &[(Token, Span)]) -> Vec<Comment> {
+    let mut comments = vec![];
+    let mut anchor = 0;
+    let mut line_start = true;
+    for (token, _span) in tokens.iter() {
+        match token {
+            Token::Comment(text) => comments.push(Comment { anchor, text: String::from(text.trim_end()), own_line: line_start }),
+            Token::NewLine => line_start = true,
+            token => {
+                if is_significant(token) {
+                    anchor += 1;
+                }
+                line_start = false;
+            }
+        }
+    }
+    comments
+}
+
+fn escape(string: &str, delimiter: char, template: bool) -> String {
+    let mut escaped = String::new();
+    for character in string.chars() {
+        match character {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            '\r' => escaped.push_str("\\r"),
+            '\0' => escaped.push_str("\\0"),
+            '{' if template => escaped.push_str("{{"),
+            '}' if template => escaped.push_str("}}"),
+            character if character == delimiter => {
+                escaped.push('\\');
+                escaped.push(character);
+            }
+            character => escaped.push(character),
+        }
+    }
+    escaped
+}
+
+struct Printer<'a> {
+    output: String,
+    indent: usize,
+    count: usize,
+    comments: &'a [Comment],
+    next: usize,
+    trailing: Vec<String>,
+    at_line_start: bool,
+    flat: bool,
+}
+
+impl<'a> Printer<'a> {
+    fn new(comments: &'a [Comment], flat: bool) -> Printer<'a> {
+        Printer { output: String::new(), indent: 0, count: 0, comments, next: 0, trailing: vec![], at_line_start: true, flat }
+    }
+
+    fn column(&self) -> usize {
+        self.output.len() - self.output.rfind('\n').map_or(0, |newline| newline + 1)
+    }
+
+    fn write_indent(&mut self) {
+        for _ in 0..self.indent {
+            self.output.push_str(INDENT);
+        }
+    }
+
+    // Comments on their own line wait for the start of a line, the others for its end.
+    fn place_comments(&mut self) {
+        while let Some(comment) = self.comments.get(self.next) {
+            if comment.anchor > self.count {
+                break;
+            }
+            if self.at_line_start {
+                self.write_indent();
+                self.output.push('#');
+                self.output.push_str(&comment.text);
+                self.output.push('\n');
+            } else if comment.own_line {
+                break;
+            } else {
+                self.trailing.push(comment.text.to_owned());
+            }
+            self.next += 1;
+        }
+    }
+
+    fn word(&mut self, text: &str) {
+        if self.at_line_start {
+            self.place_comments();
+            self.write_indent();
+            self.at_line_start = false;
+        }
+        self.output.push_str(text);
+        self.count += 1;
+        self.place_comments();
+    }
+
+    fn punctuation(&mut self, text: &str) {
+        self.output.push_str(text);
+    }
+
+    fn space(&mut self) {
+        if !self.at_line_start {
+            self.output.push(' ');
+        }
+    }
+
+    fn newline(&mut self) {
+        let mut trailing = self.trailing.drain(..);
+        if let Some(comment) = trailing.next() {
+            self.output.push_str("  #");
+            self.output.push_str(&comment);
+        }
+        let rest: Vec<String> = trailing.collect();
+        self.output.push('\n');
+        self.at_line_start = true;
+        for comment in rest {
+            self.write_indent();
+            self.output.push('#');
+            self.output.push_str(&comment);
+            self.output.push('\n');
+        }
+    }
+
+    fn blank_line(&mut self) {
+        if !self.output.is_empty() && !self.output.ends_with("\n\n") {
+            self.output.push('\n');
+        }
+    }
+
+    fn finish(mut self) -> String {
+        if !self.at_line_start {
+            self.newline();
+        }
+        self.count = usize::MAX;
+        self.place_comments();
+        self.output
+    }
+
+    fn inline(node: &Node) -> String {
+        Printer::measure(node).0
+    }
+
+    // Renders a node on one line and counts its significant tokens.
+    fn measure(node: &Node) -> (String, usize) {
+        let mut printer = Printer::new(&[], true);
+        printer.node(node);
+        (printer.output, printer.count)
+    }
+
+    // A comment among the next `tokens` tokens would move out of a construct printed on one line.
+    fn comment_within(&self, tokens: usize) -> bool {
+        self.comments[self.next..].iter().any(|comment| comment.anchor < self.count + tokens)
+    }
+
+    fn fits(&self, width: usize) -> bool {
+        self.flat || self.column() + width <= MAX_WIDTH
+    }
+
+    // Elements stay on one line when they fit, otherwise they go one per line with a trailing comma.
+    fn list(&mut self, open: &str, elements: &[Node], close: &str) {
+        let elements: Vec<&Node> = elements.iter().filter(|element| **element != Node::Empty).collect();
+        self.word(open);
+        let inline: Vec<(String, usize)> = elements.iter().map(|element| Printer::measure(element)).collect();
+        let width = inline.iter().map(|(element, _count)| element.len() + 2).sum::<usize>() + close.len();
+        let tokens = inline.iter().map(|(_element, count)| count).sum::<usize>();
+        let multiline = inline.iter().any(|(element, _count)| element.contains('\n'));
+        if elements.is_empty() || (self.fits(width) && !multiline && !self.comment_within(tokens)) {
+            for (index, element) in elements.iter().enumerate() {
+                if index > 0 {
+                    self.punctuation(", ");
+                }
+                self.node(element);
+            }
+        } else {
+            self.newline();
+            self.indent += 1;
+            for element in elements.iter() {
+                self.node(element);
+                self.punctuation(",");
+                self.newline();
+            }
+            self.indent -= 1;
+        }
+        self.word(close);
+    }
+
+    fn statements(&mut self, statements: &[Node]) {
+        for statement in statements.iter().filter(|statement| **statement != Node::Empty) {
+            self.node(statement);
+            self.newline();
+        }
+    }
+
+    fn block(&mut self, statements: &[Node], inline: bool) {
+        let statements: Vec<Node> = statements.iter().filter(|statement| **statement != Node::Empty).cloned().collect();
+        if statements.is_empty() {
+            self.word("{");
+            self.word("}");
+            return;
+        }
+        if inline && statements.len() == 1 {
+            let (statement, tokens) = Printer::measure(&statements[0]);
+            if !statement.contains('\n') && self.fits(statement.len() + 4) && !self.comment_within(tokens + 1) {
+                self.word("{");
+                self.space();
+                self.node(&statements[0]);
+                self.space();
+                self.word("}");
+                return;
+            }
+        }
+        self.word("{");
+        self.newline();
+        self.indent += 1;
+        self.statements(&statements);
+        self.indent -= 1;
+        self.word("}");
+    }
+
+    fn program(node: &Node) -> &[Node] {
+        match node {
+            Node::Program(statements) => statements,
+            _ => std::slice::from_ref(node),
+        }
+    }
+
+    fn binary(&mut self, left: &Node, operator: &str, right: &Node) {
+        self.node(left);
+        self.space();
+        self.word(operator);
+        self.space();
+        self.node(right);
+    }
+
+    fn template(parts: &[Node]) -> String {
+        let delimiter = match parts.first() {
+            Some(Node::Literal(_, delimiter)) => *delimiter,
+            _ => '\'',
+        };
+        let mut text = String::new();
+        text.push(delimiter);
+        for part in parts.iter() {
+            match part {
+                Node::Literal(string, _) => text.push_str(&escape(string, delimiter, true)),
+                expression => {
+                    text.push('{');
+                    text.push_str(&Printer::inline(expression));
+                    text.push('}');
+                }
+            }
+        }
+        text.push(delimiter);
+        text
+    }
+
+    fn node(&mut self, node: &Node) {
+        match node {
+            Node::Program(statements) => self.statements(statements),
+            Node::Assignment(target, value) => self.binary(target, "=", value),
+            Node::CompoundAssignment(target, operator, value) => self.binary(target, &format!("{}=", operator), value),
+            Node::Addition(left, right) => self.binary(left, "+", right),
+            Node::Substraction(left, right) => self.binary(left, "-", right),
+            Node::Multiplication(left, right) => self.binary(left, "*", right),
+            Node::Division(left, right) => self.binary(left, "/", right),
+            Node::Modulus(left, right) => self.binary(left, "%", right),
+            Node::Negation(operand) => {
+                self.word("-");
+                self.node(operand);
+            }
+            Node::Variadic(expression) => {
+                self.word("*");
+                self.node(expression);
+            }
+            Node::Number(number) => self.word(number),
+            Node::Identifier(name) => self.word(name),
+            Node::Literal(string, delimiter) => self.word(&format!("{}{}{}", delimiter, escape(string, *delimiter, false), delimiter)),
+            Node::Prefixed(prefix, literal) => {
+                self.node(prefix);
+                match &**literal {
+                    Node::Template(parts) => self.word(&Printer::template(parts)),
+                    literal => self.node(literal),
+                }
+            }
+            Node::Template(parts) => self.word(&Printer::template(parts)),
+            Node::Parenthesized(expression) => {
+                self.word("(");
+                self.node(expression);
+                self.word(")");
+            }
+            Node::Call(callee, arguments) => {
+                self.node(callee);
+                self.list("(", arguments, ")");
+            }
+            Node::Member(object, name) => {
+                self.node(object);
+                self.word(".");
+                self.word(name);
+            }
+            Node::Index(object, index) => {
+                self.node(object);
+                match &**index {
+                    Node::FunctionArgs(elements) => self.list("[", elements, "]"),
+                    index => {
+                        self.word("[");
+                        self.node(index);
+                        self.word("]");
+                    }
+                }
+            }
+            Node::Slice(start, end) => {
+                self.node(start);
+                self.word(":");
+                self.node(end);
+            }
+            Node::FunctionArgs(elements) | Node::Tuple(elements) => {
+                for (index, element) in elements.iter().enumerate() {
+                    if index > 0 {
+                        self.punctuation(", ");
+                    }
+                    self.node(element);
+                }
+            }
+            Node::Array(elements) => self.list("[", elements, "]"),
+            Node::Map(entries) | Node::Set(entries) => self.list("{", entries, "}"),
+            Node::KeyValue(key, value) => {
+                self.node(key);
+                self.word(":");
+                self.space();
+                self.node(value);
+            }
+            Node::FunctionDefinition(parameters, body) => {
+                self.list("(", parameters, ")");
+                self.space();
+                self.block(body, true);
+            }
+            Node::EnumDefinition(name, variants) => {
+                self.word("enum");
+                self.space();
+                self.node(name);
+                self.space();
+                self.block(variants, false);
+            }
+            Node::ClassDefinition(name, body) => {
+                self.word("class");
+                self.space();
+                self.node(name);
+                self.space();
+                self.block(Printer::program(body), false);
+            }
+            Node::Match(subject, arms) => {
+                self.node(subject);
+                self.word(":");
+                self.space();
+                self.block(arms, false);
+            }
+            Node::MatchArm(pattern, body) => {
+                self.node(pattern);
+                self.word(":");
+                self.space();
+                self.block(Printer::program(body), true);
+            }
+            _ => {}
+        }
+    }
+}
+
+// Formats a tree without comments, None when the tree holds a statement that is not finished.
+pub fn format_node(node: &Node) -> Option<String> {
+    if Printer::program(node).iter().any(|statement| statement.is_incomplete()) {
+        return None;
+    }
+    let mut printer = Printer::new(&[], false);
+    printer.node(node);
+    Some(printer.finish())
+}
+
+// Formats source code, keeping comments and single blank lines between top-level statements.
+pub fn format(code: &str) -> Option<String> {
+    let tokens = lex_with_comments(code)?;
+    let comments = comments(&tokens);
+    let statements = parse_spanned(tokens.into_iter().filter(|(token, _span)| !matches!(token, Token::Comment(_))).collect())?;
+    let statements: Vec<(Node, Span)> = statements.into_iter().filter(|(statement, _span)| *statement != Node::Empty).collect();
+    if statements.iter().any(|(statement, _span)| statement.is_incomplete()) {
+        return None;
+    }
+    let mut printer = Printer::new(&comments, false);
+    let mut previous: Option<Span> = None;
+    for (statement, span) in statements.iter() {
+        if let Some(previous) = previous {
+            if span.start.line > previous.end.line + 1 {
+                printer.blank_line();
+            }
+        }
+        printer.node(statement);
+        printer.newline();
+        previous = Some(*span);
+    }
+    Some(printer.finish())
+}
@@ -1,3 +1,4 @@
+pub mod format;
 pub mod node;
 pub mod parser;
 pub mod tests;
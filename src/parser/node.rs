@@ -1,3 +1,5 @@
+use crate::parser::parse;
+use lexer::lexer::lex;
 use lexer::token::Token;
 
 #[derive(Clone, Debug, PartialEq)]
@@ -36,10 +38,83 @@ pub enum Node {
     PartialMatchArm(Box<Node>, Box<Node>),
     MatchArm(Box<Node>, Box<Node>),
     Match(Box<Node>, Vec<Node>),
+    PartialArray(Vec<Node>),
+    Array(Vec<Node>),
+    PartialIndex(Box<Node>, Box<Node>),
+    Index(Box<Node>, Box<Node>),
+    Slice(Box<Node>, Box<Node>),
+    Negation(Box<Node>),
+    Variadic(Box<Node>),
+    Tuple(Vec<Node>),
+    PartialMap(Vec<Node>),
+    KeyValue(Box<Node>, Box<Node>),
+    Map(Vec<Node>),
+    Set(Vec<Node>),
+    PartialPrefixed(Box<Node>),
+    Prefixed(Box<Node>, Box<Node>),
+    Template(Vec<Node>),
+    CompoundAssignment(Box<Node>, String, Box<Node>),
     Empty,
 }
 
 impl Node {
+    // The name of the variant, for messages about a node whatever its content.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Node::Program(_) => "Program",
+            Node::Assignment(_, _) => "Assignment",
+            Node::Addition(_, _) => "Addition",
+            Node::Substraction(_, _) => "Substraction",
+            Node::Multiplication(_, _) => "Multiplication",
+            Node::Division(_, _) => "Division",
+            Node::Modulus(_, _) => "Modulus",
+            Node::Number(_) => "Number",
+            Node::Identifier(_) => "Identifier",
+            Node::Literal(_, _) => "Literal",
+            Node::Call(_, _) => "Call",
+            Node::PartialCall(_, _) => "PartialCall",
+            Node::Parenthesized(_) => "Parenthesized",
+            Node::Member(_, _) => "Member",
+            Node::PartialMember(_) => "PartialMember",
+            Node::PartialParenthesized(_) => "PartialParenthesized",
+            Node::FunctionArgs(_) => "FunctionArgs",
+            Node::PartialFunctionDefinition(_, _) => "PartialFunctionDefinition",
+            Node::FunctionDefinition(_, _) => "FunctionDefinition",
+            Node::UnnamedEnumDefinition => "UnnamedEnumDefinition",
+            Node::NamedEnumDefinition(_) => "NamedEnumDefinition",
+            Node::PartialEnumDefinition(_, _) => "PartialEnumDefinition",
+            Node::EnumDefinition(_, _) => "EnumDefinition",
+            Node::UnnamedClassDefinition => "UnnamedClassDefinition",
+            Node::NamedClassDefinition(_) => "NamedClassDefinition",
+            Node::PartialClassDefinition(_, _) => "PartialClassDefinition",
+            Node::ClassDefinition(_, _) => "ClassDefinition",
+            Node::PartialMatch(_, _) => "PartialMatch",
+            Node::UnopenedMatch(_) => "UnopenedMatch",
+            Node::UnopenedMatchArm(_) => "UnopenedMatchArm",
+            Node::MatchArmWithoutColon(_) => "MatchArmWithoutColon",
+            Node::PartialMatchArm(_, _) => "PartialMatchArm",
+            Node::MatchArm(_, _) => "MatchArm",
+            Node::Match(_, _) => "Match",
+            Node::PartialArray(_) => "PartialArray",
+            Node::Array(_) => "Array",
+            Node::PartialIndex(_, _) => "PartialIndex",
+            Node::Index(_, _) => "Index",
+            Node::Slice(_, _) => "Slice",
+            Node::Negation(_) => "Negation",
+            Node::Variadic(_) => "Variadic",
+            Node::Tuple(_) => "Tuple",
+            Node::PartialMap(_) => "PartialMap",
+            Node::KeyValue(_, _) => "KeyValue",
+            Node::Map(_) => "Map",
+            Node::Set(_) => "Set",
+            Node::PartialPrefixed(_) => "PartialPrefixed",
+            Node::Prefixed(_, _) => "Prefixed",
+            Node::Template(_) => "Template",
+            Node::CompoundAssignment(_, _, _) => "CompoundAssignment",
+            Node::Empty => "Empty",
+        }
+    }
+
     pub fn is_a(&self, node: Node) -> bool {
         match self {
             node => true,
@@ -47,6 +122,126 @@ impl Node {
         }
     }
 
+    pub fn is_open(&self) -> bool {
+        match self {
+            Node::PartialCall(_, _)
+            | Node::PartialParenthesized(_)
+            | Node::PartialFunctionDefinition(_, _)
+            | Node::PartialMatch(_, _)
+            | Node::PartialClassDefinition(_, _)
+            | Node::PartialEnumDefinition(_, _)
+            | Node::PartialArray(_)
+            | Node::PartialIndex(_, _)
+            | Node::PartialMap(_)
+            | Node::PartialPrefixed(_) => true,
+            Node::FunctionArgs(args) => args.last().unwrap().is_open(),
+            Node::Tuple(elements) => elements.last().is_some_and(|last| *last == Node::Empty || last.is_open()),
+            Node::Addition(_, rhs)
+            | Node::Substraction(_, rhs)
+            | Node::Multiplication(_, rhs)
+            | Node::Division(_, rhs)
+            | Node::Modulus(_, rhs)
+            | Node::Assignment(_, rhs)
+            | Node::CompoundAssignment(_, _, rhs)
+            | Node::Slice(_, rhs)
+            | Node::Negation(rhs)
+            | Node::Variadic(rhs)
+            | Node::KeyValue(_, rhs)
+            | Node::Prefixed(_, rhs) => rhs.is_open(),
+            _ => false,
+        }
+    }
+
+    // Whether more tokens are required before the node can be a complete statement.
+    pub fn is_incomplete(&self) -> bool {
+        match self {
+            Node::PartialMember(_)
+            | Node::UnopenedMatch(_)
+            | Node::UnnamedClassDefinition
+            | Node::UnnamedEnumDefinition
+            | Node::NamedClassDefinition(_)
+            | Node::NamedEnumDefinition(_)
+            | Node::PartialEnumDefinition(_, _) => true,
+            Node::Addition(_, rhs)
+            | Node::Substraction(_, rhs)
+            | Node::Multiplication(_, rhs)
+            | Node::Division(_, rhs)
+            | Node::Modulus(_, rhs)
+            | Node::Assignment(_, rhs)
+            | Node::CompoundAssignment(_, _, rhs)
+            | Node::Negation(rhs)
+            | Node::Variadic(rhs)
+            | Node::KeyValue(_, rhs) => **rhs == Node::Empty || rhs.is_incomplete(),
+            Node::Tuple(elements) => elements.last().is_some_and(|last| *last == Node::Empty || last.is_incomplete()),
+            _ => self.is_open(),
+        }
+    }
+
+    // A comma after a complete statement turns it into a tuple, or extends the right hand side of an assignment.
+    pub fn comma(&mut self) {
+        match self {
+            Node::Assignment(_, rhs) | Node::CompoundAssignment(_, _, rhs) => rhs.comma(),
+            Node::Tuple(elements) => elements.push(Node::Empty),
+            _ => *self = Node::Tuple(vec![self.clone(), Node::Empty]),
+        }
+    }
+
+    pub fn template(string: &str, delimiter: char) -> Option<Vec<Node>> {
+        let mut parts = vec![Node::Literal(String::new(), delimiter)];
+        let mut characters = string.chars().peekable();
+        while let Some(character) = characters.next() {
+            match character {
+                '{' if characters.peek() != Some(&'{') => {
+                    let mut code = String::new();
+                    let mut depth = 0;
+                    loop {
+                        match characters.next()? {
+                            '}' if depth == 0 => break,
+                            character => {
+                                if character == '{' {
+                                    depth += 1;
+                                } else if character == '}' {
+                                    depth -= 1;
+                                }
+                                code.push(character);
+                            }
+                        }
+                    }
+                    let mut nodes = match parse(lex(&code)?)? {
+                        Node::Program(nodes) => nodes.into_iter().filter(|node| *node != Node::Empty).collect::<Vec<Node>>(),
+                        _ => unreachable!(),
+                    };
+                    if nodes.len() != 1 {
+                        return None;
+                    }
+                    parts.push(nodes.remove(0));
+                    parts.push(Node::Literal(String::new(), delimiter));
+                }
+                '}' if characters.peek() != Some(&'}') => return None,
+                _ => {
+                    if character == '{' || character == '}' {
+                        characters.next();
+                    }
+                    if let Some(Node::Literal(text, _)) = parts.last_mut() {
+                        text.push(character);
+                    }
+                }
+            }
+        }
+        Some(parts)
+    }
+
+    pub fn compound_operator(token: &Token) -> Option<&'static str> {
+        match token {
+            Token::PlusEqual => Some("+"),
+            Token::MinusEqual => Some("-"),
+            Token::TimesEqual => Some("*"),
+            Token::DivisionEqual => Some("/"),
+            Token::ModulusEqual => Some("%"),
+            _ => None,
+        }
+    }
+
     pub fn start_of(token: Token) -> Option<Node> {
         match token {
             Token::Identifier(string) => {
@@ -60,7 +255,12 @@ impl Node {
             }
             Token::Literal(string, delimiter) => Some(Node::Literal(string, delimiter)),
             Token::Number(string) => Some(Node::Number(string)),
+            Token::Prefix(string) => Some(Node::PartialPrefixed(Box::new(Node::Identifier(string)))),
             Token::OpenParenthesis => Some(Node::PartialParenthesized(Box::new(Node::Empty))),
+            Token::OpenBracket => Some(Node::PartialArray(vec![])),
+            Token::OpenBrace => Some(Node::PartialMap(vec![])),
+            Token::Minus => Some(Node::Negation(Box::new(Node::Empty))),
+            Token::Times => Some(Node::Variadic(Box::new(Node::Empty))),
             Token::NewLine | Token::SemiColon => Some(Node::Empty),
             _ => None,
         }
@@ -68,102 +268,197 @@ impl Node {
 
     pub fn continues(&self, token: &Token) -> Option<bool> {
         match self {
-            Node::Member(node, string) => {
-                match token {
-                    Token::OpenParenthesis => Some(true),
-                    Token::Equal => Some(true),
-                    _ => Some(false),
-                }
-            }
             Node::Program(nodes) => {
                 if let Some(last_node) = nodes.last() {
-                    if last_node.continues(token).unwrap() {
+                    if last_node.continues(token)? {
                         Some(true)
                     } else {
                         match token {
                             Token::Identifier(_) => Some(true),
+                            Token::Comma => Some(*last_node != Node::Empty),
                             _ => Some(false),
                         }
                     }
                 } else {
                     match token {
-                        Token::Identifier(_) => Some(true),
-                        _ => Some(false),
+                        Token::NewLine | Token::SemiColon => Some(false),
+                        _ => Node::Empty.continues(token),
                     }
                 }
             }
-            Node::PartialMember(node) => match token {
+            Node::PartialMember(_node) => match token {
                 Token::Identifier(_) => Some(true),
-                _ => panic!("{:?}", token),
+                _ => None,
             },
             Node::UnnamedEnumDefinition | Node::UnnamedClassDefinition => match token {
                 Token::Identifier(_) => Some(true),
-                _ => panic!("{:?}", token),
+                _ => None,
             },
             Node::NamedEnumDefinition(_) | Node::NamedClassDefinition(_) => match token {
                 Token::OpenBrace => Some(true),
-                _ => panic!("{:?}", token),
+                _ => None,
             },
             Node::PartialEnumDefinition(_, _) => match token {
                 Token::CloseBrace => Some(true),
                 Token::Identifier(_) => Some(true),
                 Token::NewLine => Some(true),
-                _ => panic!("{:?}", token),
+                _ => None,
             },
             Node::PartialClassDefinition(_, code) => {
-                if code.continues(token).unwrap() {
-                    return code.continues(token)
+                if code.continues(token)? {
+                    return Some(true);
                 }
                 match token {
                     Token::CloseBrace => Some(true),
                     Token::NewLine => Some(true),
-                    _ => panic!("{:?}", token),
+                    _ => None,
+                }
+            },
+            Node::UnopenedMatch(_node) => match token {
+                Token::OpenBrace => Some(true),
+                _ => None,
+            },
+            Node::Identifier(_) | Node::Member(_, _) | Node::Index(_, _) => match token {
+                Token::Equal | Token::Plus | Token::Minus | Token::Times | Token::Division | Token::Period | Token::Ellipsis | Token::Modulus | Token::OpenParenthesis | Token::OpenBracket | Token::Colon => Some(true),
+                _ => Some(Node::compound_operator(token).is_some()),
+            },
+            Node::Call(_, _) => match token {
+                Token::Equal | Token::Plus | Token::Minus | Token::Times | Token::Division | Token::Period | Token::Ellipsis | Token::Modulus | Token::OpenParenthesis | Token::OpenBracket | Token::Colon => Some(true),
+                _ => Some(false),
+            },
+            Node::Literal(_, _) => match token {
+                Token::Plus | Token::Minus | Token::Times | Token::Division | Token::Period | Token::Modulus | Token::OpenBracket | Token::Colon => Some(true),
+                _ => Some(false),
+            },
+            Node::Array(_elements) => match token {
+                Token::Period | Token::OpenBracket | Token::Equal => Some(true),
+                _ => Some(false),
+            },
+            Node::PartialArray(elements) => match (token, elements.last()) {
+                (Token::Comma | Token::CloseBracket, Some(last)) if last.is_open() => last.requires(token),
+                (Token::Comma, None | Some(Node::Empty)) => None,
+                (Token::Comma | Token::CloseBracket | Token::NewLine, _) => Some(true),
+                (_, last) => last.unwrap_or(&Node::Empty).continues(token),
+            },
+            Node::PartialIndex(_target, index) if index.is_open() => match token {
+                Token::CloseBracket | Token::Colon | Token::Comma => index.requires(token),
+                _ => index.continues(token),
+            },
+            Node::PartialIndex(_target, index) => match token {
+                Token::CloseBracket | Token::Colon | Token::Comma => Some(true),
+                _ => index.continues(token),
+            },
+            Node::Slice(_start, end) => end.continues(token),
+            Node::PartialMap(entries) if entries.last().is_some_and(|entry| entry.is_open()) => match token {
+                Token::NewLine => Some(true),
+                _ => entries.last()?.requires(token),
+            },
+            Node::PartialMap(entries) => match token {
+                Token::CloseBrace => {
+                    let pairs = entries.iter().filter(|entry| matches!(entry, Node::KeyValue(..))).count();
+                    let elements = entries.iter().filter(|entry| **entry != Node::Empty).count();
+                    if pairs == 0 || pairs == elements {
+                        Some(true)
+                    } else {
+                        None
+                    }
                 }
+                Token::Colon => match entries.last() {
+                    Some(Node::KeyValue(_key, value)) => value.requires(token),
+                    Some(Node::Empty) | None => None,
+                    Some(_) => Some(true),
+                },
+                Token::Comma | Token::NewLine => Some(true),
+                _ => entries.last().unwrap_or(&Node::Empty).continues(token),
             },
-            Node::UnopenedMatch(node) => match token {
+            Node::KeyValue(_key, value) => value.continues(token),
+            Node::Variadic(node) => node.continues(token),
+            Node::Tuple(elements) => {
+                let last = elements.last().unwrap();
+                if last.is_open() {
+                    last.continues(token)
+                } else {
+                    match token {
+                        Token::Comma if *last == Node::Empty => None,
+                        Token::Comma => Some(true),
+                        Token::Equal if *last != Node::Empty => Some(true),
+                        _ => last.continues(token),
+                    }
+                }
+            }
+            Node::PartialPrefixed(_prefix) => match token {
+                Token::Literal(string, delimiter) => Node::template(string, *delimiter).map(|_| true),
                 Token::OpenBrace => Some(true),
-                _ => panic!("{:?}", token),
+                _ => None,
             },
-            Node::Identifier(_identifier) => match token {
-                Token::Equal | Token::Plus | Token::Minus | Token::Times | Token::Division | Token::Period | Token::Modulus | Token::OpenParenthesis | Token::Colon => Some(true),
+            Node::Prefixed(_prefix, literal) => {
+                if literal.is_open() {
+                    literal.continues(token)
+                } else {
+                    match token {
+                        Token::Period | Token::OpenBracket => Some(true),
+                        _ => Some(false),
+                    }
+                }
+            }
+            Node::Map(_entries) => match token {
+                Token::Period | Token::OpenBracket | Token::Equal => Some(true),
+                _ => Some(false),
+            },
+            Node::Set(_entries) => match token {
+                Token::Period | Token::OpenBracket => Some(true),
                 _ => Some(false),
             },
+            Node::Negation(node) if **node == Node::Empty || node.is_open() => node.continues(token),
+            Node::Negation(node) => match token {
+                Token::Plus | Token::Minus | Token::Times | Token::Division | Token::Modulus => Some(true),
+                _ => node.continues(token),
+            },
             Node::Number(_identifier) => match token {
                 Token::Plus | Token::Minus | Token::Times | Token::Division | Token::Period | Token::Modulus | Token::OpenParenthesis => Some(true),
                 _ => Some(false),
             },
             Node::Parenthesized(_identifier) => match token {
-                Token::Plus | Token::Minus | Token::Times | Token::Division | Token::Period | Token::Modulus | Token::OpenParenthesis | Token::OpenBrace => Some(true),
+                Token::Plus | Token::Minus | Token::Times | Token::Division | Token::Period | Token::Ellipsis | Token::Modulus | Token::OpenParenthesis | Token::OpenBracket | Token::OpenBrace => Some(true),
                 _ => Some(false),
             },
             Node::PartialMatch(_matched, arms) => {
                 if let Token::NewLine = token {
                     return Some(true);
                 }
-                let last_arm = arms.last();
-                if let Some(Node::PartialMatchArm(matcher, nodes)) = last_arm {
-                    if nodes.continues(token).unwrap() {
-                        Some(true)
-                    } else {
-                        match token {
-                            Token::CloseBrace => Some(true),
-                            _ => Some(false),
+                match arms.last() {
+                    Some(Node::PartialMatchArm(_pattern, nodes)) => {
+                        if nodes.continues(token)? {
+                            Some(true)
+                        } else {
+                            match token {
+                                Token::CloseBrace => Some(true),
+                                _ => Some(false),
+                            }
                         }
                     }
-                } else {
-                    match token {
-                        Token::Identifier(_) | Token::CloseBrace | Token::Colon | Token::OpenBrace | Token::Period => Some(true),
-                        _ => panic!("{:?}", token),
-                    }
+                    // The pattern of an arm is any expression, a colon after a complete one opens its body.
+                    Some(Node::MatchArmWithoutColon(pattern)) => match token {
+                        Token::Colon if !pattern.is_incomplete() => Some(true),
+                        _ => pattern.requires(token),
+                    },
+                    Some(Node::UnopenedMatchArm(_pattern)) => match token {
+                        Token::OpenBrace => Some(true),
+                        _ => None,
+                    },
+                    _ => match token {
+                        Token::CloseBrace | Token::Identifier(_) | Token::Number(_) | Token::Literal(_, _) | Token::Minus | Token::OpenBracket | Token::OpenBrace => Some(true),
+                        _ => None,
+                    },
                 }
             }
-            Node::PartialMatchArm(matcher, program) => {
-                if let Some(true) = program.continues(token) {
+            Node::PartialMatchArm(_pattern, program) => {
+                if program.continues(token)? {
                     Some(true)
                 } else {
                     match token {
                         Token::CloseBrace => Some(true),
-                        _ => panic!("Cant add token {:?} to node {:?}", token, self),
+                        _ => None,
                     }
                 }
             }
@@ -173,50 +468,67 @@ impl Node {
             Node::Division(_lhs, rhs) => rhs.continues(token),
             Node::Modulus(_lhs, rhs) => rhs.continues(token),
             Node::Assignment(_lhs, rhs) => rhs.continues(token),
+            Node::CompoundAssignment(_lhs, _operator, rhs) => rhs.continues(token),
             Node::PartialFunctionDefinition(_args, nodes) => {
                 let last_node = nodes.last().unwrap();
-                if last_node.continues(&token).unwrap() {
+                if last_node.continues(token)? {
                     Some(true)
                 } else {
                     match token {
                         Token::CloseBrace | Token::NewLine | Token::SemiColon => Some(true),
+                        Token::Comma => Some(*last_node != Node::Empty),
                         _ => Some(false),
                     }
                 }
             }
+            Node::FunctionArgs(args) => match token {
+                Token::Comma if args.last().unwrap().is_open() => args.last().unwrap().requires(token),
+                Token::Comma => Some(true),
+                _ => args.last().unwrap().continues(token),
+            },
             Node::PartialParenthesized(node) => {
-                if node.continues(&token).unwrap() {
+                if node.continues(token)? {
                     Some(true)
                 } else {
                     match token {
-                        Token::CloseParenthesis => Some(true),
+                        Token::CloseParenthesis | Token::Comma => Some(true),
                         _ => Some(false),
                     }
                 }
             }
-            Node::Call(_lhs, rhs) => Some(false),
-            Node::PartialCall(_lhs, rhs) => match token {
-                Token::Comma => Some(true),
-                Token::CloseParenthesis => Some(true),
-                _ => {
-                    if rhs.len() == 0 {
-                        Some(true)
-                    } else {
-                        rhs.last().unwrap().continues(token)
-                    }
-                }
+            Node::PartialCall(_lhs, rhs) => match (token, rhs.last()) {
+                (Token::Comma | Token::CloseParenthesis, Some(last)) if last.is_open() => last.requires(token),
+                (Token::Comma, None | Some(Node::Empty)) => None,
+                (Token::Comma | Token::CloseParenthesis, _) => Some(true),
+                (_, last) => last.unwrap_or(&Node::Empty).continues(token),
             },
             Node::Empty => match token {
-                Token::CloseBrace | Token::CloseParenthesis => Some(false),
-                _ => Some(true),
+                Token::CloseBrace | Token::CloseParenthesis | Token::CloseBracket => Some(false),
+                _ => Node::start_of(token.to_owned()).map(|_| true),
             },
             _ => Some(false),
         }
     }
 
+    // Like `continues` for a node that cannot be left as it is, a token it does not take is a syntax error.
+    fn requires(&self, token: &Token) -> Option<bool> {
+        match self.continues(token)? {
+            true => Some(true),
+            false => None,
+        }
+    }
+
     pub fn append(&mut self, token: Token) {
         match self {
-            Node::Identifier(_) | Node::Number(_) => match token {
+            Node::Parenthesized(node) if matches!(token, Token::OpenBrace) => {
+                let arguments = match &**node {
+                    Node::Empty => vec![],
+                    Node::FunctionArgs(args) => args.to_vec(),
+                    _ => vec![*node.clone()],
+                };
+                *self = Node::PartialFunctionDefinition(arguments, vec![Node::Empty]);
+            }
+            Node::Identifier(_) | Node::Number(_) | Node::Literal(_, _) | Node::Member(_, _) | Node::Index(_, _) | Node::Call(_, _) | Node::Parenthesized(_) => match token {
                 Token::Equal => {
                     *self = Node::Assignment(Box::new(self.clone()), Box::new(Node::Empty));
                 }
@@ -238,67 +550,62 @@ impl Node {
                 Token::OpenParenthesis => {
                     *self = Node::PartialCall(Box::new(self.clone()), vec![]);
                 }
+                Token::OpenBracket => {
+                    *self = Node::PartialIndex(Box::new(self.clone()), Box::new(Node::Empty));
+                }
                 Token::Period => *self = Node::PartialMember(Box::new(self.clone())),
+                Token::Ellipsis => *self = Node::Variadic(Box::new(self.clone())),
                 Token::Colon => {
                     *self = Node::UnopenedMatch(Box::new(self.clone()));
                 }
-                _ => {}
-            },
-            Node::Parenthesized(node) => match token {
-                Token::Times => *self = Node::Multiplication(Box::new(self.clone()), Box::new(Node::Empty)),
-                Token::OpenBrace => {
-                    let arguments = match **node {
-                        Node::Empty => vec![],
-                        _ => vec![*node.clone()],
-                    };
-                    *self = Node::PartialFunctionDefinition(arguments, vec![Node::Empty]);
-                }
-                _ => unimplemented!(),
-            },
-            Node::Addition(_lhs, rhs) => match token {
-                Token::Plus => *self = Node::Addition(Box::new(self.clone()), Box::new(Node::Empty)),
                 _ => {
-                    rhs.append(token);
-                }
-            },
-            Node::Substraction(_lhs, rhs) => match token {
-                Token::Plus => *self = Node::Addition(Box::new(self.clone()), Box::new(Node::Empty)),
-                _ => {
-                    rhs.append(token);
+                    if let Some(operator) = Node::compound_operator(&token) {
+                        *self = Node::CompoundAssignment(Box::new(self.clone()), String::from(operator), Box::new(Node::Empty));
+                    }
                 }
             },
-            Node::Modulus(_lhs, rhs) => match token {
+            Node::Addition(_lhs, rhs) | Node::Substraction(_lhs, rhs) | Node::Multiplication(_lhs, rhs) | Node::Division(_lhs, rhs) | Node::Modulus(_lhs, rhs) if **rhs == Node::Empty || rhs.is_open() => {
+                rhs.append(token);
+            }
+            Node::Addition(_lhs, rhs) | Node::Substraction(_lhs, rhs) => match token {
                 Token::Plus => *self = Node::Addition(Box::new(self.clone()), Box::new(Node::Empty)),
+                Token::Minus => *self = Node::Substraction(Box::new(self.clone()), Box::new(Node::Empty)),
                 _ => {
                     rhs.append(token);
                 }
             },
-            Node::Multiplication(_lhs, rhs) => match token {
-                Token::Times => *self = Node::Multiplication(Box::new(self.clone()), Box::new(Node::Empty)),
+            Node::Multiplication(_lhs, rhs) | Node::Division(_lhs, rhs) | Node::Modulus(_lhs, rhs) => match token {
                 Token::Plus => *self = Node::Addition(Box::new(self.clone()), Box::new(Node::Empty)),
-                _ => {
-                    rhs.append(token);
-                }
-            },
-            Node::Division(_lhs, rhs) => match token {
+                Token::Minus => *self = Node::Substraction(Box::new(self.clone()), Box::new(Node::Empty)),
                 Token::Times => *self = Node::Multiplication(Box::new(self.clone()), Box::new(Node::Empty)),
-                Token::Plus => *self = Node::Addition(Box::new(self.clone()), Box::new(Node::Empty)),
+                Token::Division => *self = Node::Division(Box::new(self.clone()), Box::new(Node::Empty)),
+                Token::Modulus => *self = Node::Modulus(Box::new(self.clone()), Box::new(Node::Empty)),
                 _ => {
                     rhs.append(token);
                 }
             },
             Node::PartialParenthesized(node) => {
-                if node.continues(&token).unwrap() {
+                if node.continues(&token) == Some(true) {
                     node.append(token);
                 } else {
                     if token == Token::CloseParenthesis {
                         *self = Node::Parenthesized(node.clone());
+                    } else if token == Token::Comma {
+                        **node = Node::FunctionArgs(vec![*node.clone(), Node::Empty]);
                     }
                 }
             }
+            Node::FunctionArgs(args) => {
+                let last_arg = args.last_mut().unwrap();
+                if token == Token::Comma && !last_arg.is_open() {
+                    args.push(Node::Empty);
+                } else {
+                    last_arg.append(token);
+                }
+            }
             Node::PartialFunctionDefinition(args, nodes) => {
                 let mut last_node = nodes.last_mut().unwrap();
-                if last_node.continues(&token).unwrap() {
+                if last_node.continues(&token) == Some(true) {
                     last_node.append(token);
                 } else {
                     match token {
@@ -311,6 +618,7 @@ impl Node {
                         Token::NewLine => {
                             nodes.push(Node::Empty);
                         }
+                        Token::Comma => last_node.comma(),
                         _ => {}
                     }
                 }
@@ -340,7 +648,7 @@ impl Node {
                 _ => {}
             },
             Node::PartialClassDefinition(name, code) => {
-                if code.continues(&token).unwrap() {
+                if code.continues(&token) == Some(true) {
                     code.append(token)
                 } else {
                     match token {
@@ -361,7 +669,7 @@ impl Node {
             Node::PartialCall(callee, args) => match token {
                 Token::CloseParenthesis => {
                     if let Some(last_arg) = args.last_mut() {
-                        if last_arg.continues(&token).unwrap() {
+                        if last_arg.continues(&token) == Some(true) {
                             last_arg.append(token)
                         } else {
                             *self = Node::Call(callee.to_owned(), args.to_vec())
@@ -372,7 +680,7 @@ impl Node {
                 }
                 Token::Comma => {
                     let mut last_arg = args.last_mut().unwrap();
-                    if last_arg.continues(&token).unwrap() {
+                    if last_arg.continues(&token) == Some(true) {
                         last_arg.append(token)
                     } else {
                         args.push(Node::Empty);
@@ -385,16 +693,133 @@ impl Node {
                     args.last_mut().unwrap().append(token);
                 }
             },
-            Node::Number(_number) => match token {
-                Token::Plus => {
-                    *self = Node::Addition(Box::new(self.clone()), Box::new(Node::Empty));
+            Node::Prefixed(_prefix, literal) if literal.is_open() => literal.append(token),
+            Node::Array(_) | Node::Map(_) | Node::Set(_) | Node::Prefixed(_, _) => match token {
+                Token::OpenBracket => {
+                    *self = Node::PartialIndex(Box::new(self.clone()), Box::new(Node::Empty));
                 }
-                Token::Times => {
-                    *self = Node::Multiplication(Box::new(self.clone()), Box::new(Node::Empty));
+                Token::Period => *self = Node::PartialMember(Box::new(self.clone())),
+                Token::Equal => *self = Node::Assignment(Box::new(self.clone()), Box::new(Node::Empty)),
+                _ => {}
+            },
+            Node::PartialArray(elements) => match token {
+                Token::CloseBracket => {
+                    if let Some(last_element) = elements.last_mut() {
+                        if last_element.continues(&token) == Some(true) {
+                            last_element.append(token);
+                            return;
+                        }
+                        if *last_element == Node::Empty {
+                            elements.remove(elements.len() - 1);
+                        }
+                    }
+                    *self = Node::Array(elements.to_vec());
+                }
+                Token::Comma => {
+                    let last_element = elements.last_mut().unwrap();
+                    if last_element.continues(&token) == Some(true) {
+                        last_element.append(token)
+                    } else {
+                        elements.push(Node::Empty);
+                    }
+                }
+                Token::NewLine => {
+                    if let Some(last_element) = elements.last_mut() {
+                        if last_element.is_open() {
+                            last_element.append(token);
+                        }
+                    }
+                }
+                _ => {
+                    if elements.is_empty() {
+                        elements.push(Node::Empty);
+                    }
+                    elements.last_mut().unwrap().append(token);
+                }
+            },
+            Node::PartialIndex(target, index) => match token {
+                Token::CloseBracket if !index.is_open() => {
+                    *self = Node::Index(target.to_owned(), index.to_owned());
+                }
+                Token::Colon if !index.is_open() => {
+                    if let Node::Slice(_, _) = **index {
+                    } else {
+                        **index = Node::Slice(index.to_owned(), Box::new(Node::Empty));
+                    }
+                }
+                Token::Comma if !index.is_open() => {
+                    if let Node::FunctionArgs(_) = **index {
+                        index.append(token);
+                    } else {
+                        **index = Node::FunctionArgs(vec![*index.to_owned(), Node::Empty]);
+                    }
+                }
+                _ => index.append(token),
+            },
+            Node::Slice(_start, end) => end.append(token),
+            Node::PartialMap(entries) => {
+                if let Some(last_entry) = entries.last_mut() {
+                    if last_entry.is_open() {
+                        last_entry.append(token);
+                        return;
+                    }
+                }
+                match token {
+                    Token::CloseBrace => {
+                        if entries.last() == Some(&Node::Empty) {
+                            entries.remove(entries.len() - 1);
+                        }
+                        if entries.iter().all(|entry| matches!(entry, Node::KeyValue(..))) {
+                            *self = Node::Map(entries.to_vec());
+                        } else {
+                            *self = Node::Set(entries.to_vec());
+                        }
+                    }
+                    Token::Colon => {
+                        let last_entry = entries.last_mut().unwrap();
+                        if let Node::KeyValue(_, value) = last_entry {
+                            value.append(token);
+                        } else {
+                            *last_entry = Node::KeyValue(Box::new(last_entry.to_owned()), Box::new(Node::Empty));
+                        }
+                    }
+                    Token::Comma => entries.push(Node::Empty),
+                    Token::NewLine => {}
+                    _ => {
+                        if entries.is_empty() {
+                            entries.push(Node::Empty);
+                        }
+                        entries.last_mut().unwrap().append(token);
+                    }
+                }
+            }
+            Node::KeyValue(_key, value) => value.append(token),
+            Node::PartialPrefixed(prefix) => match token {
+                Token::Literal(string, delimiter) => {
+                    *self = Node::Prefixed(prefix.to_owned(), Box::new(Node::Template(Node::template(&string, delimiter).unwrap())));
+                }
+                Token::OpenBrace => {
+                    *self = Node::Prefixed(prefix.to_owned(), Box::new(Node::PartialMap(vec![])));
                 }
                 _ => {}
             },
-            Node::Assignment(_lhs, rhs) => {
+            Node::Negation(node) if **node == Node::Empty || node.is_open() => node.append(token),
+            Node::Variadic(node) => node.append(token),
+            Node::Tuple(elements) if elements.last().unwrap().is_open() => elements.last_mut().unwrap().append(token),
+            Node::Tuple(elements) => match token {
+                Token::Comma => elements.push(Node::Empty),
+                Token::Equal => *self = Node::Assignment(Box::new(self.clone()), Box::new(Node::Empty)),
+                _ => elements.last_mut().unwrap().append(token),
+            },
+            Node::Negation(node) => match token {
+                Token::Plus => *self = Node::Addition(Box::new(self.clone()), Box::new(Node::Empty)),
+                Token::Minus => *self = Node::Substraction(Box::new(self.clone()), Box::new(Node::Empty)),
+                Token::Times => *self = Node::Multiplication(Box::new(self.clone()), Box::new(Node::Empty)),
+                Token::Division => *self = Node::Division(Box::new(self.clone()), Box::new(Node::Empty)),
+                Token::Modulus => *self = Node::Modulus(Box::new(self.clone()), Box::new(Node::Empty)),
+                _ => node.append(token),
+            },
+            Node::Assignment(_lhs, rhs) | Node::CompoundAssignment(_lhs, _, rhs) => {
                 rhs.append(token);
             }
             Node::Empty => {
@@ -407,8 +832,10 @@ impl Node {
 
             Node::Program(nodes) => {
                 if let Some(last_node) = nodes.last_mut() {
-                    if last_node.continues(&token).unwrap() {
+                    if last_node.continues(&token) == Some(true) {
                         last_node.append(token)
+                    } else if token == Token::Comma {
+                        last_node.comma();
                     } else {
                         nodes.push(Node::start_of(token).unwrap())
                     }
@@ -417,75 +844,44 @@ impl Node {
                 }
             }
 
-            Node::PartialMatch(node, arms) => {
-                if let Some(last_arm) = arms.last_mut() {
-                    match last_arm {
-                        Node::PartialMatchArm(matcher, nodes) => {
-                            if nodes.continues(&token).unwrap() {
-                                nodes.append(token);
-                            } else {
-                                match token {
-                                    Token::CloseBrace => {
-                                        *last_arm = Node::MatchArm(matcher.to_owned(), nodes.to_owned());
-                                    }
-                                    _ => {}
-                                }
-                            }
-                        }
-                        Node::MatchArmWithoutColon(matcher) => match token {
-                            Token::Colon => {
-                                *last_arm = Node::UnopenedMatchArm(matcher.to_owned());
-                            }
-                            _ => {
-                                if matcher.continues(&token).unwrap() {
-                                    matcher.append(token);
-                                }
-                            }
-                        },
-                        Node::UnopenedMatchArm(matcher) => {
-                            if let Token::OpenBrace = token {
-                                *last_arm = Node::PartialMatchArm(matcher.to_owned(), Box::new(Node::Program(vec![])));
-                            } else {
-                                unreachable!();
-                            }
-                        }
-                        Node::MatchArm(_matcher, _nodes) => match token {
-                            Token::CloseBrace => {
-                                *self = Node::Match(node.clone(), arms.to_vec());
-                            }
-                            Token::Identifier(string) => arms.push(Node::MatchArmWithoutColon(Box::new(Node::Identifier(string)))),
-                            _ => {}
-                        },
-                        _ => {}
+            Node::PartialMatch(node, arms) => match arms.last_mut() {
+                Some(Node::PartialMatchArm(matcher, nodes)) => {
+                    if nodes.continues(&token) == Some(true) {
+                        nodes.append(token);
+                    } else if token == Token::CloseBrace {
+                        *arms.last_mut().unwrap() = Node::MatchArm(matcher.to_owned(), nodes.to_owned());
                     }
-                } else {
-                    match token {
-                        Token::CloseBrace => {
-                            *self = Node::Match(node.clone(), arms.to_vec());
-                        }
-                        Token::Identifier(string) => {
-                            arms.push(Node::MatchArmWithoutColon(Box::new(Node::Identifier(string))));
+                }
+                Some(Node::MatchArmWithoutColon(matcher)) => match token {
+                    Token::Colon if !matcher.is_incomplete() => {
+                        *arms.last_mut().unwrap() = Node::UnopenedMatchArm(matcher.to_owned());
+                    }
+                    _ => {
+                        if matcher.continues(&token) == Some(true) {
+                            matcher.append(token);
                         }
-                        _ => {}
+                    }
+                },
+                Some(Node::UnopenedMatchArm(matcher)) => {
+                    if let Token::OpenBrace = token {
+                        *arms.last_mut().unwrap() = Node::PartialMatchArm(matcher.to_owned(), Box::new(Node::Program(vec![])));
                     }
                 }
-            }
+                _ => match token {
+                    Token::CloseBrace => {
+                        *self = Node::Match(node.clone(), arms.to_vec());
+                    }
+                    Token::NewLine => {}
+                    token => {
+                        if let Some(pattern) = Node::start_of(token) {
+                            arms.push(Node::MatchArmWithoutColon(Box::new(pattern)));
+                        }
+                    }
+                },
+            },
             Node::PartialMember(node) => {
                 if let Token::Identifier(string) = token {
                     *self = Node::Member(node.to_owned(), string);
-                } else {
-                    unreachable!();
-                }
-            }
-            Node::Member(node, string) => {
-                match token {
-                    Token::OpenParenthesis => {
-                        *self = Node::PartialCall(Box::new(self.to_owned()), vec![]);
-                    }
-                    Token::Equal => {
-                        *self = Node::Assignment(Box::new(self.clone()), Box::new(Node::Empty));
-                    }
-                    _ => unimplemented!(),
                 }
             }
             _ => {}
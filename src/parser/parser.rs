@@ -1,18 +1,200 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+use lexer::span::Span;
 use lexer::token::Token;
 use crate::node::Node;
 
-pub fn parse(mut tokens: Vec<Token>) -> Option<Node> {
-    let mut tree = Node::Program(vec![]);
+// Grows a statement for as long as it continues, returns how many tokens it took or None on a syntax error.
+fn extend(node: &mut Node, tokens: &mut Vec<Token>) -> Option<usize> {
+    let mut consumed = 0;
+    while !tokens.is_empty() {
+        if node.continues(&tokens[0])? {
+            node.append(tokens.remove(0));
+        } else if tokens[0] == Token::Comma && *node != Node::Empty {
+            tokens.remove(0);
+            node.comma();
+        } else {
+            break;
+        }
+        consumed += 1;
+    }
+    Some(consumed)
+}
+
+pub fn parse(tokens: Vec<Token>) -> Option<Node> {
+    Some(Node::Program(statements(tokens)?.into_iter().map(|(node, _range)| node).collect()))
+}
+
+// Parses the top-level statements along with the span from their first to their last token.
+pub fn parse_spanned(spanned: Vec<(Token, Span)>) -> Option<Vec<(Node, Span)>> {
+    let (tokens, spans): (Vec<Token>, Vec<Span>) = spanned.into_iter().unzip();
+    let separators: Vec<bool> = tokens.iter().map(|token| matches!(token, Token::NewLine | Token::SemiColon)).collect();
+    let statements = statements(tokens)?;
+    Some(
+        statements
+            .into_iter()
+            .map(|(node, range)| {
+                // Blank lines before a statement are absorbed by it but are not part of its span.
+                let start = range.clone().find(|index| !separators[*index]).unwrap_or(range.start);
+                (node, Span { start: spans[start].start, end: spans[range.end - 1].end })
+            })
+            .collect(),
+    )
+}
+
+// The top-level statements with the range of token indices each one was built from.
+fn statements(mut tokens: Vec<Token>) -> Option<Vec<(Node, Range<usize>)>> {
+    let mut statements = vec![];
+    let mut index = 0;
     while !tokens.is_empty() {
         let token = tokens.remove(0);
+        let first = index;
+        index += 1;
         if let Some(mut node) = Node::start_of(token) {
-            while !tokens.is_empty() && node.continues(&tokens[0])? {
-                node.append(tokens.remove(0));
-            }
-            if let Node::Program(nodes) = &mut tree {
-                nodes.push(node);
+            index += extend(&mut node, &mut tokens)?;
+            statements.push((node, first..index));
+        }
+    }
+    Some(statements)
+}
+
+// True when the tokens are a valid prefix of a program that needs more input, like `f = (x) {`.
+pub fn is_incomplete(mut tokens: Vec<Token>) -> bool {
+    let mut last = Node::Empty;
+    while !tokens.is_empty() {
+        let token = tokens.remove(0);
+        if let Some(mut node) = Node::start_of(token) {
+            if extend(&mut node, &mut tokens).is_none() {
+                return false;
             }
+            last = node;
         }
     }
-    Some(tree)
+    last.is_incomplete()
+}
+
+// The spans of the nodes nested in the statements, keyed by the address of the node. Every node is
+// walked over the tokens of its statement in source order, a node covers the tokens from its first
+// to its last one, brackets and keywords included. Empty nodes have no span, nor do the parts of a
+// template since they are all read from a single String token.
+pub fn node_spans(statements: &[(Node, Span)], tokens: &[(Token, Span)]) -> HashMap<*const Node, Span> {
+    let mut walker = Walker { tokens, next: 0, spans: HashMap::new() };
+    for (statement, span) in statements.iter() {
+        walker.next = tokens.iter().position(|(_token, token_span)| token_span.start.offset >= span.start.offset).unwrap_or(tokens.len());
+        walker.walk(statement);
+        walker.spans.insert(statement, *span);
+    }
+    walker.spans
+}
+
+struct Walker<'a> {
+    tokens: &'a [(Token, Span)],
+    next: usize,
+    spans: HashMap<*const Node, Span>,
+}
+
+impl Walker<'_> {
+    // Moves past the next token matching `wanted` and returns its index, the tokens skipped on the
+    // way are the separators and operators of the parent node.
+    fn find(&mut self, wanted: impl Fn(&Token) -> bool) -> Option<usize> {
+        let index = self.next + self.tokens[self.next..].iter().position(|(token, _span)| wanted(token))?;
+        self.next = index + 1;
+        Some(index)
+    }
+
+    // Walks nodes that follow each other in the source and returns the range they cover together, a
+    // missing node takes no token.
+    fn all<'n>(&mut self, nodes: impl IntoIterator<Item = &'n Node>) -> Option<(usize, usize)> {
+        nodes.into_iter().filter_map(|node| self.walk(node)).fold(None, |range, (first, last)| Some(range.map_or((first, last), |(start, _end)| (start, last))))
+    }
+
+    // Records the span of `node` and returns the indices of its first and last tokens.
+    fn walk(&mut self, node: &Node) -> Option<(usize, usize)> {
+        let keyword = |name: &'static str| move |token: &Token| *token == Token::Identifier(String::from(name));
+        let range = match node {
+            Node::Identifier(name) => {
+                let index = self.find(|token| matches!(token, Token::Identifier(found) | Token::Prefix(found) if found == name))?;
+                Some((index, index))
+            }
+            Node::Number(_) => self.find(|token| matches!(token, Token::Number(_))).map(|index| (index, index)),
+            Node::Literal(_, _) | Node::Template(_) => self.find(|token| matches!(token, Token::Literal(_, _))).map(|index| (index, index)),
+            Node::Assignment(lhs, rhs) | Node::CompoundAssignment(lhs, _, rhs) | Node::KeyValue(lhs, rhs) | Node::Prefixed(lhs, rhs) | Node::MatchArm(lhs, rhs) => self.all([&**lhs, &**rhs]),
+            Node::Addition(lhs, rhs) | Node::Substraction(lhs, rhs) | Node::Multiplication(lhs, rhs) | Node::Division(lhs, rhs) | Node::Modulus(lhs, rhs) => self.all([&**lhs, &**rhs]),
+            Node::Slice(start, end) => {
+                let start = self.walk(start);
+                let colon = self.find(|token| *token == Token::Colon)?;
+                let end = self.walk(end);
+                Some((start.map_or(colon, |(first, _last)| first), end.map_or(colon, |(_first, last)| last)))
+            }
+            Node::Negation(operand) => {
+                let minus = self.find(|token| *token == Token::Minus)?;
+                Some((minus, self.walk(operand)?.1))
+            }
+            Node::Variadic(expression) => {
+                let (first, last) = self.walk(expression)?;
+                if first > 0 && self.tokens[first - 1].0 == Token::Times {
+                    Some((first - 1, last))
+                } else {
+                    Some((first, self.find(|token| *token == Token::Ellipsis)?))
+                }
+            }
+            Node::Parenthesized(expression) => {
+                let open = self.find(|token| *token == Token::OpenParenthesis)?;
+                self.walk(expression);
+                Some((open, self.find(|token| *token == Token::CloseParenthesis)?))
+            }
+            Node::Call(callee, arguments) => {
+                let (first, _last) = self.walk(callee)?;
+                self.all(arguments);
+                Some((first, self.find(|token| *token == Token::CloseParenthesis)?))
+            }
+            Node::Member(object, name) => {
+                let (first, _last) = self.walk(object)?;
+                Some((first, self.find(|token| matches!(token, Token::Identifier(found) if found == name))?))
+            }
+            Node::Index(object, index) => {
+                let (first, _last) = self.walk(object)?;
+                self.walk(index);
+                Some((first, self.find(|token| *token == Token::CloseBracket)?))
+            }
+            Node::FunctionArgs(elements) | Node::Tuple(elements) => self.all(elements),
+            Node::Array(elements) => {
+                let open = self.find(|token| *token == Token::OpenBracket)?;
+                self.all(elements);
+                Some((open, self.find(|token| *token == Token::CloseBracket)?))
+            }
+            Node::Map(elements) | Node::Set(elements) | Node::Program(elements) => {
+                let open = self.find(|token| *token == Token::OpenBrace)?;
+                self.all(elements);
+                Some((open, self.find(|token| *token == Token::CloseBrace)?))
+            }
+            Node::FunctionDefinition(parameters, body) => {
+                let open = self.find(|token| *token == Token::OpenParenthesis)?;
+                self.all(parameters);
+                self.all(body);
+                Some((open, self.find(|token| *token == Token::CloseBrace)?))
+            }
+            Node::EnumDefinition(name, variants) => {
+                let first = self.find(keyword("enum"))?;
+                self.walk(name);
+                self.all(variants);
+                Some((first, self.find(|token| *token == Token::CloseBrace)?))
+            }
+            Node::ClassDefinition(name, body) => {
+                let first = self.find(keyword("class"))?;
+                self.walk(name);
+                Some((first, self.walk(body)?.1))
+            }
+            Node::Match(subject, arms) => {
+                let (first, _last) = self.walk(subject)?;
+                self.all(arms);
+                Some((first, self.find(|token| *token == Token::CloseBrace)?))
+            }
+            // Only an unfinished statement holds the other nodes.
+            _ => None,
+        }?;
+        self.spans.insert(node, Span { start: self.tokens[range.0].1.start, end: self.tokens[range.1].1.end });
+        Some(range)
+    }
 }
@@ -1,13 +1,24 @@
 #[allow(unused_imports)]
 use lexer;
 #[allow(unused_imports)]
-use crate::parser;
+use crate as parser;
 
 #[cfg(test)]
 mod tests {
     use super::lexer::token::Token;
     use super::parser::node::Node;
-    use super::parser::parser::parse;
+
+    // Every tree parsed by these tests also goes through the formatter, the formatted source must parse back
+    // to the same tree and format to itself. Only unfinished statements cannot be formatted, those are
+    // listed in `format_node_refuses_unfinished_statements`.
+    fn parse(tokens: Vec<Token>) -> Option<Node> {
+        let tree = super::parser::parser::parse(tokens)?;
+        let formatted = super::parser::format::format_node(&tree).unwrap_or_else(|| panic!("cannot format {:?}", tree));
+        let reparsed = super::lexer::lexer::lex(formatted.trim_end()).and_then(super::parser::parser::parse);
+        assert_eq!(reparsed.as_ref(), Some(&tree), "formatted as:\n{}", formatted);
+        assert_eq!(super::parser::format::format(&formatted).as_ref(), Some(&formatted));
+        Some(tree)
+    }
 
     #[test]
     fn identifier() {
@@ -746,4 +757,885 @@ mod tests {
             )])),
         );
     }
+
+    #[test]
+    fn function_definition_two_args() {
+        assert_eq!(
+            parse(vec![
+                Token::OpenParenthesis,
+                Token::Identifier(String::from("foo")),
+                Token::Comma,
+                Token::Identifier(String::from("bar")),
+                Token::CloseParenthesis,
+                Token::OpenBrace,
+                Token::CloseBrace,
+            ]),
+            Some(Node::Program(vec![Node::FunctionDefinition(vec![Node::Identifier(String::from("foo")), Node::Identifier(String::from("bar")),], vec![],),],),)
+        );
+    }
+
+    #[test]
+    fn empty_array() {
+        assert_eq!(parse(vec![Token::OpenBracket, Token::CloseBracket,]), Some(Node::Program(vec![Node::Array(vec![]),],),));
+    }
+
+    #[test]
+    fn array_literal() {
+        assert_eq!(
+            parse(vec![
+                Token::OpenBracket,
+                Token::Number(String::from("1")),
+                Token::Comma,
+                Token::Identifier(String::from("foo")),
+                Token::Plus,
+                Token::Number(String::from("2")),
+                Token::CloseBracket,
+            ]),
+            Some(Node::Program(vec![Node::Array(vec![
+                Node::Number(String::from("1")),
+                Node::Addition(Box::new(Node::Identifier(String::from("foo"))), Box::new(Node::Number(String::from("2"))),),
+            ]),],),)
+        );
+    }
+
+    #[test]
+    fn multiline_array_literal_with_trailing_comma() {
+        assert_eq!(
+            parse(vec![
+                Token::OpenBracket,
+                Token::NewLine,
+                Token::Number(String::from("1")),
+                Token::Comma,
+                Token::NewLine,
+                Token::OpenBracket,
+                Token::Number(String::from("2")),
+                Token::CloseBracket,
+                Token::Comma,
+                Token::NewLine,
+                Token::CloseBracket,
+            ]),
+            Some(Node::Program(vec![Node::Array(vec![Node::Number(String::from("1")), Node::Array(vec![Node::Number(String::from("2"))]),]),],),)
+        );
+    }
+
+    #[test]
+    fn index() {
+        assert_eq!(
+            parse(vec![Token::Identifier(String::from("foo")), Token::OpenBracket, Token::Number(String::from("0")), Token::CloseBracket,]),
+            Some(Node::Program(vec![Node::Index(Box::new(Node::Identifier(String::from("foo"))), Box::new(Node::Number(String::from("0"))),),],),)
+        );
+    }
+
+    #[test]
+    fn negative_index() {
+        assert_eq!(
+            parse(vec![Token::Identifier(String::from("foo")), Token::OpenBracket, Token::Minus, Token::Number(String::from("1")), Token::CloseBracket,]),
+            Some(Node::Program(vec![Node::Index(
+                Box::new(Node::Identifier(String::from("foo"))),
+                Box::new(Node::Negation(Box::new(Node::Number(String::from("1"))))),
+            ),],),)
+        );
+    }
+
+    #[test]
+    fn nested_index() {
+        assert_eq!(
+            parse(vec![
+                Token::Identifier(String::from("foo")),
+                Token::OpenBracket,
+                Token::Identifier(String::from("bar")),
+                Token::OpenBracket,
+                Token::Number(String::from("0")),
+                Token::CloseBracket,
+                Token::CloseBracket,
+                Token::OpenBracket,
+                Token::Number(String::from("1")),
+                Token::CloseBracket,
+            ]),
+            Some(Node::Program(vec![Node::Index(
+                Box::new(Node::Index(
+                    Box::new(Node::Identifier(String::from("foo"))),
+                    Box::new(Node::Index(Box::new(Node::Identifier(String::from("bar"))), Box::new(Node::Number(String::from("0"))),)),
+                )),
+                Box::new(Node::Number(String::from("1"))),
+            ),],),)
+        );
+    }
+
+    #[test]
+    fn index_assignment() {
+        assert_eq!(
+            parse(vec![
+                Token::Identifier(String::from("foo")),
+                Token::OpenBracket,
+                Token::Number(String::from("0")),
+                Token::CloseBracket,
+                Token::Equal,
+                Token::Number(String::from("4")),
+            ]),
+            Some(Node::Program(vec![Node::Assignment(
+                Box::new(Node::Index(Box::new(Node::Identifier(String::from("foo"))), Box::new(Node::Number(String::from("0"))),)),
+                Box::new(Node::Number(String::from("4"))),
+            ),],),)
+        );
+    }
+
+    #[test]
+    fn slice() {
+        assert_eq!(
+            parse(vec![
+                Token::Identifier(String::from("foo")),
+                Token::OpenBracket,
+                Token::Number(String::from("1")),
+                Token::Colon,
+                Token::Identifier(String::from("bar")),
+                Token::CloseBracket,
+            ]),
+            Some(Node::Program(vec![Node::Index(
+                Box::new(Node::Identifier(String::from("foo"))),
+                Box::new(Node::Slice(Box::new(Node::Number(String::from("1"))), Box::new(Node::Identifier(String::from("bar"))),)),
+            ),],),)
+        );
+    }
+
+    #[test]
+    fn slice_without_bounds() {
+        assert_eq!(
+            parse(vec![Token::Identifier(String::from("foo")), Token::OpenBracket, Token::Colon, Token::CloseBracket,]),
+            Some(Node::Program(vec![Node::Index(Box::new(Node::Identifier(String::from("foo"))), Box::new(Node::Slice(Box::new(Node::Empty), Box::new(Node::Empty),)),),],),)
+        );
+    }
+
+    #[test]
+    fn method_call_on_array_literal() {
+        assert_eq!(
+            parse(vec![Token::OpenBracket, Token::CloseBracket, Token::Period, Token::Identifier(String::from("len")), Token::OpenParenthesis, Token::CloseParenthesis,]),
+            Some(Node::Program(vec![Node::Call(Box::new(Node::Member(Box::new(Node::Array(vec![])), String::from("len"))), vec![]),],),)
+        );
+    }
+
+    #[test]
+    fn empty_map() {
+        assert_eq!(parse(vec![Token::OpenBrace, Token::CloseBrace,]), Some(Node::Program(vec![Node::Map(vec![]),],),));
+    }
+
+    #[test]
+    fn map_literal() {
+        assert_eq!(
+            parse(vec![
+                Token::OpenBrace,
+                Token::Identifier(String::from("foo")),
+                Token::Colon,
+                Token::Number(String::from("1")),
+                Token::Comma,
+                Token::Literal(String::from("bar"), '\''),
+                Token::Colon,
+                Token::Identifier(String::from("baz")),
+                Token::CloseBrace,
+            ]),
+            Some(Node::Program(vec![Node::Map(vec![
+                Node::KeyValue(Box::new(Node::Identifier(String::from("foo"))), Box::new(Node::Number(String::from("1")))),
+                Node::KeyValue(Box::new(Node::Literal(String::from("bar"), '\'')), Box::new(Node::Identifier(String::from("baz")))),
+            ]),],),)
+        );
+    }
+
+    #[test]
+    fn multiline_map_literal_with_function_value() {
+        assert_eq!(
+            parse(vec![
+                Token::OpenBrace,
+                Token::NewLine,
+                Token::Identifier(String::from("foo")),
+                Token::Colon,
+                Token::OpenParenthesis,
+                Token::CloseParenthesis,
+                Token::OpenBrace,
+                Token::NewLine,
+                Token::Identifier(String::from("bar")),
+                Token::NewLine,
+                Token::CloseBrace,
+                Token::Comma,
+                Token::NewLine,
+                Token::CloseBrace,
+            ]),
+            Some(Node::Program(vec![Node::Map(vec![Node::KeyValue(
+                Box::new(Node::Identifier(String::from("foo"))),
+                Box::new(Node::FunctionDefinition(vec![], vec![Node::Identifier(String::from("bar"))])),
+            ),]),],),)
+        );
+    }
+
+    #[test]
+    fn set_literal() {
+        assert_eq!(
+            parse(vec![Token::OpenBrace, Token::Number(String::from("1")), Token::Comma, Token::Identifier(String::from("foo")), Token::CloseBrace,]),
+            Some(Node::Program(vec![Node::Set(vec![Node::Number(String::from("1")), Node::Identifier(String::from("foo")),]),],),)
+        );
+    }
+
+    #[test]
+    fn single_element_set_literal() {
+        assert_eq!(parse(vec![Token::OpenBrace, Token::Identifier(String::from("foo")), Token::CloseBrace,]), Some(Node::Program(vec![Node::Set(vec![Node::Identifier(String::from("foo")),]),],),));
+    }
+
+    #[test]
+    fn map_literal_mixed_with_set_elements() {
+        assert_eq!(
+            parse(vec![
+                Token::OpenBrace,
+                Token::Identifier(String::from("foo")),
+                Token::Colon,
+                Token::Number(String::from("1")),
+                Token::Comma,
+                Token::Identifier(String::from("bar")),
+                Token::CloseBrace,
+            ]),
+            None
+        );
+    }
+
+    #[test]
+    fn map_literal_as_argument() {
+        assert_eq!(
+            parse(vec![
+                Token::Identifier(String::from("foo")),
+                Token::OpenParenthesis,
+                Token::OpenBrace,
+                Token::Number(String::from("1")),
+                Token::Colon,
+                Token::Number(String::from("2")),
+                Token::CloseBrace,
+                Token::CloseParenthesis,
+            ]),
+            Some(Node::Program(vec![Node::Call(
+                Box::new(Node::Identifier(String::from("foo"))),
+                vec![Node::Map(vec![Node::KeyValue(Box::new(Node::Number(String::from("1"))), Box::new(Node::Number(String::from("2")))),]),],
+            ),],),)
+        );
+    }
+
+    #[test]
+    fn prefixed_string_literal() {
+        assert_eq!(
+            parse(vec![Token::Prefix(String::from("f")), Token::Literal(String::from("Hello {name}!"), '\''),]),
+            Some(Node::Program(vec![Node::Prefixed(
+                Box::new(Node::Identifier(String::from("f"))),
+                Box::new(Node::Template(vec![
+                    Node::Literal(String::from("Hello "), '\''),
+                    Node::Identifier(String::from("name")),
+                    Node::Literal(String::from("!"), '\''),
+                ])),
+            ),],),)
+        );
+    }
+
+    #[test]
+    fn prefixed_string_literal_with_escaped_braces_and_expression() {
+        assert_eq!(
+            parse(vec![Token::Prefix(String::from("f")), Token::Literal(String::from("{{{foo + 1}}}"), '"'),]),
+            Some(Node::Program(vec![Node::Prefixed(
+                Box::new(Node::Identifier(String::from("f"))),
+                Box::new(Node::Template(vec![
+                    Node::Literal(String::from("{"), '"'),
+                    Node::Addition(Box::new(Node::Identifier(String::from("foo"))), Box::new(Node::Number(String::from("1")))),
+                    Node::Literal(String::from("}"), '"'),
+                ])),
+            ),],),)
+        );
+    }
+
+    #[test]
+    fn prefixed_string_literal_with_unbalanced_brace() {
+        assert_eq!(parse(vec![Token::Prefix(String::from("f")), Token::Literal(String::from("{foo"), '\''),]), None);
+    }
+
+    #[test]
+    fn prefixed_map_literal() {
+        assert_eq!(
+            parse(vec![Token::Prefix(String::from("ordered")), Token::OpenBrace, Token::Number(String::from("1")), Token::Colon, Token::Number(String::from("2")), Token::CloseBrace,]),
+            Some(Node::Program(vec![Node::Prefixed(
+                Box::new(Node::Identifier(String::from("ordered"))),
+                Box::new(Node::Map(vec![Node::KeyValue(Box::new(Node::Number(String::from("1"))), Box::new(Node::Number(String::from("2")))),])),
+            ),],),)
+        );
+    }
+
+    #[test]
+    fn index_with_multiple_elements() {
+        assert_eq!(
+            parse(vec![
+                Token::Identifier(String::from("set")),
+                Token::OpenBracket,
+                Token::Number(String::from("1")),
+                Token::Comma,
+                Token::Number(String::from("2")),
+                Token::CloseBracket,
+            ]),
+            Some(Node::Program(vec![Node::Index(
+                Box::new(Node::Identifier(String::from("set"))),
+                Box::new(Node::FunctionArgs(vec![Node::Number(String::from("1")), Node::Number(String::from("2")),])),
+            ),],),)
+        );
+    }
+
+    #[test]
+    fn compound_assignment() {
+        assert_eq!(
+            parse(vec![Token::Identifier(String::from("x")), Token::PlusEqual, Token::Identifier(String::from("y")), Token::Times, Token::Number(String::from("2")),]),
+            Some(Node::Program(vec![Node::CompoundAssignment(
+                Box::new(Node::Identifier(String::from("x"))),
+                String::from("+"),
+                Box::new(Node::Multiplication(Box::new(Node::Identifier(String::from("y"))), Box::new(Node::Number(String::from("2"))))),
+            ),],),)
+        );
+    }
+
+    #[test]
+    fn compound_assignment_of_member() {
+        assert_eq!(
+            parse(vec![
+                Token::Identifier(String::from("self")),
+                Token::Period,
+                Token::Identifier(String::from("total")),
+                Token::MinusEqual,
+                Token::Identifier(String::from("x")),
+            ]),
+            Some(Node::Program(vec![Node::CompoundAssignment(
+                Box::new(Node::Member(Box::new(Node::Identifier(String::from("self"))), String::from("total"))),
+                String::from("-"),
+                Box::new(Node::Identifier(String::from("x"))),
+            ),],),)
+        );
+    }
+
+    #[test]
+    fn compound_assignment_of_index() {
+        assert_eq!(
+            parse(vec![
+                Token::Identifier(String::from("foo")),
+                Token::OpenBracket,
+                Token::Number(String::from("0")),
+                Token::CloseBracket,
+                Token::ModulusEqual,
+                Token::Number(String::from("2")),
+            ]),
+            Some(Node::Program(vec![Node::CompoundAssignment(
+                Box::new(Node::Index(Box::new(Node::Identifier(String::from("foo"))), Box::new(Node::Number(String::from("0"))))),
+                String::from("%"),
+                Box::new(Node::Number(String::from("2"))),
+            ),],),)
+        );
+    }
+
+    #[test]
+    fn negation_binds_tighter_than_modulus() {
+        assert_eq!(
+            parse(vec![Token::Minus, Token::Number(String::from("7")), Token::Modulus, Token::Number(String::from("3")),]),
+            Some(Node::Program(vec![Node::Modulus(Box::new(Node::Negation(Box::new(Node::Number(String::from("7"))))), Box::new(Node::Number(String::from("3"))),),],),)
+        );
+    }
+
+    #[test]
+    fn method_call_on_index_of_member() {
+        assert_eq!(
+            parse(vec![
+                Token::Identifier(String::from("obj")),
+                Token::Period,
+                Token::Identifier(String::from("items")),
+                Token::OpenBracket,
+                Token::Number(String::from("0")),
+                Token::CloseBracket,
+                Token::Period,
+                Token::Identifier(String::from("name")),
+                Token::OpenParenthesis,
+                Token::CloseParenthesis,
+            ]),
+            Some(Node::Program(vec![Node::Call(
+                Box::new(Node::Member(
+                    Box::new(Node::Index(
+                        Box::new(Node::Member(Box::new(Node::Identifier(String::from("obj"))), String::from("items"))),
+                        Box::new(Node::Number(String::from("0"))),
+                    )),
+                    String::from("name"),
+                )),
+                vec![],
+            ),],),)
+        );
+    }
+
+    #[test]
+    fn index_of_call() {
+        assert_eq!(
+            parse(vec![
+                Token::Identifier(String::from("foo")),
+                Token::OpenParenthesis,
+                Token::CloseParenthesis,
+                Token::OpenBracket,
+                Token::Number(String::from("1")),
+                Token::Colon,
+                Token::CloseBracket,
+            ]),
+            Some(Node::Program(vec![Node::Index(
+                Box::new(Node::Call(Box::new(Node::Identifier(String::from("foo"))), vec![])),
+                Box::new(Node::Slice(Box::new(Node::Number(String::from("1"))), Box::new(Node::Empty))),
+            ),],),)
+        );
+    }
+
+    #[test]
+    fn index_assignment_of_member() {
+        assert_eq!(
+            parse(vec![
+                Token::Identifier(String::from("self")),
+                Token::Period,
+                Token::Identifier(String::from("cells")),
+                Token::OpenBracket,
+                Token::Identifier(String::from("index")),
+                Token::CloseBracket,
+                Token::Equal,
+                Token::Identifier(String::from("value")),
+            ]),
+            Some(Node::Program(vec![Node::Assignment(
+                Box::new(Node::Index(
+                    Box::new(Node::Member(Box::new(Node::Identifier(String::from("self"))), String::from("cells"))),
+                    Box::new(Node::Identifier(String::from("index"))),
+                )),
+                Box::new(Node::Identifier(String::from("value"))),
+            ),],),)
+        );
+    }
+
+    #[test]
+    fn call_on_call_result() {
+        assert_eq!(
+            parse(vec![
+                Token::Identifier(String::from("f")),
+                Token::OpenParenthesis,
+                Token::CloseParenthesis,
+                Token::OpenParenthesis,
+                Token::Number(String::from("1")),
+                Token::CloseParenthesis,
+            ]),
+            Some(Node::Program(vec![Node::Call(
+                Box::new(Node::Call(Box::new(Node::Identifier(String::from("f"))), vec![])),
+                vec![Node::Number(String::from("1"))],
+            ),],),)
+        );
+    }
+
+    #[test]
+    fn member_of_call_result() {
+        assert_eq!(
+            parse(vec![
+                Token::Identifier(String::from("make")),
+                Token::OpenParenthesis,
+                Token::CloseParenthesis,
+                Token::Period,
+                Token::Identifier(String::from("field")),
+            ]),
+            Some(Node::Program(vec![Node::Member(
+                Box::new(Node::Call(Box::new(Node::Identifier(String::from("make"))), vec![])),
+                String::from("field"),
+            ),],),)
+        );
+    }
+
+    #[test]
+    fn chained_method_calls() {
+        assert_eq!(
+            parse(vec![
+                Token::Identifier(String::from("a")),
+                Token::Period,
+                Token::Identifier(String::from("b")),
+                Token::OpenParenthesis,
+                Token::CloseParenthesis,
+                Token::Period,
+                Token::Identifier(String::from("c")),
+                Token::OpenParenthesis,
+                Token::CloseParenthesis,
+                Token::OpenBracket,
+                Token::Number(String::from("0")),
+                Token::CloseBracket,
+                Token::Period,
+                Token::Identifier(String::from("d")),
+            ]),
+            Some(Node::Program(vec![Node::Member(
+                Box::new(Node::Index(
+                    Box::new(Node::Call(
+                        Box::new(Node::Member(
+                            Box::new(Node::Call(Box::new(Node::Member(Box::new(Node::Identifier(String::from("a"))), String::from("b"))), vec![])),
+                            String::from("c"),
+                        )),
+                        vec![],
+                    )),
+                    Box::new(Node::Number(String::from("0"))),
+                )),
+                String::from("d"),
+            ),],),)
+        );
+    }
+
+    #[test]
+    fn member_of_parenthesized() {
+        assert_eq!(
+            parse(vec![
+                Token::OpenParenthesis,
+                Token::Identifier(String::from("a")),
+                Token::CloseParenthesis,
+                Token::Period,
+                Token::Identifier(String::from("b")),
+            ]),
+            Some(Node::Program(vec![Node::Member(Box::new(Node::Parenthesized(Box::new(Node::Identifier(String::from("a"))))), String::from("b")),],),)
+        );
+    }
+
+    #[test]
+    fn call_result_in_addition() {
+        assert_eq!(
+            parse(vec![
+                Token::Identifier(String::from("f")),
+                Token::OpenParenthesis,
+                Token::CloseParenthesis,
+                Token::Plus,
+                Token::Number(String::from("1")),
+            ]),
+            Some(Node::Program(vec![Node::Addition(
+                Box::new(Node::Call(Box::new(Node::Identifier(String::from("f"))), vec![])),
+                Box::new(Node::Number(String::from("1"))),
+            ),],),)
+        );
+    }
+
+    #[test]
+    fn substraction_is_left_associative() {
+        assert_eq!(
+            parse(vec![
+                Token::Identifier(String::from("a")),
+                Token::Minus,
+                Token::Identifier(String::from("b")),
+                Token::Minus,
+                Token::Identifier(String::from("c")),
+            ]),
+            Some(Node::Program(vec![Node::Substraction(
+                Box::new(Node::Substraction(Box::new(Node::Identifier(String::from("a"))), Box::new(Node::Identifier(String::from("b"))))),
+                Box::new(Node::Identifier(String::from("c"))),
+            ),],),)
+        );
+    }
+
+    #[test]
+    fn function_definition_default_and_variadic_args() {
+        assert_eq!(
+            parse(vec![
+                Token::OpenParenthesis,
+                Token::Identifier(String::from("a")),
+                Token::Comma,
+                Token::Identifier(String::from("b")),
+                Token::Equal,
+                Token::Number(String::from("2")),
+                Token::Comma,
+                Token::Times,
+                Token::Identifier(String::from("rest")),
+                Token::CloseParenthesis,
+                Token::OpenBrace,
+                Token::CloseBrace,
+            ]),
+            Some(Node::Program(vec![Node::FunctionDefinition(
+                vec![
+                    Node::Identifier(String::from("a")),
+                    Node::Assignment(Box::new(Node::Identifier(String::from("b"))), Box::new(Node::Number(String::from("2")))),
+                    Node::Variadic(Box::new(Node::Identifier(String::from("rest")))),
+                ],
+                vec![],
+            ),],),)
+        );
+    }
+
+    #[test]
+    fn function_call_named_and_spread_args() {
+        assert_eq!(
+            parse(vec![
+                Token::Identifier(String::from("f")),
+                Token::OpenParenthesis,
+                Token::Times,
+                Token::Identifier(String::from("args")),
+                Token::Comma,
+                Token::Identifier(String::from("b")),
+                Token::Equal,
+                Token::Number(String::from("2")),
+                Token::CloseParenthesis,
+            ]),
+            Some(Node::Program(vec![Node::Call(
+                Box::new(Node::Identifier(String::from("f"))),
+                vec![
+                    Node::Variadic(Box::new(Node::Identifier(String::from("args")))),
+                    Node::Assignment(Box::new(Node::Identifier(String::from("b"))), Box::new(Node::Number(String::from("2")))),
+                ],
+            ),],),)
+        );
+    }
+
+    #[test]
+    fn tuple_assignment() {
+        assert_eq!(
+            parse(vec![
+                Token::Identifier(String::from("a")),
+                Token::Comma,
+                Token::Identifier(String::from("b")),
+                Token::Equal,
+                Token::Identifier(String::from("b")),
+                Token::Comma,
+                Token::Identifier(String::from("a")),
+            ]),
+            Some(Node::Program(vec![Node::Assignment(
+                Box::new(Node::Tuple(vec![Node::Identifier(String::from("a")), Node::Identifier(String::from("b"))])),
+                Box::new(Node::Tuple(vec![Node::Identifier(String::from("b")), Node::Identifier(String::from("a"))])),
+            ),],),)
+        );
+    }
+
+    #[test]
+    fn array_pattern_assignment() {
+        assert_eq!(
+            parse(vec![
+                Token::OpenBracket,
+                Token::Identifier(String::from("first")),
+                Token::Comma,
+                Token::Times,
+                Token::Identifier(String::from("rest")),
+                Token::CloseBracket,
+                Token::Equal,
+                Token::Identifier(String::from("list")),
+            ]),
+            Some(Node::Program(vec![Node::Assignment(
+                Box::new(Node::Array(vec![
+                    Node::Identifier(String::from("first")),
+                    Node::Variadic(Box::new(Node::Identifier(String::from("rest")))),
+                ])),
+                Box::new(Node::Identifier(String::from("list"))),
+            ),],),)
+        );
+    }
+
+    #[test]
+    fn map_pattern_assignment() {
+        assert_eq!(
+            parse(vec![
+                Token::OpenBrace,
+                Token::Literal(String::from("x"), '\''),
+                Token::Colon,
+                Token::Identifier(String::from("x")),
+                Token::CloseBrace,
+                Token::Equal,
+                Token::Identifier(String::from("point")),
+            ]),
+            Some(Node::Program(vec![Node::Assignment(
+                Box::new(Node::Map(vec![Node::KeyValue(
+                    Box::new(Node::Literal(String::from("x"), '\'')),
+                    Box::new(Node::Identifier(String::from("x"))),
+                )])),
+                Box::new(Node::Identifier(String::from("point"))),
+            ),],),)
+        );
+    }
+
+    #[test]
+    fn tuple_assignment_in_function_body() {
+        assert_eq!(
+            parse(vec![
+                Token::OpenParenthesis,
+                Token::CloseParenthesis,
+                Token::OpenBrace,
+                Token::Identifier(String::from("a")),
+                Token::Comma,
+                Token::Identifier(String::from("b")),
+                Token::Equal,
+                Token::Identifier(String::from("c")),
+                Token::CloseBrace,
+            ]),
+            Some(Node::Program(vec![Node::FunctionDefinition(
+                vec![],
+                vec![Node::Assignment(
+                    Box::new(Node::Tuple(vec![Node::Identifier(String::from("a")), Node::Identifier(String::from("b"))])),
+                    Box::new(Node::Identifier(String::from("c"))),
+                )],
+            ),],),)
+        );
+    }
+
+    #[test]
+    fn incomplete_function_definition() {
+        assert!(super::parser::parser::is_incomplete(vec![
+            Token::Identifier(String::from("f")),
+            Token::Equal,
+            Token::OpenParenthesis,
+            Token::Identifier(String::from("x")),
+            Token::CloseParenthesis,
+            Token::OpenBrace,
+        ]));
+    }
+
+    #[test]
+    fn incomplete_operators() {
+        assert!(super::parser::parser::is_incomplete(vec![Token::Identifier(String::from("x")), Token::Equal]));
+        assert!(super::parser::parser::is_incomplete(vec![Token::Identifier(String::from("x")), Token::Plus]));
+        assert!(!super::parser::parser::is_incomplete(vec![
+            Token::Identifier(String::from("x")),
+            Token::Plus,
+            Token::Number(String::from("1")),
+        ]));
+        assert!(!super::parser::parser::is_incomplete(vec![Token::Identifier(String::from("f")), Token::OpenParenthesis, Token::CloseParenthesis]));
+    }
+
+    #[test]
+    fn statement_spans() {
+        use super::lexer::span::{Position, Span};
+        let span = |start: usize, end: usize| Span {
+            start: Position { line: 1, column: start + 1, offset: start },
+            end: Position { line: 1, column: end + 1, offset: end },
+        };
+        assert_eq!(
+            super::parser::parser::parse_spanned(vec![
+                (Token::Identifier(String::from("x")), span(0, 1)),
+                (Token::Equal, span(2, 3)),
+                (Token::Number(String::from("1")), span(4, 5)),
+                (Token::SemiColon, span(5, 6)),
+                (Token::Identifier(String::from("y")), span(7, 8)),
+            ]),
+            Some(vec![
+                (Node::Assignment(Box::new(Node::Identifier(String::from("x"))), Box::new(Node::Number(String::from("1")))), span(0, 5)),
+                (Node::Identifier(String::from("y")), span(7, 8)),
+            ]),
+        );
+    }
+
+    #[test]
+    fn nested_node_spans() {
+        let tokens = super::lexer::lexer::lex_spanned("x = 1\nitems[1:] = -(a + b)").unwrap();
+        let statements = super::parser::parser::parse_spanned(tokens.to_vec()).unwrap();
+        let spans = super::parser::parser::node_spans(&statements, &tokens);
+        let offsets = |node: &Node| spans.get(&(node as *const Node)).map(|span| (span.start.offset, span.end.offset));
+        if let Node::Assignment(target, value) = &statements[1].0 {
+            assert_eq!(offsets(&statements[1].0), Some((6, 26)));
+            assert_eq!(offsets(target), Some((6, 15)));
+            assert_eq!(offsets(value), Some((18, 26)));
+            if let Node::Index(object, slice) = &**target {
+                assert_eq!(offsets(object), Some((6, 11)));
+                assert_eq!(offsets(slice), Some((12, 14)));
+            } else {
+                panic!("{:?}", target);
+            }
+        } else {
+            panic!("{:?}", statements[1].0);
+        }
+    }
+
+    #[test]
+    fn formatting_keeps_comments() {
+        let code = "# header\nx=1+2 # trailing\nf = (a,b) {\n# inside\na*b }\n";
+        let formatted = super::parser::format::format(code);
+        assert_eq!(formatted.as_deref(), Some("# header\nx = 1 + 2  # trailing\nf = (a, b) {\n  # inside\n  a * b\n}\n"));
+        assert_eq!(super::parser::format::format(&formatted.unwrap()).as_deref(), Some("# header\nx = 1 + 2  # trailing\nf = (a, b) {\n  # inside\n  a * b\n}\n"));
+    }
+
+    #[test]
+    fn rest_elements_after_the_name() {
+        let tokens = super::lexer::lexer::lex("[first, rest...] = list").unwrap();
+        assert_eq!(tokens[4], Token::Ellipsis);
+        assert_eq!(
+            parse(tokens),
+            Some(Node::Program(vec![Node::Assignment(
+                Box::new(Node::Array(vec![
+                    Node::Identifier(String::from("first")),
+                    Node::Variadic(Box::new(Node::Identifier(String::from("rest")))),
+                ])),
+                Box::new(Node::Identifier(String::from("list"))),
+            )]))
+        );
+        let spelled = |code: &str| super::lexer::lexer::lex(code).and_then(super::parser::parser::parse);
+        assert_eq!(spelled("a, rest... = 1, 2, 3"), spelled("a, *rest = 1, 2, 3"));
+        assert_eq!(spelled("f = (a, rest...) { rest }"), spelled("f = (a, *rest) { rest }"));
+        assert_eq!(super::lexer::lexer::lex("a. .."), Some(vec![Token::Identifier(String::from("a")), Token::Period, Token::Period, Token::Period]));
+    }
+
+    #[test]
+    fn match_arm_patterns() {
+        let parsed = parse(super::lexer::lexer::lex("shape: {\n  Shape.Circle(r): { r }\n  0: { 0 }\n  'none': { 1 }\n  [a, b]: { a }\n}").unwrap());
+        let arms = match parsed {
+            Some(Node::Program(mut statements)) => match statements.remove(0) {
+                Node::Match(_subject, arms) => arms,
+                statement => panic!("{:?}", statement),
+            },
+            tree => panic!("{:?}", tree),
+        };
+        let patterns: Vec<&str> = arms
+            .iter()
+            .map(|arm| match arm {
+                Node::MatchArm(pattern, _body) => pattern.kind(),
+                arm => arm.kind(),
+            })
+            .collect();
+        assert_eq!(patterns, vec!["Call", "Number", "Literal", "Array"]);
+    }
+
+    // Syntax errors are None, whatever tokens the parser is given.
+    #[test]
+    fn syntax_errors_do_not_panic() {
+        for code in ["x: { 1 }", "x: { a: b }", "x: {\n  a: { 1 } =\n}", "enum E { 1 }", "class A { a = 1, ) }", "f({)", "[f(}]", "{f(]}", "-+", "x.1", "(,", "() { = }"] {
+            let _ = super::lexer::lexer::lex(code).and_then(super::parser::parser::parse);
+        }
+        assert_eq!(super::lexer::lexer::lex("enum E { 1 }").and_then(super::parser::parser::parse), None);
+        assert_eq!(super::lexer::lexer::lex("x: { a: b }").and_then(super::parser::parser::parse), None);
+        let alphabet = vec![
+            Token::Identifier(String::from("a")),
+            Token::Identifier(String::from("class")),
+            Token::Identifier(String::from("enum")),
+            Token::Prefix(String::from("p")),
+            Token::Number(String::from("1")),
+            Token::Literal(String::from("s"), '\''),
+            Token::Equal,
+            Token::Plus,
+            Token::Minus,
+            Token::Times,
+            Token::PlusEqual,
+            Token::Comma,
+            Token::Colon,
+            Token::Period,
+            Token::Ellipsis,
+            Token::SemiColon,
+            Token::NewLine,
+            Token::OpenParenthesis,
+            Token::CloseParenthesis,
+            Token::OpenBrace,
+            Token::CloseBrace,
+            Token::OpenBracket,
+            Token::CloseBracket,
+        ];
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        for _ in 0..20000 {
+            let length = 1 + state as usize % 12;
+            let tokens: Vec<Token> = (0..length)
+                .map(|_| {
+                    state ^= state << 13;
+                    state ^= state >> 7;
+                    state ^= state << 17;
+                    alphabet[state as usize % alphabet.len()].to_owned()
+                })
+                .collect();
+            let tree = super::parser::parser::parse(tokens.clone());
+            let _ = super::parser::parser::is_incomplete(tokens);
+            if let Some(tree) = tree {
+                let _ = super::parser::format::format_node(&tree);
+            }
+        }
+    }
+
+    #[test]
+    fn format_node_refuses_unfinished_statements() {
+        for code in ["x = (", "x +", "f = (x) {", "a.", "enum Color", "enum Color {", "class Point", "x: ", "x: {", "[1, 2", "f(1,", "{'a': 1", "a, b ="] {
+            let tree = super::lexer::lexer::lex(code).and_then(super::parser::parser::parse).unwrap_or_else(|| panic!("{} does not parse", code));
+            assert_eq!(super::parser::format::format_node(&tree), None, "{} was formatted", code);
+        }
+    }
 }